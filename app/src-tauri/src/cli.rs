@@ -0,0 +1,601 @@
+//! 无界面的命令行模式
+//!
+//! `project-graph <子命令> ...` 在创建任何窗口、初始化 CEF 之前执行并直接退出，
+//! 用于在 CI 等没有显示器的环境中检查、转换 .prg 文件。
+//! 第一个参数不是子命令时返回 None，继续走原来由前端处理的命令行参数（打开文件、导出等）。
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cmd::diff;
+use crate::cmd::merge;
+use crate::cmd::prg::{
+    build_project, mime_from_ext, PrgAttachmentInfo, PrgAttachmentInput, PrgMetadata, PrgProject,
+    PrgReferences, PrgSaveRequest,
+};
+use crate::cmd::stage;
+
+const SUBCOMMANDS: &[&str] = &[
+    "info",
+    "extract-text",
+    "to-json",
+    "from-json",
+    "list-attachments",
+    "extract-attachments",
+    "diff",
+    "textconv",
+    "merge",
+    "help",
+];
+
+const USAGE: &str = "\
+用法: project-graph <子命令> [选项] <文件>
+
+子命令:
+  info <file.prg>                   显示版本、舞台对象、附件等信息
+  extract-text <file.prg>           输出所有节点中的文字
+  to-json <file.prg> [-o out.json]  转换为 JSON，附件以 base64 内嵌
+  from-json <in.json|-> -o <out.prg>
+                                    从 JSON 生成 .prg
+  list-attachments <file.prg>       列出附件
+  extract-attachments <file.prg> [-o 目录]
+                                    把附件解压到目录中（默认当前目录）
+  diff <old.prg> <new.prg>          对比两个文件：节点增删、文字修改、移动、连线重连等
+                                    也可以作为 git 的 diff.<driver>.command 使用
+  textconv <file.prg>               输出稳定的纯文本，用作 git 的 diff.<driver>.textconv
+  merge <base.prg> <ours.prg> <theirs.prg> [-o out.prg]
+                                    三方合并，默认写回 ours，有冲突时退出码为 1
+                                    可以作为 git 的 merge.<driver>.driver 使用：
+                                    project-graph merge %O %A %B
+  help                              显示这段帮助
+
+选项:
+  --json                      info、extract-text、list-attachments、diff 以 JSON 输出
+  -o, --output <路径>         输出位置，为 - 时输出到标准输出
+  --no-attachment-data        to-json 时只输出附件的描述信息，不内嵌内容
+  --attachments-from <.prg>   from-json 时，没有内容的附件从这个文件中复制
+";
+
+enum CliError {
+    /// 参数错误，退出码 2
+    Usage(String),
+    /// 执行失败，退出码 1
+    Failed(String),
+    /// 合并完成但有冲突，退出码 1（git 合并驱动以此判断是否需要手动处理）
+    Conflicts(usize),
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Failed(message)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    inputs: Vec<String>,
+    output: Option<String>,
+    json: bool,
+    no_attachment_data: bool,
+    attachments_from: Option<String>,
+    help: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, CliError> {
+    let mut parsed = Args {
+        command: args[0].clone(),
+        ..Default::default()
+    };
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| CliError::Usage(format!("{} 需要一个值", name)))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => parsed.output = Some(value(arg)?),
+            "--attachments-from" => parsed.attachments_from = Some(value(arg)?),
+            "--json" => parsed.json = true,
+            "--no-attachment-data" => parsed.no_attachment_data = true,
+            "-h" | "--help" => parsed.help = true,
+            "-" => parsed.inputs.push(arg.clone()),
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("未知的选项: {}", flag)))
+            }
+            _ => parsed.inputs.push(arg.clone()),
+        }
+    }
+    Ok(parsed)
+}
+
+impl Args {
+    /// 唯一的输入文件
+    fn input(&self) -> Result<&str, CliError> {
+        match self.inputs.as_slice() {
+            [input] => Ok(input),
+            [] => Err(CliError::Usage(format!(
+                "{} 需要一个输入文件",
+                self.command
+            ))),
+            _ => Err(CliError::Usage(format!(
+                "{} 只接受一个输入文件",
+                self.command
+            ))),
+        }
+    }
+
+    fn open(&self) -> Result<PrgProject, CliError> {
+        Ok(PrgProject::open(Path::new(self.input()?))?)
+    }
+}
+
+/// info 的输出
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrgInfo {
+    version: String,
+    /// 各类舞台对象的数量
+    objects: BTreeMap<String, usize>,
+    tags: usize,
+    attachments: usize,
+    attachment_bytes: u64,
+    has_thumbnail: bool,
+    has_readme: bool,
+    referenced_sections: usize,
+    referenced_by_files: usize,
+}
+
+/// to-json / from-json 使用的格式
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonProject {
+    #[serde(default)]
+    metadata: PrgMetadata,
+    stage: Vec<Value>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    references: PrgReferences,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    readme: Option<String>,
+    #[serde(default)]
+    attachments: Vec<JsonAttachment>,
+    /// base64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonAttachment {
+    id: String,
+    ext: String,
+    #[serde(default)]
+    size: u64,
+    /// base64，为空时需要通过 --attachments-from 指定来源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+}
+
+fn decode_base64(name: &str, data: &str) -> Result<Vec<u8>, CliError> {
+    general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| CliError::Failed(format!("{} 不是有效的 base64: {}", name, e)))
+}
+
+fn write_json<T: Serialize>(out: &mut dyn Write, value: &T) -> Result<(), CliError> {
+    serde_json::to_writer_pretty(&mut *out, value)
+        .map_err(|e| CliError::Failed(format!("无法输出 JSON: {}", e)))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn info(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = args.open()?;
+    let mut objects = BTreeMap::new();
+    stage::for_each_object(&project.stage, |object| {
+        if let Some(class) = stage::class_name(object) {
+            *objects.entry(class.to_string()).or_insert(0) += 1;
+        }
+    });
+    let info = PrgInfo {
+        version: project.metadata.version.clone(),
+        objects,
+        tags: project.tags.len(),
+        attachments: project.attachments.len(),
+        attachment_bytes: project
+            .attachments
+            .iter()
+            .map(|attachment| attachment.data.len() as u64)
+            .sum(),
+        has_thumbnail: project.thumbnail.is_some(),
+        has_readme: project.readme.is_some(),
+        referenced_sections: project.references.sections.len(),
+        referenced_by_files: project.references.files.len(),
+    };
+    if args.json {
+        return write_json(out, &info);
+    }
+    let yes_no = |value: bool| if value { "有" } else { "无" };
+    let total: usize = info.objects.values().sum();
+    let detail: Vec<String> = info
+        .objects
+        .iter()
+        .map(|(class, count)| format!("{} {}", class, count))
+        .collect();
+    writeln!(out, "版本: {}", info.version)?;
+    writeln!(out, "舞台对象: {} ({})", total, detail.join(", "))?;
+    writeln!(out, "标签: {}", info.tags)?;
+    writeln!(
+        out,
+        "附件: {} ({} 字节)",
+        info.attachments, info.attachment_bytes
+    )?;
+    writeln!(out, "缩略图: {}", yes_no(info.has_thumbnail))?;
+    writeln!(out, "README: {}", yes_no(info.has_readme))?;
+    writeln!(
+        out,
+        "引用: {} 个 Section, {} 个文件",
+        info.referenced_sections, info.referenced_by_files
+    )?;
+    Ok(())
+}
+
+fn extract_text(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = args.open()?;
+    let items = stage::text_items(&project.stage);
+    if args.json {
+        let items: Vec<Value> = items
+            .into_iter()
+            .map(|(uuid, text)| serde_json::json!({ "uuid": uuid, "text": text }))
+            .collect();
+        return write_json(out, &items);
+    }
+    // 每个对象的文字之间空一行
+    for (i, (_, text)) in items.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{}", text)?;
+    }
+    Ok(())
+}
+
+fn to_json(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = args.open()?;
+    let with_data = !args.no_attachment_data;
+    let json = JsonProject {
+        metadata: project.metadata,
+        stage: project.stage,
+        tags: project.tags,
+        references: project.references,
+        readme: project.readme,
+        attachments: project
+            .attachments
+            .into_iter()
+            .map(|attachment| JsonAttachment {
+                size: attachment.data.len() as u64,
+                data: with_data.then(|| general_purpose::STANDARD.encode(&attachment.data)),
+                id: attachment.id,
+                ext: attachment.ext,
+            })
+            .collect(),
+        thumbnail: project
+            .thumbnail
+            .filter(|_| with_data)
+            .map(|thumbnail| general_purpose::STANDARD.encode(thumbnail)),
+    };
+    match args.output.as_deref() {
+        None | Some("-") => write_json(out, &json),
+        Some(output) => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(output)?);
+            write_json(&mut file, &json)?;
+            file.flush()?;
+            Ok(())
+        }
+    }
+}
+
+fn from_json(args: &Args, _out: &mut dyn Write) -> Result<(), CliError> {
+    let output = args
+        .output
+        .as_deref()
+        .filter(|output| *output != "-")
+        .ok_or_else(|| CliError::Usage("from-json 需要用 -o 指定输出的 .prg 文件".to_string()))?;
+    let input = args.input()?;
+    let content = if input == "-" {
+        let mut content = Vec::new();
+        std::io::stdin().read_to_end(&mut content)?;
+        content
+    } else {
+        std::fs::read(input)?
+    };
+    let json: JsonProject = serde_json::from_slice(&content)
+        .map_err(|e| CliError::Failed(format!("无法解析 JSON: {}", e)))?;
+
+    let mut attachments = Vec::with_capacity(json.attachments.len());
+    for attachment in json.attachments {
+        let data = match &attachment.data {
+            Some(data) => Some(decode_base64(&attachment.id, data)?),
+            None => None,
+        };
+        attachments.push(PrgAttachmentInput {
+            id: attachment.id,
+            ext: attachment.ext,
+            data,
+        });
+    }
+    let thumbnail = match &json.thumbnail {
+        Some(data) => Some(decode_base64("thumbnail", data)?),
+        None => None,
+    };
+    let project = build_project(PrgSaveRequest {
+        stage: json.stage,
+        tags: json.tags,
+        references: json.references,
+        metadata: json.metadata,
+        readme: json.readme,
+        attachments,
+        thumbnail,
+        copy_from: args.attachments_from.clone(),
+    })?;
+    project.save(Path::new(output))?;
+    Ok(())
+}
+
+fn list_attachments(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = args.open()?;
+    let attachments: Vec<PrgAttachmentInfo> = project
+        .attachments
+        .iter()
+        .map(|attachment| PrgAttachmentInfo {
+            id: attachment.id.clone(),
+            ext: attachment.ext.clone(),
+            mime: mime_from_ext(&attachment.ext).to_string(),
+            size: attachment.data.len() as u64,
+        })
+        .collect();
+    if args.json {
+        return write_json(out, &attachments);
+    }
+    for attachment in attachments {
+        writeln!(
+            out,
+            "{}.{}\t{}\t{}",
+            attachment.id, attachment.ext, attachment.size, attachment.mime
+        )?;
+    }
+    Ok(())
+}
+
+fn extract_attachments(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = args.open()?;
+    let dir = PathBuf::from(args.output.as_deref().unwrap_or("."));
+    std::fs::create_dir_all(&dir)?;
+    for attachment in &project.attachments {
+        let path = dir.join(format!("{}.{}", attachment.id, attachment.ext));
+        std::fs::write(&path, &attachment.data)?;
+        writeln!(out, "{}", path.display())?;
+    }
+    Ok(())
+}
+
+fn diff_files(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    // git 的外部 diff 命令会传入 7 个参数：path old-file old-hex old-mode new-file new-hex new-mode
+    let (old, new) = match args.inputs.as_slice() {
+        [old, new] => (old, new),
+        [path, old, _, _, new, _, _] => {
+            writeln!(out, "diff --prg a/{} b/{}", path, path)?;
+            (old, new)
+        }
+        _ => return Err(CliError::Usage("diff 需要两个输入文件".to_string())),
+    };
+    let result = diff::diff(
+        &diff::open_or_empty(Path::new(old))?,
+        &diff::open_or_empty(Path::new(new))?,
+    );
+    if args.json {
+        return write_json(out, &result);
+    }
+    out.write_all(diff::render_diff(&result).as_bytes())?;
+    Ok(())
+}
+
+fn textconv(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let project = diff::open_or_empty(Path::new(args.input()?))?;
+    out.write_all(diff::textconv(&project).as_bytes())?;
+    Ok(())
+}
+
+fn merge_files(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    let [base, ours, theirs] = args.inputs.as_slice() else {
+        return Err(CliError::Usage(
+            "merge 需要 base、ours、theirs 三个输入文件".to_string(),
+        ));
+    };
+    let output = match args.output.as_deref() {
+        Some("-") => return Err(CliError::Usage("merge 不能输出到标准输出".to_string())),
+        Some(output) => output,
+        None => ours,
+    };
+    let result = merge::merge_files(
+        Path::new(base),
+        Path::new(ours),
+        Path::new(theirs),
+        Path::new(output),
+    )?;
+    if args.json {
+        write_json(out, &result)?;
+    } else {
+        for conflict in &result.conflicts {
+            writeln!(out, "冲突: 「{}」{}", conflict.label, conflict.reason)?;
+        }
+    }
+    match result.conflicts.len() {
+        0 => Ok(()),
+        count => Err(CliError::Conflicts(count)),
+    }
+}
+
+fn execute(args: &Args, out: &mut dyn Write) -> Result<(), CliError> {
+    if args.help || args.command == "help" {
+        out.write_all(USAGE.as_bytes())?;
+        return Ok(());
+    }
+    match args.command.as_str() {
+        "info" => info(args, out),
+        "extract-text" => extract_text(args, out),
+        "to-json" => to_json(args, out),
+        "from-json" => from_json(args, out),
+        "list-attachments" => list_attachments(args, out),
+        "extract-attachments" => extract_attachments(args, out),
+        "diff" => diff_files(args, out),
+        "textconv" => textconv(args, out),
+        "merge" => merge_files(args, out),
+        command => Err(CliError::Usage(format!("未知的子命令: {}", command))),
+    }
+}
+
+/// Windows 发布版是 GUI 程序，没有控制台，需要附加到启动它的终端上才能输出
+#[cfg(windows)]
+fn attach_console() {
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// 第一个参数是子命令时执行并返回退出码，否则返回 None
+pub fn run(args: impl IntoIterator<Item = OsString>) -> Option<i32> {
+    let args: Vec<String> = args
+        .into_iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    if !SUBCOMMANDS.contains(&args.first()?.as_str()) {
+        return None;
+    }
+    #[cfg(windows)]
+    attach_console();
+
+    let mut stdout = std::io::stdout().lock();
+    let result = parse_args(&args).and_then(|args| execute(&args, &mut stdout));
+    let _ = stdout.flush();
+    Some(match result {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            2
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("错误: {}", message);
+            1
+        }
+        Err(CliError::Conflicts(count)) => {
+            eprintln!("合并完成，有 {} 处冲突，已在文件中插入冲突节点", count);
+            1
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::PrgAttachment;
+    use serde_json::json;
+
+    fn run_to_string(args: &[&str]) -> Result<String, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = Vec::new();
+        execute(&parse_args(&args)?, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn converts_prg_to_json_and_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.prg");
+        let project = PrgProject {
+            stage: vec![
+                json!({ "_": "TextNode", "uuid": "a", "text": "你好" }),
+                json!({ "_": "TextNode", "uuid": "b", "text": "世界" }),
+            ],
+            tags: vec!["a".to_string()],
+            attachments: vec![PrgAttachment {
+                id: "0b6e2f7c".to_string(),
+                ext: "png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G'],
+            }],
+            thumbnail: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        project.save(&source).unwrap();
+        let source = source.to_str().unwrap();
+
+        let info: Value =
+            serde_json::from_str(&run_to_string(&["info", source, "--json"]).ok().unwrap())
+                .unwrap();
+        assert_eq!(info["objects"]["TextNode"], 2);
+        assert_eq!(info["attachmentBytes"], 4);
+        assert_eq!(
+            run_to_string(&["extract-text", source]).ok().unwrap(),
+            "你好\n\n世界\n"
+        );
+        assert_eq!(
+            run_to_string(&["list-attachments", source]).ok().unwrap(),
+            "0b6e2f7c.png\t4\timage/png\n"
+        );
+
+        // 内嵌附件的 JSON 可以还原出完全相同的工程
+        let json_path = dir.path().join("a.json");
+        let output = dir.path().join("b.prg");
+        run_to_string(&["to-json", source, "-o", json_path.to_str().unwrap()])
+            .ok()
+            .unwrap();
+        run_to_string(&[
+            "from-json",
+            json_path.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .ok()
+        .unwrap();
+        assert_eq!(PrgProject::open(&output).unwrap(), project);
+
+        // 不内嵌附件时从原文件复制
+        let json = run_to_string(&["to-json", source, "--no-attachment-data"])
+            .ok()
+            .unwrap();
+        assert!(!json.contains("\"data\""));
+        std::fs::write(&json_path, json).unwrap();
+        let args = ["from-json", json_path.to_str().unwrap(), "-o"];
+        let output = output.to_str().unwrap();
+        assert!(run_to_string(&[&args[..], &[output]].concat()).is_err());
+        run_to_string(&[&args[..], &[output, "--attachments-from", source]].concat())
+            .ok()
+            .unwrap();
+        let restored = PrgProject::open(Path::new(output)).unwrap();
+        assert_eq!(restored.attachments, project.attachments);
+        assert_eq!(restored.thumbnail, None);
+
+        assert!(matches!(run_to_string(&["info"]), Err(CliError::Usage(_))));
+        assert_eq!(run(["project-graph", "a.prg"].map(OsString::from)), None);
+    }
+}
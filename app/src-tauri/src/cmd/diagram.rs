@@ -0,0 +1,339 @@
+//! 用本地安装的工具渲染图表
+//!
+//! - DOT：Graphviz 的 `dot`
+//! - PlantUML：`plantuml`
+//! - Mermaid：mermaid-cli 的 `mmdc`
+//!
+//! 源码写入一个单独的临时目录，工具以这个目录为工作目录运行，超时后结束整个进程组。
+//! Graphviz 通过 GV_FILE_PATH、PlantUML 通过 SANDBOX 安全配置限制只能读取这个目录，
+//! 渲染结束后临时目录被删除。
+//!
+//! 结果缓存在应用缓存目录的 diagram-cache 中，文件名为（语言、格式、源码）的哈希，
+//! 同样的源码不会重复渲染。
+//!
+//! 程序和参数都是固定的，只有源码来自前端，所以不经过 cmd::shell_policy 的检查。
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+
+use super::fs::write_file_atomic;
+use super::shell::{run, CommandOptions};
+use super::shell_policy::resolve_program;
+
+const CACHE_DIR: &str = "diagram-cache";
+
+/// 默认超时
+pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// 图表的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagramLanguage {
+    Dot,
+    PlantUml,
+    Mermaid,
+}
+
+impl DiagramLanguage {
+    pub const ALL: [DiagramLanguage; 3] = [
+        DiagramLanguage::Dot,
+        DiagramLanguage::PlantUml,
+        DiagramLanguage::Mermaid,
+    ];
+
+    /// 渲染用的程序
+    pub fn program(self) -> &'static str {
+        match self {
+            DiagramLanguage::Dot => "dot",
+            DiagramLanguage::PlantUml => "plantuml",
+            DiagramLanguage::Mermaid => "mmdc",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            DiagramLanguage::Dot => "dot",
+            DiagramLanguage::PlantUml => "puml",
+            DiagramLanguage::Mermaid => "mmd",
+        }
+    }
+
+    /// 在 dir 中渲染 input，返回参数和输出文件的位置
+    fn command(self, dir: &Path, input: &Path, format: DiagramFormat) -> (Vec<String>, PathBuf) {
+        let ext = format.extension();
+        let arg = |path: &Path| path.to_string_lossy().into_owned();
+        match self {
+            DiagramLanguage::Dot => {
+                let output = dir.join(format!("output.{}", ext));
+                let args = vec![
+                    format!("-T{}", ext),
+                    "-o".to_string(),
+                    arg(&output),
+                    arg(input),
+                ];
+                (args, output)
+            }
+            // PlantUML 把结果写在输入文件旁边，文件名与输入相同
+            DiagramLanguage::PlantUml => (
+                vec![
+                    format!("-t{}", ext),
+                    "-charset".to_string(),
+                    "UTF-8".to_string(),
+                    "-nometadata".to_string(),
+                    arg(input),
+                ],
+                input.with_extension(ext),
+            ),
+            DiagramLanguage::Mermaid => {
+                let output = dir.join(format!("output.{}", ext));
+                let args = vec![
+                    "-q".to_string(),
+                    "-i".to_string(),
+                    arg(input),
+                    "-o".to_string(),
+                    arg(&output),
+                ];
+                (args, output)
+            }
+        }
+    }
+}
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagramFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+impl DiagramFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            DiagramFormat::Svg => "svg",
+            DiagramFormat::Png => "png",
+        }
+    }
+}
+
+/// 按 PATH 查找渲染用的程序
+pub fn find_tool(language: DiagramLanguage) -> Option<PathBuf> {
+    Some(resolve_program(language.program(), None, None)).filter(|path| path.is_file())
+}
+
+pub fn cache_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| format!("无法获取缓存目录: {}", e))
+}
+
+/// 缓存文件的位置，由语言、格式和源码决定
+fn cache_path(
+    cache_dir: &Path,
+    language: DiagramLanguage,
+    format: DiagramFormat,
+    source: &str,
+) -> PathBuf {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(language.program().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(source.as_bytes());
+    cache_dir.join(format!(
+        "{}.{}",
+        &hasher.finalize().to_hex()[..32],
+        format.extension()
+    ))
+}
+
+/// 用 tool 渲染，不使用缓存
+pub fn render_with(
+    tool: &Path,
+    language: DiagramLanguage,
+    format: DiagramFormat,
+    source: &str,
+    timeout_ms: u64,
+) -> Result<Vec<u8>, String> {
+    // 随机命名并且只有当前用户可以访问，离开作用域时删除
+    let dir = tempfile::Builder::new()
+        .prefix("project-graph-diagram-")
+        .tempdir()
+        .map_err(|e| format!("无法创建临时目录: {}", e))?;
+    let input = dir.path().join(format!("input.{}", language.extension()));
+    std::fs::write(&input, source).map_err(|e| format!("无法写入图表源码: {}", e))?;
+    let (args, output) = language.command(dir.path(), &input, format);
+
+    let mut env = std::collections::HashMap::new();
+    // Graphviz 只能读取这个目录中的图片
+    env.insert(
+        "GV_FILE_PATH".to_string(),
+        dir.path().to_string_lossy().into_owned(),
+    );
+    // PlantUML 禁止 !include 本地文件、访问网络等
+    env.insert(
+        "PLANTUML_SECURITY_PROFILE".to_string(),
+        "SANDBOX".to_string(),
+    );
+    let options = CommandOptions {
+        cwd: Some(dir.path().to_string_lossy().into_owned()),
+        env,
+        timeout_ms: Some(timeout_ms),
+        max_output_bytes: Some(64 * 1024),
+        ..Default::default()
+    };
+    let result = run(tool, &args, None, &options);
+    if result.timed_out {
+        return Err(format!(
+            "{} 渲染超时（{} 毫秒）",
+            language.program(),
+            timeout_ms
+        ));
+    }
+    if result.code != Some(0) {
+        let message = result.stderr.trim();
+        let message = if message.is_empty() {
+            result.stdout.trim()
+        } else {
+            message
+        };
+        return Err(format!(
+            "{} 渲染失败（退出码 {:?}）: {}",
+            language.program(),
+            result.code,
+            message
+        ));
+    }
+    std::fs::read(&output).map_err(|e| format!("{} 没有生成输出: {}", language.program(), e))
+}
+
+/// 渲染图表，已有缓存且 refresh 为 false 时直接使用
+/// 返回内容和是否来自缓存
+pub fn render(
+    cache_dir: &Path,
+    tool: &Path,
+    language: DiagramLanguage,
+    format: DiagramFormat,
+    source: &str,
+    timeout_ms: u64,
+    refresh: bool,
+) -> Result<(Vec<u8>, bool), String> {
+    let cached = cache_path(cache_dir, language, format, source);
+    if !refresh {
+        if let Ok(data) = std::fs::read(&cached) {
+            return Ok((data, true));
+        }
+    }
+    let data = render_with(tool, language, format, source, timeout_ms)?;
+    std::fs::create_dir_all(cache_dir).map_err(|e| format!("无法创建缓存目录: {}", e))?;
+    write_file_atomic(&cached, &data, false)?;
+    Ok((data, false))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagramTool {
+    pub language: DiagramLanguage,
+    pub program: &'static str,
+    /// 找到的程序的完整路径，没有安装时为空
+    pub path: Option<String>,
+}
+
+/// 查找本机安装了哪些渲染工具
+#[tauri::command]
+pub async fn find_diagram_tools() -> Result<Vec<DiagramTool>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        DiagramLanguage::ALL
+            .into_iter()
+            .map(|language| DiagramTool {
+                language,
+                program: language.program(),
+                path: find_tool(language).map(|path| path.to_string_lossy().into_owned()),
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("查找图表工具任务失败: {}", e))
+}
+
+/// 渲染 DOT / PlantUML / Mermaid 源码，以二进制返回 SVG 或 PNG
+/// format 默认为 SVG，timeout_ms 默认 30 秒，refresh 为 true 时忽略已有缓存
+#[tauri::command]
+pub async fn render_diagram<R: Runtime>(
+    app: AppHandle<R>,
+    language: DiagramLanguage,
+    source: String,
+    format: Option<DiagramFormat>,
+    timeout_ms: Option<u64>,
+    refresh: Option<bool>,
+) -> Result<tauri::ipc::Response, String> {
+    let cache_dir = cache_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let tool = find_tool(language)
+            .ok_or_else(|| format!("没有找到 {}，请先安装并加入 PATH", language.program()))?;
+        render(
+            &cache_dir,
+            &tool,
+            language,
+            format.unwrap_or_default(),
+            &source,
+            timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS),
+            refresh.unwrap_or(false),
+        )
+        .map(|(data, _)| tauri::ipc::Response::new(data))
+    })
+    .await
+    .map_err(|e| format!("渲染图表任务失败: {}", e))?
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn renders_in_a_temp_dir_and_caches_by_content() {
+        let dir = tempfile::tempdir().unwrap();
+        // 假的 dot：把输入文件复制到 -o 指定的位置，并记录运行次数和工作目录
+        let tool = dir.path().join("dot");
+        let log = dir.path().join("runs.log");
+        std::fs::write(
+            &tool,
+            format!(
+                "#!/bin/sh\npwd >> '{}'\ngrep -q bad \"$4\" && {{ echo syntax error >&2; exit 1; }}\ncp \"$4\" \"$3\"\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let cache = dir.path().join("cache");
+        let render_dot = |source: &str| {
+            render(
+                &cache,
+                &tool,
+                DiagramLanguage::Dot,
+                DiagramFormat::Svg,
+                source,
+                5_000,
+                false,
+            )
+        };
+
+        assert_eq!(
+            render_dot("digraph { a -> b }").unwrap(),
+            (b"digraph { a -> b }".to_vec(), false)
+        );
+        assert_eq!(
+            render_dot("digraph { a -> b }").unwrap(),
+            (b"digraph { a -> b }".to_vec(), true)
+        );
+        let runs = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(runs.lines().count(), 1);
+        // 临时目录用完后被删除
+        assert!(!Path::new(runs.trim()).exists());
+
+        let error = render_dot("bad").unwrap_err();
+        assert!(error.contains("syntax error"), "{}", error);
+    }
+}
@@ -0,0 +1,615 @@
+//! 两个 .prg 文件的语义对比
+//!
+//! .prg 是 msgpack 的 ZIP，git diff 只能看到二进制变化。
+//! 这里按 UUID 对齐两边的舞台对象，报告节点的增删、文字修改、移动、连线重连、
+//! Section 子对象变化，以及标签、附件、README 和版本号的变化。
+//!
+//! textconv 把单个 .prg 渲染成稳定的纯文本，用作 git 的 diff 驱动：
+//! ```text
+//! # .gitattributes
+//! *.prg diff=prg
+//! # git config
+//! git config diff.prg.textconv "project-graph textconv"
+//! ```
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use tauri::State;
+
+use super::prg::PrgProject;
+use super::scope::FsScope;
+use super::stage;
+
+/// 单独比较、不计入“属性”的字段
+const STRUCTURAL_KEYS: &[&str] = &[
+    "_",
+    "uuid",
+    "text",
+    "title",
+    "url",
+    "latexSource",
+    "details",
+    "collisionBox",
+    "_collisionBoxNormal",
+    "associationList",
+    "children",
+];
+
+/// 小于这个距离的位置变化不算移动
+const MOVE_EPSILON: f64 = 0.01;
+
+/// 对比时关心的舞台对象信息
+#[derive(Debug, Clone, PartialEq)]
+struct ObjectSummary {
+    class: String,
+    text: String,
+    location: Option<[f64; 2]>,
+    /// 连线连接的对象 UUID
+    endpoints: Vec<String>,
+    /// Section 包含的对象 UUID
+    children: Vec<String>,
+    /// 其余字段
+    properties: Map<String, Value>,
+}
+
+fn location(object: &Value) -> Option<[f64; 2]> {
+    let collision_box = object
+        .get("collisionBox")
+        .or_else(|| object.get("_collisionBoxNormal"))?;
+    let location = collision_box.get("shapes")?.get(0)?.get("location")?;
+    Some([location.get("x")?.as_f64()?, location.get("y")?.as_f64()?])
+}
+
+fn referenced_uuids(stage: &[Value], list: Option<&Value>) -> Vec<String> {
+    list.and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| stage::resolve(stage, item).and_then(stage::uuid))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn summarize(stage: &[Value]) -> BTreeMap<String, ObjectSummary> {
+    let mut objects = BTreeMap::new();
+    stage::for_each_object(stage, |object| {
+        let (Some(class), Some(uuid)) = (stage::class_name(object), stage::uuid(object)) else {
+            return;
+        };
+        let properties = object
+            .as_object()
+            .map(|map| {
+                map.iter()
+                    .filter(|(key, _)| !STRUCTURAL_KEYS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        objects.insert(
+            uuid.to_string(),
+            ObjectSummary {
+                class: class.to_string(),
+                text: stage::object_text(object),
+                location: location(object),
+                endpoints: referenced_uuids(stage, object.get("associationList")),
+                children: referenced_uuids(stage, object.get("children")),
+                properties,
+            },
+        );
+    });
+    objects
+}
+
+/// 变化中涉及的对象
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectRef {
+    pub uuid: String,
+    pub class: String,
+    /// 便于阅读的名称：文字的第一行，没有文字时为 UUID 的前 8 位
+    pub label: String,
+}
+
+/// 一项变化
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PrgChange {
+    ObjectAdded {
+        object: ObjectRef,
+    },
+    ObjectRemoved {
+        object: ObjectRef,
+    },
+    TextChanged {
+        object: ObjectRef,
+        old: String,
+        new: String,
+    },
+    Moved {
+        object: ObjectRef,
+        from: [f64; 2],
+        to: [f64; 2],
+    },
+    /// 连线的端点改变，old/new 是端点的名称
+    Rewired {
+        object: ObjectRef,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    ChildrenChanged {
+        object: ObjectRef,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    PropertiesChanged {
+        object: ObjectRef,
+        keys: Vec<String>,
+    },
+    TagAdded {
+        label: String,
+    },
+    TagRemoved {
+        label: String,
+    },
+    AttachmentAdded {
+        name: String,
+        size: u64,
+    },
+    AttachmentRemoved {
+        name: String,
+        size: u64,
+    },
+    AttachmentModified {
+        name: String,
+        old_size: u64,
+        new_size: u64,
+    },
+    VersionChanged {
+        old: String,
+        new: String,
+    },
+    ReadmeChanged,
+    ThumbnailChanged,
+}
+
+/// diff_prg 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgDiff {
+    pub changes: Vec<PrgChange>,
+}
+
+fn label(uuid: &str, text: &str) -> String {
+    let first_line = text.lines().find(|line| !line.trim().is_empty());
+    match first_line {
+        Some(line) if line.chars().count() > 40 => {
+            format!("{}…", line.chars().take(40).collect::<String>())
+        }
+        Some(line) => line.to_string(),
+        None => uuid.chars().take(8).collect(),
+    }
+}
+
+fn object_ref(uuid: &str, summary: &ObjectSummary) -> ObjectRef {
+    ObjectRef {
+        uuid: uuid.to_string(),
+        class: summary.class.clone(),
+        label: label(uuid, &summary.text),
+    }
+}
+
+/// 对象的名称，两边都找不到时使用 UUID 的前 8 位
+fn name_of(uuid: &str, objects: &BTreeMap<String, ObjectSummary>) -> String {
+    objects
+        .get(uuid)
+        .map(|summary| label(uuid, &summary.text))
+        .unwrap_or_else(|| uuid.chars().take(8).collect())
+}
+
+fn attachment_map(project: &PrgProject) -> BTreeMap<String, &[u8]> {
+    project
+        .attachments
+        .iter()
+        .map(|attachment| (attachment.entry_name(), attachment.data.as_slice()))
+        .collect()
+}
+
+/// 对比两个工程
+pub fn diff(old: &PrgProject, new: &PrgProject) -> PrgDiff {
+    let old_objects = summarize(&old.stage);
+    let new_objects = summarize(&new.stage);
+    let mut changes = Vec::new();
+
+    for (uuid, before) in &old_objects {
+        match new_objects.get(uuid) {
+            Some(after) if after.class == before.class => {}
+            _ => changes.push(PrgChange::ObjectRemoved {
+                object: object_ref(uuid, before),
+            }),
+        }
+    }
+    for (uuid, after) in &new_objects {
+        let Some(before) = old_objects
+            .get(uuid)
+            .filter(|before| before.class == after.class)
+        else {
+            changes.push(PrgChange::ObjectAdded {
+                object: object_ref(uuid, after),
+            });
+            continue;
+        };
+        if before == after {
+            continue;
+        }
+        let object = object_ref(uuid, after);
+        if before.text != after.text {
+            changes.push(PrgChange::TextChanged {
+                object: object.clone(),
+                old: before.text.clone(),
+                new: after.text.clone(),
+            });
+        }
+        if let (Some(from), Some(to)) = (before.location, after.location) {
+            if (from[0] - to[0]).abs() > MOVE_EPSILON || (from[1] - to[1]).abs() > MOVE_EPSILON {
+                changes.push(PrgChange::Moved {
+                    object: object.clone(),
+                    from,
+                    to,
+                });
+            }
+        }
+        if before.endpoints != after.endpoints {
+            changes.push(PrgChange::Rewired {
+                object: object.clone(),
+                old: before
+                    .endpoints
+                    .iter()
+                    .map(|id| name_of(id, &old_objects))
+                    .collect(),
+                new: after
+                    .endpoints
+                    .iter()
+                    .map(|id| name_of(id, &new_objects))
+                    .collect(),
+            });
+        }
+        if before.children != after.children {
+            let old_children: BTreeSet<&String> = before.children.iter().collect();
+            let new_children: BTreeSet<&String> = after.children.iter().collect();
+            let added: Vec<String> = new_children
+                .difference(&old_children)
+                .map(|id| name_of(id, &new_objects))
+                .collect();
+            let removed: Vec<String> = old_children
+                .difference(&new_children)
+                .map(|id| name_of(id, &old_objects))
+                .collect();
+            // 只是顺序变化时不报告
+            if !added.is_empty() || !removed.is_empty() {
+                changes.push(PrgChange::ChildrenChanged {
+                    object: object.clone(),
+                    added,
+                    removed,
+                });
+            }
+        }
+        let keys: BTreeSet<&String> = before
+            .properties
+            .keys()
+            .chain(after.properties.keys())
+            .filter(|key| before.properties.get(*key) != after.properties.get(*key))
+            .collect();
+        if !keys.is_empty() {
+            changes.push(PrgChange::PropertiesChanged {
+                object,
+                keys: keys.into_iter().cloned().collect(),
+            });
+        }
+    }
+
+    let old_tags: BTreeSet<&String> = old.tags.iter().collect();
+    let new_tags: BTreeSet<&String> = new.tags.iter().collect();
+    for tag in old_tags.difference(&new_tags) {
+        changes.push(PrgChange::TagRemoved {
+            label: name_of(tag, &old_objects),
+        });
+    }
+    for tag in new_tags.difference(&old_tags) {
+        changes.push(PrgChange::TagAdded {
+            label: name_of(tag, &new_objects),
+        });
+    }
+
+    let old_attachments = attachment_map(old);
+    let new_attachments = attachment_map(new);
+    for (name, data) in &old_attachments {
+        if !new_attachments.contains_key(name) {
+            changes.push(PrgChange::AttachmentRemoved {
+                name: name.clone(),
+                size: data.len() as u64,
+            });
+        }
+    }
+    for (name, data) in &new_attachments {
+        match old_attachments.get(name) {
+            None => changes.push(PrgChange::AttachmentAdded {
+                name: name.clone(),
+                size: data.len() as u64,
+            }),
+            Some(old_data) if old_data != data => changes.push(PrgChange::AttachmentModified {
+                name: name.clone(),
+                old_size: old_data.len() as u64,
+                new_size: data.len() as u64,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    if old.metadata.version != new.metadata.version {
+        changes.push(PrgChange::VersionChanged {
+            old: old.metadata.version.clone(),
+            new: new.metadata.version.clone(),
+        });
+    }
+    if old.readme != new.readme {
+        changes.push(PrgChange::ReadmeChanged);
+    }
+    if old.thumbnail != new.thumbnail {
+        changes.push(PrgChange::ThumbnailChanged);
+    }
+
+    PrgDiff { changes }
+}
+
+/// 读取 .prg，不存在的文件（以及 git 传入的 /dev/null）视为空工程
+pub fn open_or_empty(path: &Path) -> Result<PrgProject, String> {
+    if path == Path::new("/dev/null") || !path.exists() {
+        return Ok(PrgProject::default());
+    }
+    PrgProject::open(path)
+}
+
+fn format_point(point: [f64; 2]) -> String {
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    format!("({}, {})", round(point[0]), round(point[1]))
+}
+
+fn quote_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("\"{}\"", item))
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+/// 把对比结果渲染成文本，每项变化一行，+ 新增、- 删除、~ 修改
+pub fn render_diff(diff: &PrgDiff) -> String {
+    let mut out = String::new();
+    for change in &diff.changes {
+        let object_name = |object: &ObjectRef| format!("{} \"{}\"", object.class, object.label);
+        let _ = match change {
+            PrgChange::ObjectAdded { object } => writeln!(out, "+ {}", object_name(object)),
+            PrgChange::ObjectRemoved { object } => writeln!(out, "- {}", object_name(object)),
+            PrgChange::TextChanged { object, old, new } => {
+                writeln!(out, "~ {}: 文字 {:?} → {:?}", object_name(object), old, new)
+            }
+            PrgChange::Moved { object, from, to } => writeln!(
+                out,
+                "~ {}: 移动 {} → {}",
+                object_name(object),
+                format_point(*from),
+                format_point(*to)
+            ),
+            PrgChange::Rewired { object, old, new } => writeln!(
+                out,
+                "~ {}: 连接 {} 改为 {}",
+                object_name(object),
+                quote_list(old),
+                quote_list(new)
+            ),
+            PrgChange::ChildrenChanged {
+                object,
+                added,
+                removed,
+            } => {
+                let items: Vec<String> = added
+                    .iter()
+                    .map(|name| format!("+\"{}\"", name))
+                    .chain(removed.iter().map(|name| format!("-\"{}\"", name)))
+                    .collect();
+                writeln!(out, "~ {}: 子对象 {}", object_name(object), items.join(" "))
+            }
+            PrgChange::PropertiesChanged { object, keys } => {
+                writeln!(out, "~ {}: 属性 {}", object_name(object), keys.join(", "))
+            }
+            PrgChange::TagAdded { label } => writeln!(out, "+ 标签 \"{}\"", label),
+            PrgChange::TagRemoved { label } => writeln!(out, "- 标签 \"{}\"", label),
+            PrgChange::AttachmentAdded { name, size } => {
+                writeln!(out, "+ 附件 {} ({} 字节)", name, size)
+            }
+            PrgChange::AttachmentRemoved { name, size } => {
+                writeln!(out, "- 附件 {} ({} 字节)", name, size)
+            }
+            PrgChange::AttachmentModified {
+                name,
+                old_size,
+                new_size,
+            } => writeln!(
+                out,
+                "~ 附件 {}: 内容改变 ({} → {} 字节)",
+                name, old_size, new_size
+            ),
+            PrgChange::VersionChanged { old, new } => writeln!(out, "~ 版本 {} → {}", old, new),
+            PrgChange::ReadmeChanged => writeln!(out, "~ README"),
+            PrgChange::ThumbnailChanged => writeln!(out, "~ 缩略图"),
+        };
+    }
+    out
+}
+
+/// 把一个工程渲染成稳定的纯文本，用作 git 的 textconv
+/// 对象按 UUID 排序，同一个对象在两个版本中总是出现在相同的位置
+pub fn textconv(project: &PrgProject) -> String {
+    let objects = summarize(&project.stage);
+    let mut out = String::new();
+    let _ = writeln!(out, "版本: {}", project.metadata.version);
+    for (uuid, object) in &objects {
+        let _ = writeln!(out, "\n# {} {}", object.class, uuid);
+        for line in object.text.lines() {
+            let _ = writeln!(out, "  {}", line);
+        }
+        if let Some(point) = object.location {
+            let _ = writeln!(out, "位置: {}", format_point(point));
+        }
+        if !object.endpoints.is_empty() {
+            let names: Vec<String> = object
+                .endpoints
+                .iter()
+                .map(|id| name_of(id, &objects))
+                .collect();
+            let _ = writeln!(out, "连接: {}", quote_list(&names));
+        }
+        for child in &object.children {
+            let _ = writeln!(out, "子对象: \"{}\"", name_of(child, &objects));
+        }
+        for (key, value) in &object.properties {
+            let _ = writeln!(out, "{}: {}", key, value);
+        }
+    }
+    if !project.tags.is_empty() {
+        let _ = writeln!(out, "\n# 标签");
+        for tag in &project.tags {
+            let _ = writeln!(out, "\"{}\"", name_of(tag, &objects));
+        }
+    }
+    if !project.attachments.is_empty() {
+        let _ = writeln!(out, "\n# 附件");
+        for attachment in &project.attachments {
+            let _ = writeln!(
+                out,
+                "{} {} 字节 blake3:{}",
+                attachment.entry_name(),
+                attachment.data.len(),
+                blake3::hash(&attachment.data).to_hex()
+            );
+        }
+    }
+    if let Some(readme) = &project.readme {
+        let _ = writeln!(out, "\n# README\n{}", readme);
+    }
+    out
+}
+
+/// 对比两个 .prg 文件
+#[tauri::command]
+pub async fn diff_prg(
+    scope: State<'_, FsScope>,
+    old_path: String,
+    new_path: String,
+) -> Result<PrgDiff, String> {
+    scope.check(Path::new(&old_path))?;
+    scope.check(Path::new(&new_path))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let old = open_or_empty(Path::new(&old_path))?;
+        let new = open_or_empty(Path::new(&new_path))?;
+        Ok(diff(&old, &new))
+    })
+    .await
+    .map_err(|e| format!("对比任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::PrgAttachment;
+    use serde_json::json;
+
+    fn node(uuid: &str, text: &str, x: f64) -> Value {
+        json!({
+            "_": "TextNode",
+            "uuid": uuid,
+            "text": text,
+            "color": [0, 0, 0, 0],
+            "collisionBox": {
+                "_": "CollisionBox",
+                "shapes": [{
+                    "_": "Rectangle",
+                    "location": { "_": "Vector", "x": x, "y": 0 },
+                    "size": { "_": "Vector", "x": 100, "y": 40 }
+                }]
+            }
+        })
+    }
+
+    fn edge(uuid: &str, source: &str, target: &str) -> Value {
+        json!({
+            "_": "LineEdge",
+            "uuid": uuid,
+            "text": "",
+            "associationList": [{ "$": source }, { "$": target }]
+        })
+    }
+
+    #[test]
+    fn reports_semantic_changes() {
+        let old = PrgProject {
+            stage: vec![
+                node("a", "甲", 0.0),
+                node("b", "乙", 100.0),
+                node("c", "丙", 200.0),
+                edge("e", "/0", "/1"),
+            ],
+            tags: vec!["a".to_string()],
+            attachments: vec![PrgAttachment {
+                id: "img".to_string(),
+                ext: "png".to_string(),
+                data: vec![1, 2, 3],
+            }],
+            ..Default::default()
+        };
+        let mut moved = node("b", "乙", 150.0);
+        moved["color"] = json!([255, 0, 0, 1]);
+        let new = PrgProject {
+            stage: vec![
+                node("a", "甲改", 0.0),
+                moved,
+                node("d", "丁", 300.0),
+                edge("e", "/0", "/2"),
+            ],
+            tags: vec!["d".to_string()],
+            attachments: vec![PrgAttachment {
+                id: "img".to_string(),
+                ext: "png".to_string(),
+                data: vec![1, 2, 3, 4],
+            }],
+            ..Default::default()
+        };
+
+        let rendered = render_diff(&diff(&old, &new));
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- TextNode \"丙\"",
+                "~ TextNode \"甲改\": 文字 \"甲\" → \"甲改\"",
+                "~ TextNode \"乙\": 移动 (100, 0) → (150, 0)",
+                "~ TextNode \"乙\": 属性 color",
+                "+ TextNode \"丁\"",
+                "~ LineEdge \"e\": 连接 \"甲\" → \"乙\" 改为 \"甲改\" → \"丁\"",
+                "- 标签 \"甲\"",
+                "+ 标签 \"丁\"",
+                "~ 附件 attachments/img.png: 内容改变 (3 → 4 字节)",
+            ]
+        );
+
+        assert!(diff(&old, &old).changes.is_empty());
+        let text = textconv(&new);
+        assert!(text.contains("# LineEdge e\n连接: \"甲改\" → \"丁\""));
+        assert_eq!(text, textconv(&new.clone()));
+    }
+}
@@ -1,8 +1,196 @@
 use serde::{Deserialize, Serialize};
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use base64::engine::general_purpose;
 use base64::Engine;
+use tauri::ipc::Channel;
+use tauri::State;
+
+use super::scope::FsScope;
+use super::task::{CancellationToken, TaskRegistry};
+
+/// 文件操作的错误
+/// 序列化为 { kind, message }，前端可以根据 kind 给出不同的提示
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum FsError {
+    /// 文件或目录不存在
+    NotFound(String),
+    /// 没有权限
+    PermissionDenied(String),
+    /// 磁盘空间不足
+    DiskFull(String),
+    /// 目标是一个文件夹
+    IsADirectory(String),
+    /// 文件夹不为空
+    DirectoryNotEmpty(String),
+    /// 文件内容不是指定编码的文本
+    InvalidEncoding(String),
+    /// 路径不在允许访问的范围内，见 cmd::scope
+    OutsideScope(String),
+    Other(String),
+}
+
+impl FsError {
+    /// 根据 io::Error 的类型转换为对应的错误，message 中带上路径
+    pub fn from_io(error: std::io::Error, path: &Path) -> Self {
+        let message = format!("{}: {}", path.display(), error);
+        match error.kind() {
+            std::io::ErrorKind::NotFound => FsError::NotFound(message),
+            std::io::ErrorKind::PermissionDenied => FsError::PermissionDenied(message),
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => {
+                FsError::DiskFull(message)
+            }
+            std::io::ErrorKind::IsADirectory => FsError::IsADirectory(message),
+            std::io::ErrorKind::DirectoryNotEmpty => FsError::DirectoryNotEmpty(message),
+            std::io::ErrorKind::InvalidData => FsError::InvalidEncoding(message),
+            _ => FsError::Other(message),
+        }
+    }
+}
+
+impl std::fmt::Display for FsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FsError::NotFound(message) => write!(f, "文件不存在: {}", message),
+            FsError::PermissionDenied(message) => write!(f, "没有权限: {}", message),
+            FsError::DiskFull(message) => write!(f, "磁盘空间不足: {}", message),
+            FsError::IsADirectory(message) => write!(f, "目标是一个文件夹: {}", message),
+            FsError::DirectoryNotEmpty(message) => write!(f, "文件夹不为空: {}", message),
+            FsError::InvalidEncoding(message) => write!(f, "{}", message),
+            FsError::OutsideScope(message) => write!(f, "路径不在允许访问的范围内: {}", message),
+            FsError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<FsError> for String {
+    fn from(error: FsError) -> Self {
+        error.to_string()
+    }
+}
+
+/// 写入文件时的选项
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteOptions {
+    /// 先写入同目录下的临时文件，fsync 后再重命名覆盖目标文件，
+    /// 写入过程中崩溃或磁盘写满不会损坏原文件
+    #[serde(default)]
+    pub atomic: bool,
+    /// 覆盖前把原文件复制为 {path}.bak
+    #[serde(default)]
+    pub backup: bool,
+}
+
+/// 临时文件名的序号，避免同一进程内并发写入同一文件时冲突
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// 沿着符号链接找到最终的文件，链接指向不存在的文件时返回这个不存在的路径
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // 与系统的 MAXSYMLINKS 一致，避免循环链接
+    for _ in 0..40 {
+        let Ok(target) = std::fs::read_link(&path) else {
+            break;
+        };
+        path = match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    path
+}
+
+/// 原子地写入文件：写入同目录下的临时文件 -> fsync -> 重命名覆盖目标文件
+/// 目标是符号链接时写入链接指向的文件，链接本身保持不变
+/// backup 为 true 时，覆盖前把原文件复制为 {path}.bak
+pub fn write_file_atomic(path: &Path, content: &[u8], backup: bool) -> Result<(), FsError> {
+    if path.is_dir() {
+        return Err(FsError::IsADirectory(path.display().to_string()));
+    }
+    let link = path;
+    let target = resolve_symlinks(path);
+    let path = target.as_path();
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| FsError::Other(format!("无效的文件路径: {}", path.display())))?;
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = parent.join(temp_name);
+
+    let result = (|| {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .map_err(|e| FsError::from_io(e, &temp_path))?;
+        file.write_all(content)
+            .map_err(|e| FsError::from_io(e, &temp_path))?;
+        file.sync_all()
+            .map_err(|e| FsError::from_io(e, &temp_path))?;
+        // 保留原文件的权限
+        if let Ok(metadata) = std::fs::metadata(path) {
+            let _ = std::fs::set_permissions(&temp_path, metadata.permissions());
+        }
+        drop(file);
+
+        if backup && path.exists() {
+            let backup = backup_path(link);
+            std::fs::copy(path, &backup).map_err(|e| FsError::from_io(e, &backup))?;
+        }
+        std::fs::rename(&temp_path, path).map_err(|e| FsError::from_io(e, path))
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // 重命名本身也需要落盘，否则断电后目录项可能还指向旧文件
+    #[cfg(unix)]
+    if let Ok(dir) = std::fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// 按照选项写入文件
+pub fn write_file_with_options(
+    path: &Path,
+    content: &[u8],
+    options: &WriteOptions,
+) -> Result<(), FsError> {
+    if options.atomic {
+        return write_file_atomic(path, content, options.backup);
+    }
+    if path.is_dir() {
+        return Err(FsError::IsADirectory(path.display().to_string()));
+    }
+    if options.backup && path.exists() {
+        let backup = backup_path(path);
+        std::fs::copy(path, &backup).map_err(|e| FsError::from_io(e, &backup))?;
+    }
+    std::fs::write(path, content).map_err(|e| FsError::from_io(e, path))
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FolderEntry {
@@ -11,58 +199,417 @@ pub struct FolderEntry {
     path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     children: Option<Vec<FolderEntry>>,
+    /// 只在根节点上出现：条目数达到 max_entries，结果不完整
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// 以下字段只在 ReadFolderOptions 中开启对应选项时才有
+    /// 文件大小（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    /// 修改时间，Unix 毫秒时间戳
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    /// 创建时间，Unix 毫秒时间戳，部分文件系统不支持
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<u64>,
+    /// 根据文件内容推断的 MIME 类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mime: Option<String>,
+    /// 符号链接指向的路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    symlink_target: Option<String>,
+    /// 文件夹内所有文件的总大小
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_size: Option<u64>,
+    /// 文件夹内（递归）的文件数量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_count: Option<u64>,
 }
 
-/// 递归读取文件夹结构，返回嵌套的文件夹结构
-#[tauri::command]
-pub fn read_folder_structure(path: String) -> FolderEntry {
-    let path_buf = std::path::PathBuf::from(&path);
-    let name = path_buf
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("")
-        .to_string();
+impl FolderEntry {
+    fn new(path: &Path, is_file: bool) -> Self {
+        FolderEntry {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            is_file,
+            path: path.to_string_lossy().to_string(),
+            children: if is_file { None } else { Some(Vec::new()) },
+            truncated: false,
+            size: None,
+            modified: None,
+            created: None,
+            mime: None,
+            symlink_target: None,
+            total_size: None,
+            file_count: None,
+        }
+    }
 
-    let mut children = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(&path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                let child_name = entry.file_name().to_string_lossy().to_string();
-                let path_str = path.to_string_lossy().to_string();
-
-                if path.is_file() {
-                    children.push(FolderEntry {
-                        name: child_name,
-                        is_file: true,
-                        path: path_str,
-                        children: None,
-                    });
-                } else if path.is_dir() {
-                    children.push(read_folder_structure(path_str));
-                }
+    /// 填充大小、时间和符号链接信息
+    fn fill_metadata(&mut self, path: &Path, metadata: &std::fs::Metadata, is_symlink: bool) {
+        if self.is_file {
+            self.size = Some(metadata.len());
+        }
+        self.modified = metadata.modified().ok().and_then(unix_millis);
+        self.created = metadata.created().ok().and_then(unix_millis);
+        if is_symlink {
+            self.symlink_target = std::fs::read_link(path)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string());
+        }
+    }
+
+    /// 递归计算文件夹的总大小和文件数量，返回 (总大小, 文件数量)
+    fn aggregate(&mut self) -> (u64, u64) {
+        let Some(children) = &mut self.children else {
+            // 不进入的、指向文件夹的符号链接不算文件
+            if !self.is_file {
+                return (0, 0);
             }
+            return (self.size.unwrap_or(0), 1);
+        };
+        let (mut total_size, mut file_count) = (0, 0);
+        for child in children {
+            let (size, count) = child.aggregate();
+            total_size += size;
+            file_count += count;
         }
+        self.total_size = Some(total_size);
+        self.file_count = Some(file_count);
+        (total_size, file_count)
     }
 
-    FolderEntry {
-        name,
-        is_file: false,
-        path,
-        children: Some(children),
+    fn push_child(&mut self, child: FolderEntry) {
+        if let Some(children) = &mut self.children {
+            children.push(child);
+        }
+    }
+}
+
+/// 遇到符号链接时的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// 跟随符号链接，检测到循环时跳过
+    Follow,
+    /// 忽略符号链接
+    Skip,
+    /// 把符号链接当作叶子节点列出，不进入
+    #[default]
+    List,
+}
+
+/// read_folder_structure 的选项
+/// 不传时与旧版行为一致：不限深度和数量，包含隐藏文件，不读取 .gitignore，
+/// 但符号链接只列出、不进入
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadFolderOptions {
+    /// 最大深度，根目录的直接子项深度为 1
+    pub max_depth: Option<usize>,
+    /// 最多返回多少个条目（不含根目录），超出后停止遍历并在根节点上标记 truncated
+    pub max_entries: Option<usize>,
+    /// 只保留匹配这些 glob 的文件（相对根目录的路径），文件夹不受影响
+    pub include: Vec<String>,
+    /// 排除匹配这些 glob 的文件和文件夹，例如 "node_modules"、"**/*.log"
+    pub exclude: Vec<String>,
+    /// 是否遵循 .gitignore / .ignore
+    pub respect_gitignore: bool,
+    /// 是否包含以 . 开头的隐藏文件
+    pub include_hidden: bool,
+    pub symlinks: SymlinkPolicy,
+    /// 是否返回大小、修改/创建时间和符号链接目标
+    pub metadata: bool,
+    /// 是否读取文件头推断 MIME 类型
+    pub mime: bool,
+    /// 是否计算每个文件夹的总大小和文件数量（会同时读取文件大小）
+    pub aggregate: bool,
+}
+
+impl Default for ReadFolderOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            max_entries: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: false,
+            include_hidden: true,
+            symlinks: SymlinkPolicy::default(),
+            metadata: false,
+            mime: false,
+            aggregate: false,
+        }
     }
 }
 
+fn unix_millis(time: std::time::SystemTime) -> Option<u64> {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+/// 根据文件头推断 MIME 类型
+/// 识别不出的二进制格式按扩展名推断，看起来是文本的按 text/plain 处理
+pub fn sniff_mime(path: &Path) -> Option<String> {
+    let mut head = Vec::with_capacity(8192);
+    std::fs::File::open(path)
+        .ok()?
+        .take(8192)
+        .read_to_end(&mut head)
+        .ok()?;
+    if let Some(kind) = infer::get(&head) {
+        return Some(kind.mime_type().to_string());
+    }
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy())
+        .unwrap_or_default();
+    let by_ext = super::prg::mime_from_ext(&ext);
+    if by_ext != "application/octet-stream" {
+        return Some(by_ext.to_string());
+    }
+    // 被截断的 UTF-8 多字节字符最多差 3 个字节
+    let looks_like_text = !head.contains(&0)
+        && match std::str::from_utf8(&head) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none() && head.len() - e.valid_up_to() < 4,
+        };
+    Some(
+        if looks_like_text {
+            "text/plain"
+        } else {
+            by_ext
+        }
+        .to_string(),
+    )
+}
+
+/// 通过 Channel 发送给前端的遍历进度
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadFolderProgress {
+    /// 已经找到的条目数
+    pub entries: usize,
+    /// 当前正在处理的路径
+    pub current: String,
+}
+
+/// 每找到多少个条目汇报一次进度
+const PROGRESS_INTERVAL: usize = 200;
+
+fn build_globset(patterns: &[String]) -> Result<Option<globset::GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            globset::Glob::new(pattern).map_err(|e| format!("无效的 glob {}: {}", pattern, e))?,
+        );
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("无效的 glob: {}", e))
+}
+
+/// 符号链接的目标是否在允许访问的范围内，scope 为 None 时不限制
+fn link_in_scope(scope: Option<&FsScope>, path: &Path) -> bool {
+    scope.is_none_or(|scope| scope.check(path).is_ok())
+}
+
+/// 按照选项遍历文件夹，构建嵌套结构
+/// scope: 跟随符号链接时，跳过目标不在范围内的链接；为 None 时不限制
+pub fn walk_folder_structure(
+    root: &Path,
+    options: &ReadFolderOptions,
+    scope: Option<&FsScope>,
+    token: &CancellationToken,
+    mut on_progress: impl FnMut(ReadFolderProgress),
+) -> Result<FolderEntry, String> {
+    let include = build_globset(&options.include)?;
+    let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+    for pattern in &options.exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| format!("无效的 glob {}: {}", pattern, e))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| format!("无效的 glob: {}", e))?;
+
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .hidden(!options.include_hidden)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .ignore(options.respect_gitignore)
+        .parents(options.respect_gitignore)
+        .require_git(false)
+        .follow_links(options.symlinks == SymlinkPolicy::Follow)
+        .max_depth(options.max_depth)
+        .overrides(overrides)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if options.symlinks == SymlinkPolicy::Follow {
+        if let Some(scope) = scope.map(|scope| std::sync::Arc::new(scope.snapshot())) {
+            builder.filter_entry(move |entry| {
+                !entry.path_is_symlink() || scope.check(entry.path()).is_ok()
+            });
+        }
+    }
+
+    // 栈中第 i 个元素是深度为 i 的、正在填充的文件夹
+    let mut stack = vec![FolderEntry::new(root, false)];
+    let mut count = 0;
+    let mut truncated = false;
+    // 读取失败、符号链接循环等错误的条目直接跳过，与旧版行为一致
+    for entry in builder.build().flatten() {
+        if token.is_cancelled() {
+            return Err("已取消".to_string());
+        }
+        let depth = entry.depth();
+        if depth == 0 {
+            continue;
+        }
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        let is_symlink = entry.path_is_symlink();
+        if is_symlink && options.symlinks == SymlinkPolicy::Skip {
+            continue;
+        }
+        // 不跟随时，指向文件夹的符号链接作为叶子节点列出
+        let is_dir = if is_symlink && options.symlinks == SymlinkPolicy::List {
+            false
+        } else {
+            file_type.is_dir()
+        };
+        if !is_dir {
+            let matched = include.as_ref().is_none_or(|include| {
+                entry
+                    .path()
+                    .strip_prefix(root)
+                    .is_ok_and(|relative| include.is_match(relative))
+            });
+            if !matched {
+                continue;
+            }
+        }
+
+        if options.max_entries.is_some_and(|max| count >= max) {
+            truncated = true;
+            break;
+        }
+        count += 1;
+        if count % PROGRESS_INTERVAL == 0 {
+            on_progress(ReadFolderProgress {
+                entries: count,
+                current: entry.path().to_string_lossy().to_string(),
+            });
+        }
+
+        while stack.len() > depth {
+            let finished = stack.pop().unwrap();
+            stack.last_mut().unwrap().push_child(finished);
+        }
+        let mut child = FolderEntry::new(entry.path(), !is_dir);
+        if !is_dir && is_symlink && std::fs::metadata(entry.path()).is_ok_and(|m| m.is_dir()) {
+            child.is_file = false;
+        }
+        if options.metadata || options.aggregate {
+            // 跟随符号链接时取目标的信息，否则取链接本身的信息
+            let metadata = if options.symlinks == SymlinkPolicy::Follow {
+                std::fs::metadata(entry.path())
+            } else {
+                std::fs::symlink_metadata(entry.path())
+            };
+            if let Ok(metadata) = metadata {
+                child.fill_metadata(entry.path(), &metadata, is_symlink);
+            }
+            if !options.metadata {
+                child.modified = None;
+                child.created = None;
+                child.symlink_target = None;
+            }
+        }
+        // 只列出的符号链接不读取范围外的目标
+        if options.mime && child.is_file && (!is_symlink || link_in_scope(scope, entry.path())) {
+            child.mime = sniff_mime(entry.path());
+        }
+        if is_dir {
+            stack.push(child);
+        } else {
+            stack.last_mut().unwrap().push_child(child);
+        }
+    }
+
+    while stack.len() > 1 {
+        let finished = stack.pop().unwrap();
+        stack.last_mut().unwrap().push_child(finished);
+    }
+    let mut root_entry = stack.pop().unwrap();
+    root_entry.truncated = truncated;
+    if options.metadata {
+        if let Ok(metadata) = std::fs::metadata(root) {
+            root_entry.fill_metadata(root, &metadata, false);
+        }
+    }
+    if options.aggregate {
+        root_entry.aggregate();
+    }
+    Ok(root_entry)
+}
+
+/// 递归读取文件夹结构，返回嵌套的文件夹结构
+/// options: 深度、数量、glob 过滤、.gitignore、隐藏文件、符号链接等选项
+/// task_id: 传入后可以通过 cancel_task 取消
+/// on_progress: 遍历进度
+#[tauri::command]
+pub async fn read_folder_structure(
+    scope: State<'_, FsScope>,
+    tasks: State<'_, TaskRegistry>,
+    path: String,
+    options: Option<ReadFolderOptions>,
+    task_id: Option<String>,
+    on_progress: Option<Channel<ReadFolderProgress>>,
+) -> Result<FolderEntry, String> {
+    scope.check(Path::new(&path))?;
+    let scope = scope.snapshot();
+    let task = tasks.register(task_id);
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        walk_folder_structure(
+            Path::new(&path),
+            &options,
+            Some(&scope),
+            task.token(),
+            |progress| {
+                if let Some(channel) = &on_progress {
+                    let _ = channel.send(progress);
+                }
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("读取文件夹失败: {}", e))?
+}
+
 /// 判断文件是否存在
 #[tauri::command]
-pub fn exists(path: String) -> bool {
-    std::path::Path::new(&path).exists()
+pub fn exists(scope: State<'_, FsScope>, path: String) -> Result<bool, FsError> {
+    scope.check(Path::new(&path))?;
+    Ok(std::path::Path::new(&path).exists())
 }
 
 /// 读取文件夹中的文件列表
 /// 如果文件夹不存在，返回空列表
 #[tauri::command]
-pub fn read_folder(path: String) -> Vec<String> {
+pub fn read_folder(scope: State<'_, FsScope>, path: String) -> Result<Vec<String>, FsError> {
+    scope.check(Path::new(&path))?;
     let mut files = Vec::new();
     if let Ok(entries) = std::fs::read_dir(path) {
         for entry in entries {
@@ -75,88 +622,1364 @@ pub fn read_folder(path: String) -> Vec<String> {
             }
         }
     }
-    files
+    Ok(files)
 }
 
-/// 读取一个文件夹中的全部文件，递归的读取
-/// 如果文件夹不存在，返回空列表
-/// fileExts: 要读取的文件扩展名列表，例如：[".txt", ".md"]
-#[tauri::command]
-pub fn read_folder_recursive(path: String, file_exts: Vec<String>) -> Vec<String> {
-    let mut files = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(file_name) = path.to_str() {
-                        if file_exts.iter().any(|ext| file_name.ends_with(ext)) {
-                            files.push(file_name.to_string());
-                        }
+/// 扫描到的文件路径
+/// path 是可显示的字符串；路径不是合法 Unicode 时，raw 中带上系统原始编码
+/// （Unix 为字节，Windows 为 UTF-16 小端字节），保证不丢失信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedPath {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<u8>>,
+}
+
+impl From<&Path> for ScannedPath {
+    fn from(path: &Path) -> Self {
+        match path.to_str() {
+            Some(path) => ScannedPath {
+                path: path.to_string(),
+                raw: None,
+            },
+            None => ScannedPath {
+                path: path.to_string_lossy().to_string(),
+                raw: Some(os_str_bytes(path.as_os_str())),
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+fn os_str_bytes(value: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    value.as_bytes().to_vec()
+}
+
+#[cfg(windows)]
+fn os_str_bytes(value: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    value.encode_wide().flat_map(u16::to_le_bytes).collect()
+}
+
+/// 扩展名匹配，不区分大小写
+/// exts 形如 [".txt", ".md"]，为空时匹配所有文件
+struct ExtMatcher(Vec<String>);
+
+impl ExtMatcher {
+    fn new(exts: &[String]) -> Self {
+        ExtMatcher(exts.iter().map(|ext| ext.to_lowercase()).collect())
+    }
+
+    fn is_match(&self, path: &Path) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy().to_lowercase();
+        self.0.iter().any(|ext| name.ends_with(ext.as_str()))
+    }
+}
+
+/// 多久没有凑满一批也要发送一次
+const SCAN_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// 多线程递归扫描文件夹，把匹配扩展名的文件分批交给 on_batch
+/// 跟随符号链接，检测到循环时跳过；返回匹配的文件总数
+/// scope: 跳过目标不在范围内的符号链接；为 None 时不限制
+pub fn scan_folder_parallel(
+    root: &Path,
+    file_exts: &[String],
+    scope: Option<&FsScope>,
+    token: &CancellationToken,
+    batch_size: usize,
+    mut on_batch: impl FnMut(Vec<PathBuf>),
+) -> Result<usize, String> {
+    let matcher = ExtMatcher::new(file_exts);
+    let walker = ignore::WalkBuilder::new(root)
+        .standard_filters(false)
+        .follow_links(true)
+        .build_parallel();
+    let (tx, rx) = std::sync::mpsc::channel::<PathBuf>();
+    let batch_size = batch_size.max(1);
+    let mut total = 0;
+
+    std::thread::scope(|scope| {
+        let matcher = &matcher;
+        scope.spawn(move || {
+            walker.run(|| {
+                let tx = tx.clone();
+                Box::new(move |entry| {
+                    if token.is_cancelled() {
+                        return ignore::WalkState::Quit;
+                    }
+                    let Ok(entry) = entry else {
+                        return ignore::WalkState::Continue;
+                    };
+                    if entry.path_is_symlink() && !link_in_scope(scope, entry.path()) {
+                        return ignore::WalkState::Skip;
+                    }
+                    let is_match = entry.file_type().is_some_and(|t| t.is_file())
+                        && matcher.is_match(entry.path());
+                    if is_match && tx.send(entry.into_path()).is_err() {
+                        return ignore::WalkState::Quit;
                     }
-                } else if path.is_dir() {
-                    let mut sub_files = read_folder_recursive(
-                        path.to_str().unwrap().to_string(),
-                        file_exts.clone(),
-                    );
-                    files.append(&mut sub_files);
+                    ignore::WalkState::Continue
+                })
+            })
+        });
+
+        let mut batch = Vec::new();
+        let mut last_flush = std::time::Instant::now();
+        loop {
+            match rx.recv_timeout(SCAN_FLUSH_INTERVAL) {
+                Ok(path) => {
+                    batch.push(path);
+                    total += 1;
                 }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            if batch.len() >= batch_size
+                || (!batch.is_empty() && last_flush.elapsed() >= SCAN_FLUSH_INTERVAL)
+            {
+                on_batch(std::mem::take(&mut batch));
+                last_flush = std::time::Instant::now();
             }
         }
+        if !batch.is_empty() {
+            on_batch(batch);
+        }
+    });
+
+    if token.is_cancelled() {
+        return Err("已取消".to_string());
+    }
+    Ok(total)
+}
+
+/// 读取一个文件夹中的全部文件，递归的读取
+/// 如果文件夹不存在，返回空列表
+/// fileExts: 要读取的文件扩展名列表，例如：[".txt", ".md"]，不区分大小写，为空时不匹配任何文件
+#[tauri::command]
+pub async fn read_folder_recursive(
+    scope: State<'_, FsScope>,
+    path: String,
+    file_exts: Vec<String>,
+) -> Result<Vec<String>, FsError> {
+    scope.check(Path::new(&path))?;
+    // 与旧版一致：不传扩展名时什么都不返回（scan_folder_recursive 则返回全部文件）
+    if file_exts.is_empty() {
+        return Ok(Vec::new());
     }
-    files
+    let scope = scope.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        scan_folder_parallel(
+            Path::new(&path),
+            &file_exts,
+            Some(&scope),
+            &CancellationToken::default(),
+            usize::MAX,
+            |batch| {
+                files.extend(
+                    batch
+                        .into_iter()
+                        .map(|path| path.to_string_lossy().to_string()),
+                )
+            },
+        )
+        .map_err(FsError::Other)?;
+        files.sort();
+        Ok(files)
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("读取文件夹失败: {}", e)))?
+}
+
+/// 递归扫描文件夹，匹配的文件分批通过 on_batch 流式发送给前端
+/// 返回匹配的文件总数
+/// task_id: 传入后可以通过 cancel_task 取消
+/// batch_size: 每批最多多少个路径，默认 256
+#[tauri::command]
+pub async fn scan_folder_recursive(
+    scope: State<'_, FsScope>,
+    tasks: State<'_, TaskRegistry>,
+    path: String,
+    file_exts: Vec<String>,
+    on_batch: Channel<Vec<ScannedPath>>,
+    task_id: Option<String>,
+    batch_size: Option<usize>,
+) -> Result<usize, String> {
+    scope.check(Path::new(&path))?;
+    let scope = scope.snapshot();
+    let task = tasks.register(task_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        scan_folder_parallel(
+            Path::new(&path),
+            &file_exts,
+            Some(&scope),
+            task.token(),
+            batch_size.unwrap_or(256),
+            |batch| {
+                let _ = on_batch.send(
+                    batch
+                        .iter()
+                        .map(|p| ScannedPath::from(p.as_path()))
+                        .collect(),
+                );
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("扫描文件夹失败: {}", e))?
 }
 
 /// 删除文件
 #[tauri::command]
-pub fn delete_file(path: String) -> Result<(), String> {
+pub fn delete_file(scope: State<'_, FsScope>, path: String) -> Result<(), String> {
+    scope.check(Path::new(&path))?;
     std::fs::remove_file(path).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// 删除方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeleteMode {
+    /// 移入回收站
+    #[default]
+    Trash,
+    /// 永久删除
+    Permanent,
+}
+
+/// delete_path 的选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DeleteOptions {
+    pub mode: DeleteMode,
+    /// 允许删除不为空的文件夹
+    pub recursive: bool,
+    /// 只列出将要删除的内容，不实际删除
+    pub dry_run: bool,
+}
+
+/// delete_path 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResult {
+    /// 被删除（dry_run 时为将要被删除）的所有文件和文件夹，父文件夹在前
+    pub entries: Vec<String>,
+    /// 移入回收站后的位置，只有 Linux 上能得到
+    pub trashed_to: Option<String>,
+}
+
+/// 列出 path 以及其中的所有内容，父文件夹在前
+/// 不跟随符号链接，符号链接本身作为一个条目
+pub fn list_removal(path: &Path) -> Result<Vec<PathBuf>, FsError> {
+    let mut entries = Vec::new();
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        let metadata = current
+            .symlink_metadata()
+            .map_err(|e| FsError::from_io(e, &current))?;
+        if metadata.is_dir() {
+            let mut children = std::fs::read_dir(&current)
+                .map_err(|e| FsError::from_io(e, &current))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| FsError::from_io(e, &current))?;
+            children.sort();
+            stack.extend(children.into_iter().rev());
+        }
+        entries.push(current);
+    }
+    Ok(entries)
+}
+
+/// 删除文件或文件夹
+/// 不为空的文件夹需要 recursive，否则返回 DirectoryNotEmpty
+pub fn delete_path_with_options(
+    path: &Path,
+    options: &DeleteOptions,
+) -> Result<DeleteResult, FsError> {
+    let metadata = path
+        .symlink_metadata()
+        .map_err(|e| FsError::from_io(e, path))?;
+    let entries = list_removal(path)?;
+    if metadata.is_dir() && entries.len() > 1 && !options.recursive {
+        return Err(FsError::DirectoryNotEmpty(path.display().to_string()));
+    }
+    let entries = entries
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    if options.dry_run {
+        return Ok(DeleteResult {
+            entries,
+            trashed_to: None,
+        });
+    }
+    let trashed_to = match options.mode {
+        DeleteMode::Trash => super::trash::move_to_trash(path)?,
+        DeleteMode::Permanent => {
+            // remove_dir_all 不会跟随符号链接
+            let result = if metadata.is_dir() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+            result.map_err(|e| FsError::from_io(e, path))?;
+            None
+        }
+    };
+    Ok(DeleteResult {
+        entries,
+        trashed_to: trashed_to.map(|p| p.to_string_lossy().to_string()),
+    })
+}
+
+/// 删除文件或文件夹，默认移入回收站
+#[tauri::command]
+pub async fn delete_path(
+    scope: State<'_, FsScope>,
+    path: String,
+    options: Option<DeleteOptions>,
+) -> Result<DeleteResult, FsError> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        delete_path_with_options(Path::new(&path), &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("删除失败: {}", e)))?
+}
+
+/// 带序号的文件名，用于避免重名：name、name (2).ext、name (3).ext...
+pub fn numbered_name(name: &std::ffi::OsStr, n: usize) -> std::ffi::OsString {
+    if n <= 1 {
+        return name.to_os_string();
+    }
+    let path = Path::new(name);
+    let mut numbered = path.file_stem().unwrap_or(name).to_os_string();
+    numbered.push(format!(" ({})", n));
+    if let Some(ext) = path.extension() {
+        numbered.push(".");
+        numbered.push(ext);
+    }
+    numbered
+}
+
+/// 目标已经存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    /// 返回错误
+    #[default]
+    Fail,
+    /// 替换已有的文件或文件夹
+    Overwrite,
+    /// 跳过，什么都不做
+    Skip,
+    /// 自动改名为 "file (2).prg"
+    Rename,
+}
+
+/// copy_path / move_path 的选项
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CopyOptions {
+    pub conflict: ConflictPolicy,
+    /// 保留修改时间和访问时间
+    pub preserve_timestamps: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            conflict: ConflictPolicy::default(),
+            preserve_timestamps: true,
+        }
+    }
+}
+
+/// copy_path / move_path 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyResult {
+    /// 实际的目标路径，自动改名时与传入的不同
+    pub destination: String,
+    /// 目标已存在且策略为 Skip
+    pub skipped: bool,
+}
+
+/// 按冲突策略确定目标路径，返回 None 表示跳过
+/// 返回的 bool 表示目标已存在、需要覆盖，已有的目标要等新内容准备好之后才替换
+fn resolve_destination(
+    source: &Path,
+    destination: &Path,
+    conflict: ConflictPolicy,
+) -> Result<Option<(PathBuf, bool)>, FsError> {
+    if destination.symlink_metadata().is_err() {
+        return Ok(Some((destination.to_path_buf(), false)));
+    }
+    let same_file = match (source.canonicalize(), destination.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    match conflict {
+        ConflictPolicy::Fail => Err(FsError::Other(format!(
+            "目标已存在: {}",
+            destination.display()
+        ))),
+        ConflictPolicy::Skip => Ok(None),
+        ConflictPolicy::Overwrite if same_file => Err(FsError::Other(format!(
+            "源和目标是同一个文件: {}",
+            destination.display()
+        ))),
+        ConflictPolicy::Overwrite => Ok(Some((destination.to_path_buf(), true))),
+        ConflictPolicy::Rename => {
+            let name = destination.file_name().ok_or_else(|| {
+                FsError::Other(format!("无效的文件路径: {}", destination.display()))
+            })?;
+            (2..)
+                .map(|n| destination.with_file_name(numbered_name(name, n)))
+                .find(|candidate| candidate.symlink_metadata().is_err())
+                .map(|candidate| Some((candidate, false)))
+                .ok_or_else(|| FsError::Other(format!("目标已存在: {}", destination.display())))
+        }
+    }
+}
+
+/// 删除文件或整个文件夹，不跟随符号链接
+fn remove_any(path: &Path) -> std::io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// 与 path 同目录的临时路径，例如 .name.1234-0.part
+fn sibling_temp_path(path: &Path, tag: &str) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}-{}.{}",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        tag
+    ));
+    path.with_file_name(name)
+}
+
+/// 用 from 替换已存在的 destination
+/// 旧的目标先改名放到一边，from 改名成功后才删除，失败时还原旧的目标，from 保持不变
+fn replace_path(from: &Path, destination: &Path) -> std::io::Result<()> {
+    let aside = sibling_temp_path(destination, "old");
+    std::fs::rename(destination, &aside)?;
+    if let Err(e) = std::fs::rename(from, destination) {
+        let _ = std::fs::rename(&aside, destination);
+        return Err(e);
+    }
+    let _ = remove_any(&aside);
+    Ok(())
+}
+
+/// 覆盖时先复制到同目录下的临时路径，完整复制后再替换目标，失败时目标不受影响
+fn copy_replacing(
+    source: &Path,
+    destination: &Path,
+    preserve_timestamps: bool,
+) -> Result<(), FsError> {
+    let staging = sibling_temp_path(destination, "part");
+    copy_recursive(source, &staging, preserve_timestamps)
+        .and_then(|()| {
+            replace_path(&staging, destination).map_err(|e| FsError::from_io(e, destination))
+        })
+        .inspect_err(|_| {
+            let _ = remove_any(&staging);
+        })
+}
+
+/// 复制到 resolve_destination 选出的路径，失败时不留下不完整的目标
+fn copy_to_target(
+    source: &Path,
+    target: &Path,
+    replace: bool,
+    preserve_timestamps: bool,
+) -> Result<(), FsError> {
+    if replace {
+        return copy_replacing(source, target, preserve_timestamps);
+    }
+    copy_recursive(source, target, preserve_timestamps).inspect_err(|_| {
+        let _ = remove_any(target);
+    })
+}
+
+fn file_times(metadata: &std::fs::Metadata) -> std::io::Result<std::fs::FileTimes> {
+    let mut times = std::fs::FileTimes::new().set_modified(metadata.modified()?);
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    Ok(times)
+}
+
+/// 复制单个文件
+/// 时间通过写入内容时打开的句柄设置，最后才设置权限，所以只读的文件也能保留时间
+fn copy_file(
+    source: &Path,
+    destination: &Path,
+    metadata: &std::fs::Metadata,
+    preserve_timestamps: bool,
+) -> Result<(), FsError> {
+    let mut reader = std::fs::File::open(source).map_err(|e| FsError::from_io(e, source))?;
+    let mut writer =
+        std::fs::File::create_new(destination).map_err(|e| FsError::from_io(e, destination))?;
+    std::io::copy(&mut reader, &mut writer).map_err(|e| FsError::from_io(e, destination))?;
+    if preserve_timestamps {
+        file_times(metadata)
+            .and_then(|times| writer.set_times(times))
+            .map_err(|e| FsError::from_io(e, destination))?;
+    }
+    writer
+        .set_permissions(metadata.permissions())
+        .map_err(|e| FsError::from_io(e, destination))
+}
+
+/// 递归复制，不跟随符号链接
+fn copy_recursive(
+    source: &Path,
+    destination: &Path,
+    preserve_timestamps: bool,
+) -> Result<(), FsError> {
+    let metadata = source
+        .symlink_metadata()
+        .map_err(|e| FsError::from_io(e, source))?;
+    if metadata.is_symlink() {
+        let target = std::fs::read_link(source).map_err(|e| FsError::from_io(e, source))?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, destination)
+            .map_err(|e| FsError::from_io(e, destination))?;
+        // 同样复制链接本身：复制链接指向的内容可能读取到允许范围之外的文件
+        // Windows 上需要开发者模式或管理员权限，否则返回错误
+        #[cfg(windows)]
+        {
+            let link = if source.metadata().is_ok_and(|m| m.is_dir()) {
+                std::os::windows::fs::symlink_dir(&target, destination)
+            } else {
+                std::os::windows::fs::symlink_file(&target, destination)
+            };
+            link.map_err(|e| FsError::from_io(e, destination))?;
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = target;
+            return Err(FsError::Other(format!(
+                "无法复制符号链接: {}",
+                source.display()
+            )));
+        }
+        return Ok(());
+    }
+    if metadata.is_dir() {
+        return copy_recursive_dir(source, destination, &metadata, preserve_timestamps);
+    }
+    copy_file(source, destination, &metadata, preserve_timestamps)
+}
+
+fn copy_recursive_dir(
+    source: &Path,
+    destination: &Path,
+    metadata: &std::fs::Metadata,
+    preserve_timestamps: bool,
+) -> Result<(), FsError> {
+    std::fs::create_dir(destination).map_err(|e| FsError::from_io(e, destination))?;
+    for entry in std::fs::read_dir(source).map_err(|e| FsError::from_io(e, source))? {
+        let entry = entry.map_err(|e| FsError::from_io(e, source))?;
+        copy_recursive(
+            &entry.path(),
+            &destination.join(entry.file_name()),
+            preserve_timestamps,
+        )?;
+    }
+    // 文件夹的时间在写入内容之后才设置，部分平台上无法打开文件夹，忽略失败
+    if preserve_timestamps {
+        let _ = file_times(metadata).and_then(|times| {
+            std::fs::File::open(destination).and_then(|dir| dir.set_times(times))
+        });
+    }
+    std::fs::set_permissions(destination, metadata.permissions())
+        .map_err(|e| FsError::from_io(e, destination))
+}
+
+/// 复制到自身内部会无限递归，覆盖源所在的文件夹会把源一起删掉
+fn check_not_inside(source: &Path, destination: &Path) -> Result<(), FsError> {
+    let source = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+    let parent = destination
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok());
+    if parent.is_some_and(|parent| parent.starts_with(&source)) {
+        return Err(FsError::Other(format!(
+            "不能复制或移动到自身内部: {}",
+            destination.display()
+        )));
+    }
+    if let Ok(destination) = destination.canonicalize() {
+        if source != destination && source.starts_with(&destination) {
+            return Err(FsError::Other(format!(
+                "目标包含源: {}",
+                destination.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 复制文件或文件夹
+pub fn copy_path_with_options(
+    source: &Path,
+    destination: &Path,
+    options: &CopyOptions,
+) -> Result<CopyResult, FsError> {
+    source
+        .symlink_metadata()
+        .map_err(|e| FsError::from_io(e, source))?;
+    check_not_inside(source, destination)?;
+    let Some((target, replace)) = resolve_destination(source, destination, options.conflict)?
+    else {
+        return Ok(CopyResult {
+            destination: destination.to_string_lossy().to_string(),
+            skipped: true,
+        });
+    };
+    copy_to_target(source, &target, replace, options.preserve_timestamps)?;
+    Ok(CopyResult {
+        destination: target.to_string_lossy().to_string(),
+        skipped: false,
+    })
+}
+
+/// 移动或重命名文件、文件夹
+/// 跨文件系统时先复制再删除源
+pub fn move_path_with_options(
+    source: &Path,
+    destination: &Path,
+    options: &CopyOptions,
+) -> Result<CopyResult, FsError> {
+    let metadata = source
+        .symlink_metadata()
+        .map_err(|e| FsError::from_io(e, source))?;
+    check_not_inside(source, destination)?;
+    let Some((target, replace)) = resolve_destination(source, destination, options.conflict)?
+    else {
+        return Ok(CopyResult {
+            destination: destination.to_string_lossy().to_string(),
+            skipped: true,
+        });
+    };
+    let moved = if replace {
+        replace_path(source, &target)
+    } else {
+        std::fs::rename(source, &target)
+    };
+    match moved {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_to_target(source, &target, replace, options.preserve_timestamps)?;
+            let removed = if metadata.is_dir() {
+                std::fs::remove_dir_all(source)
+            } else {
+                std::fs::remove_file(source)
+            };
+            removed.map_err(|e| FsError::from_io(e, source))?;
+        }
+        Err(e) => return Err(FsError::from_io(e, source)),
+    }
+    Ok(CopyResult {
+        destination: target.to_string_lossy().to_string(),
+        skipped: false,
+    })
+}
+
+/// 复制文件或文件夹，destination 是完整的目标路径
+/// options.conflict: 目标已存在时的处理方式，默认返回错误
+#[tauri::command]
+pub async fn copy_path(
+    scope: State<'_, FsScope>,
+    source: String,
+    destination: String,
+    options: Option<CopyOptions>,
+) -> Result<CopyResult, FsError> {
+    scope.check(Path::new(&source))?;
+    scope.check(Path::new(&destination))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        copy_path_with_options(
+            Path::new(&source),
+            Path::new(&destination),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("复制失败: {}", e)))?
+}
+
+/// 移动或重命名文件、文件夹，destination 是完整的目标路径
+/// options 同 copy_path
+#[tauri::command]
+pub async fn move_path(
+    scope: State<'_, FsScope>,
+    source: String,
+    destination: String,
+    options: Option<CopyOptions>,
+) -> Result<CopyResult, FsError> {
+    scope.check(Path::new(&source))?;
+    scope.check(Path::new(&destination))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        move_path_with_options(
+            Path::new(&source),
+            Path::new(&destination),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("移动失败: {}", e)))?
+}
+
+/// 猜测没有 BOM 的 UTF-16 文本的字节序
+/// ASCII 字符在 UTF-16 中会有一半字节为 0，按 0 出现在偶数位还是奇数位判断
+fn guess_utf16_without_bom(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let sample = &bytes[..bytes.len().min(4096)];
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+    if odd_zeros * 10 >= pairs * 3 && even_zeros * 10 < pairs {
+        Some(encoding_rs::UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 3 && odd_zeros * 10 < pairs {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// 自动检测文本编码
+/// 优先看 BOM，其次判断无 BOM 的 UTF-16，再尝试严格的 UTF-8，最后交给 chardetng 猜测（GBK、Shift-JIS 等）
+pub fn detect_encoding(bytes: &[u8]) -> &'static encoding_rs::Encoding {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    // NUL 也是合法的 UTF-8，所以要先判断 UTF-16
+    if let Some(encoding) = guess_utf16_without_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return encoding_rs::UTF_8;
+    }
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// 按指定的编码解码文本
+/// encoding 为 None 时要求是合法的 UTF-8；为 "auto" 时自动检测；
+/// 其他值按 WHATWG 编码标签解析，例如 "gbk"、"shift_jis"、"utf-16le"
+pub fn decode_text(bytes: &[u8], encoding: Option<&str>, path: &Path) -> Result<String, FsError> {
+    let encoding = match encoding.map(str::trim) {
+        None | Some("") => {
+            return String::from_utf8(bytes.to_vec()).map_err(|e| {
+                FsError::InvalidEncoding(format!(
+                    "{}: 不是有效的 UTF-8 文本: {}",
+                    path.display(),
+                    e
+                ))
+            })
+        }
+        Some(label) if label.eq_ignore_ascii_case("auto") => detect_encoding(bytes),
+        Some(label) => encoding_rs::Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| FsError::Other(format!("不支持的编码: {}", label)))?,
+    };
+    let (text, _, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(FsError::InvalidEncoding(format!(
+            "{}: 不是有效的 {} 文本",
+            path.display(),
+            encoding.name()
+        )));
+    }
+    Ok(text.into_owned())
+}
+
 /// 读取文件，返回字符串
+/// encoding: 不传时按 UTF-8 读取；传 "auto" 时自动检测编码；也可以直接指定编码，例如 "gbk"
 #[tauri::command]
-pub fn read_text_file(path: String) -> String {
-    let mut file = std::fs::File::open(path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
-    contents
+pub fn read_text_file(
+    scope: State<'_, FsScope>,
+    path: String,
+    encoding: Option<String>,
+) -> Result<String, FsError> {
+    let path = Path::new(&path);
+    scope.check(path)?;
+    if path.is_dir() {
+        return Err(FsError::IsADirectory(path.display().to_string()));
+    }
+    let bytes = std::fs::read(path).map_err(|e| FsError::from_io(e, path))?;
+    decode_text(&bytes, encoding.as_deref(), path)
 }
 
 /// 读取文件，返回base64
 #[tauri::command]
-pub fn read_file_base64(path: String) -> Result<String, String> {
+pub fn read_file_base64(scope: State<'_, FsScope>, path: String) -> Result<String, String> {
+    scope.check(Path::new(&path))?;
     Ok(general_purpose::STANDARD
         .encode(&std::fs::read(path).map_err(|e| format!("无法读取文件: {}", e))?))
 }
 
 /// 写入文件
+/// options.atomic 为 true 时原子写入，options.backup 为 true 时保留 .bak
 #[tauri::command]
-pub fn write_text_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(path, content).map_err(|e| e.to_string())?;
-    Ok(())
+pub fn write_text_file(
+    scope: State<'_, FsScope>,
+    path: String,
+    content: String,
+    options: Option<WriteOptions>,
+) -> Result<(), FsError> {
+    scope.check(Path::new(&path))?;
+    write_file_with_options(
+        Path::new(&path),
+        content.as_bytes(),
+        &options.unwrap_or_default(),
+    )
 }
 
 /// 写入文件，base64字符串
+/// options 同 write_text_file
 #[tauri::command]
-pub fn write_file_base64(content: String, path: String) -> Result<(), String> {
+pub fn write_file_base64(
+    scope: State<'_, FsScope>,
+    content: String,
+    path: String,
+    options: Option<WriteOptions>,
+) -> Result<(), FsError> {
+    scope.check(Path::new(&path))?;
     // 解码 Base64 内容
     let decoded_content = general_purpose::STANDARD
         .decode(content)
-        .map_err(|e| format!("解码失败: {}", e))?;
+        .map_err(|e| FsError::Other(format!("解码失败: {}", e)))?;
 
     // 写入文件
-    std::fs::write(&path, decoded_content).map_err(|e| {
-        eprintln!("写入文件失败: {}", e);
-        e.to_string()
-    })?;
-
-    Ok(())
+    write_file_with_options(
+        Path::new(&path),
+        &decoded_content,
+        &options.unwrap_or_default(),
+    )
+    .inspect_err(|e| eprintln!("写入文件失败: {}", e))
 }
 
 /// 创建文件夹
 /// 如果创建成功，则返回true，如果创建失败则返回false
 #[tauri::command]
-pub fn create_folder(path: String) -> bool {
-    std::fs::create_dir_all(&path).is_ok()
+pub fn create_folder(scope: State<'_, FsScope>, path: String) -> Result<bool, FsError> {
+    scope.check(Path::new(&path))?;
+    Ok(std::fs::create_dir_all(&path).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_write_replaces_target_and_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("project.prg");
+        std::fs::write(&path, b"old").unwrap();
+
+        write_file_atomic(&path, b"new", true).expect("atomic write should succeed");
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(
+            std::fs::read(dir.path().join("project.prg.bak")).unwrap(),
+            b"old"
+        );
+        // 临时文件不应残留
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_write_keeps_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("real")).unwrap();
+        let target = dir.path().join("real/project.prg");
+        let link = dir.path().join("project.prg");
+        std::fs::write(&target, b"old").unwrap();
+        std::os::unix::fs::symlink("real/project.prg", &link).unwrap();
+
+        write_file_atomic(&link, b"new", true).expect("atomic write should succeed");
+
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+        assert_eq!(
+            std::fs::read(dir.path().join("project.prg.bak")).unwrap(),
+            b"old"
+        );
+    }
+
+    #[test]
+    fn atomic_write_rejects_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = write_file_atomic(dir.path(), b"data", false).unwrap_err();
+        assert!(matches!(error, FsError::IsADirectory(_)));
+    }
+
+    #[test]
+    fn maps_io_error_kinds() {
+        let path = Path::new("missing");
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(
+            FsError::from_io(not_found, path),
+            FsError::NotFound(_)
+        ));
+        let full = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        assert!(matches!(FsError::from_io(full, path), FsError::DiskFull(_)));
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            FsError::from_io(denied, path),
+            FsError::PermissionDenied(_)
+        ));
+    }
+
+    #[test]
+    fn decodes_text_in_common_encodings() {
+        let path = Path::new("text.txt");
+        let (gbk, _, _) = encoding_rs::GBK.encode("中文文本");
+        assert!(decode_text(&gbk, None, path).is_err());
+        assert_eq!(decode_text(&gbk, Some("gbk"), path).unwrap(), "中文文本");
+
+        let utf16le: Vec<u8> = "hello, world"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        assert_eq!(detect_encoding(&utf16le), encoding_rs::UTF_16LE);
+        assert_eq!(
+            decode_text(&utf16le, Some("auto"), path).unwrap(),
+            "hello, world"
+        );
+
+        let error = decode_text(&[0xff, 0xfe, 0xfd], None, path).unwrap_err();
+        assert!(matches!(error, FsError::InvalidEncoding(_)));
+    }
+
+    fn names(entry: &FolderEntry) -> Vec<String> {
+        let mut result = Vec::new();
+        for child in entry.children.iter().flatten() {
+            result.push(child.name.clone());
+            result.extend(
+                names(child)
+                    .into_iter()
+                    .map(|n| format!("{}/{}", child.name, n)),
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn walks_folders_with_filters_and_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/deep/deeper")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("src/main.md"), "").unwrap();
+        std::fs::write(root.join("src/debug.log"), "").unwrap();
+        std::fs::write(root.join("src/deep/deeper/a.md"), "").unwrap();
+        std::fs::write(root.join("node_modules/pkg/index.js"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root, root.join("src/loop")).unwrap();
+
+        let token = CancellationToken::default();
+        let options = ReadFolderOptions {
+            exclude: vec!["node_modules".to_string()],
+            respect_gitignore: true,
+            include_hidden: false,
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let tree = walk_folder_structure(root, &options, None, &token, |_| {}).unwrap();
+        assert_eq!(names(&tree), vec!["src", "src/deep", "src/main.md"]);
+
+        let options = ReadFolderOptions {
+            include: vec!["*.md".to_string()],
+            exclude: vec!["node_modules".to_string()],
+            ..Default::default()
+        };
+        let tree = walk_folder_structure(root, &options, None, &token, |_| {}).unwrap();
+        assert!(names(&tree).contains(&"src/deep/deeper/a.md".to_string()));
+        assert!(!names(&tree).iter().any(|n| n.ends_with(".log")));
+
+        let options = ReadFolderOptions {
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        let tree = walk_folder_structure(root, &options, None, &token, |_| {}).unwrap();
+        assert!(tree.truncated);
+
+        token.cancel();
+        assert!(
+            walk_folder_structure(root, &ReadFolderOptions::default(), None, &token, |_| {})
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reports_metadata_mime_and_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("images")).unwrap();
+        std::fs::write(root.join("notes"), "plain text without extension").unwrap();
+        std::fs::write(
+            root.join("images/pic.bin"),
+            [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0],
+        )
+        .unwrap();
+
+        let options = ReadFolderOptions {
+            metadata: true,
+            mime: true,
+            aggregate: true,
+            ..Default::default()
+        };
+        let tree =
+            walk_folder_structure(root, &options, None, &CancellationToken::default(), |_| {})
+                .unwrap();
+        assert_eq!(tree.file_count, Some(2));
+        assert_eq!(tree.total_size, Some(28 + 10));
+
+        let children = tree.children.as_ref().unwrap();
+        let images = children.iter().find(|c| c.name == "images").unwrap();
+        let pic = &images.children.as_ref().unwrap()[0];
+        assert_eq!(pic.mime.as_deref(), Some("image/png"));
+        assert_eq!(pic.size, Some(10));
+        assert!(pic.modified.is_some());
+        let notes = children.iter().find(|c| c.name == "notes").unwrap();
+        assert_eq!(notes.mime.as_deref(), Some("text/plain"));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("images"), root.join("link")).unwrap();
+            let options = ReadFolderOptions {
+                symlinks: SymlinkPolicy::List,
+                ..options
+            };
+            let tree =
+                walk_folder_structure(root, &options, None, &CancellationToken::default(), |_| {})
+                    .unwrap();
+            assert_eq!(tree.file_count, Some(2));
+            let children = tree.children.as_ref().unwrap();
+            let link = children.iter().find(|c| c.name == "link").unwrap();
+            assert!(!link.is_file);
+            assert!(link.symlink_target.is_some());
+        }
+    }
+
+    #[test]
+    fn scans_in_parallel_with_case_insensitive_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for i in 0..20 {
+            let sub = root.join(format!("dir{}", i));
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(sub.join("a.PRG"), "").unwrap();
+            std::fs::write(sub.join("b.prg"), "").unwrap();
+            std::fs::write(sub.join("c.txt"), "").unwrap();
+        }
+
+        let mut found = Vec::new();
+        let total = scan_folder_parallel(
+            root,
+            &[".prg".to_string()],
+            None,
+            &CancellationToken::default(),
+            7,
+            |batch| {
+                assert!(batch.len() <= 7);
+                found.extend(batch);
+            },
+        )
+        .unwrap();
+        assert_eq!(total, 40);
+        assert_eq!(found.len(), 40);
+
+        let token = CancellationToken::default();
+        token.cancel();
+        assert!(scan_folder_parallel(root, &[], None, &token, 10, |_| {}).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinks_that_leave_the_scope() {
+        use crate::cmd::scope::ScopeKind;
+
+        let dir = tempfile::tempdir().unwrap();
+        let allowed = dir.path().join("allowed");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(allowed.join("inner")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(allowed.join("inner/a.prg"), "").unwrap();
+        std::fs::write(outside.join("secret.prg"), "").unwrap();
+        std::os::unix::fs::symlink(&outside, allowed.join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.prg"), allowed.join("b.prg")).unwrap();
+        std::os::unix::fs::symlink(allowed.join("inner"), allowed.join("alias")).unwrap();
+        let scope = FsScope::default();
+        scope.allow(&allowed, ScopeKind::Project).unwrap();
+
+        let mut found = Vec::new();
+        scan_folder_parallel(
+            &allowed,
+            &[".prg".to_string()],
+            Some(&scope),
+            &CancellationToken::default(),
+            usize::MAX,
+            |batch| found.extend(batch),
+        )
+        .unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![allowed.join("alias/a.prg"), allowed.join("inner/a.prg")]
+        );
+
+        // 默认只列出符号链接，不进入
+        let token = CancellationToken::default();
+        let tree = walk_folder_structure(
+            &allowed,
+            &ReadFolderOptions::default(),
+            Some(&scope),
+            &token,
+            |_| {},
+        )
+        .unwrap();
+        assert!(!names(&tree).iter().any(|n| n.starts_with("escape/")));
+        assert!(names(&tree).contains(&"escape".to_string()));
+
+        let options = ReadFolderOptions {
+            symlinks: SymlinkPolicy::Follow,
+            ..Default::default()
+        };
+        let tree = walk_folder_structure(&allowed, &options, Some(&scope), &token, |_| {}).unwrap();
+        let listed = names(&tree);
+        assert!(listed.contains(&"alias/a.prg".to_string()));
+        assert!(!listed
+            .iter()
+            .any(|n| n.starts_with("escape") || n == "b.prg"));
+    }
+
+    #[test]
+    fn deletes_folders_recursively_with_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        std::fs::create_dir_all(folder.join("sub")).unwrap();
+        std::fs::write(folder.join("a.prg"), b"a").unwrap();
+        std::fs::write(folder.join("sub/b.md"), b"b").unwrap();
+
+        let permanent = |recursive, dry_run| DeleteOptions {
+            mode: DeleteMode::Permanent,
+            recursive,
+            dry_run,
+        };
+        assert!(matches!(
+            delete_path_with_options(&folder, &permanent(false, false)),
+            Err(FsError::DirectoryNotEmpty(_))
+        ));
+
+        let listed = delete_path_with_options(&folder, &permanent(true, true)).unwrap();
+        let relative: Vec<_> = listed
+            .entries
+            .iter()
+            .map(|p| Path::new(p).strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            relative,
+            ["folder", "folder/a.prg", "folder/sub", "folder/sub/b.md"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+        assert!(folder.join("sub/b.md").exists());
+
+        let removed = delete_path_with_options(&folder, &permanent(true, false)).unwrap();
+        assert_eq!(removed.entries, listed.entries);
+        assert!(!folder.exists());
+        assert!(matches!(
+            delete_path_with_options(&folder, &permanent(true, false)),
+            Err(FsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn copies_and_moves_with_conflict_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("project");
+        std::fs::create_dir_all(source.join("attachments")).unwrap();
+        std::fs::write(source.join("file.prg"), b"prg").unwrap();
+        std::fs::write(source.join("attachments/a.png"), b"png").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(source.join("file.prg"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let options = |conflict| CopyOptions {
+            conflict,
+            preserve_timestamps: true,
+        };
+        let copy = dir.path().join("copy");
+        copy_path_with_options(&source, &copy, &options(ConflictPolicy::Fail)).unwrap();
+        assert_eq!(
+            std::fs::read(copy.join("attachments/a.png")).unwrap(),
+            b"png"
+        );
+        assert_eq!(
+            std::fs::metadata(copy.join("file.prg"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            old
+        );
+        assert!(copy_path_with_options(
+            &source,
+            &source.join("inner"),
+            &options(ConflictPolicy::Fail)
+        )
+        .is_err());
+
+        let file = source.join("file.prg");
+        let target = copy.join("file.prg");
+        assert!(copy_path_with_options(&file, &target, &options(ConflictPolicy::Fail)).is_err());
+        assert!(
+            copy_path_with_options(&file, &target, &options(ConflictPolicy::Skip))
+                .unwrap()
+                .skipped
+        );
+        let renamed =
+            copy_path_with_options(&file, &target, &options(ConflictPolicy::Rename)).unwrap();
+        assert!(renamed.destination.ends_with("file (2).prg"));
+        let renamed =
+            copy_path_with_options(&file, &target, &options(ConflictPolicy::Rename)).unwrap();
+        assert!(renamed.destination.ends_with("file (3).prg"));
+
+        std::fs::write(&target, b"changed").unwrap();
+        copy_path_with_options(&file, &target, &options(ConflictPolicy::Overwrite)).unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"prg");
+
+        let moved = dir.path().join("moved");
+        move_path_with_options(&copy, &moved, &options(ConflictPolicy::Fail)).unwrap();
+        assert!(!copy.exists());
+        assert_eq!(std::fs::read(moved.join("file (2).prg")).unwrap(), b"prg");
+        move_path_with_options(&source, &moved, &options(ConflictPolicy::Overwrite)).unwrap();
+        assert!(!source.exists());
+        assert!(!moved.join("file (2).prg").exists());
+        assert!(move_path_with_options(
+            &moved.join("file.prg"),
+            &moved,
+            &options(ConflictPolicy::Overwrite)
+        )
+        .is_err());
+        assert!(moved.join("file.prg").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_the_destination_when_overwriting_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.prg"), b"new").unwrap();
+        // 套接字无法复制，复制会在中途失败
+        let _socket = std::os::unix::net::UnixListener::bind(source.join("z.sock")).unwrap();
+        let destination = dir.path().join("destination");
+        std::fs::create_dir_all(&destination).unwrap();
+        std::fs::write(destination.join("a.prg"), b"old").unwrap();
+
+        let options = CopyOptions {
+            conflict: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        assert!(copy_path_with_options(&source, &destination, &options).is_err());
+        assert_eq!(std::fs::read(destination.join("a.prg")).unwrap(), b"old");
+        // 没有留下临时文件夹
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        // 不覆盖时也不留下不完整的目标
+        let fresh = dir.path().join("fresh");
+        assert!(copy_path_with_options(&source, &fresh, &CopyOptions::default()).is_err());
+        assert!(fresh.symlink_metadata().is_err());
+
+        std::fs::remove_file(source.join("z.sock")).unwrap();
+        copy_path_with_options(&source, &destination, &options).unwrap();
+        assert_eq!(std::fs::read(destination.join("a.prg")).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_read_only_files_with_timestamps() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        let file = source.join("a.prg");
+        std::fs::write(&file, b"read only").unwrap();
+        let modified =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o444)).unwrap();
+
+        let destination = dir.path().join("destination");
+        copy_path_with_options(&source, &destination, &CopyOptions::default()).unwrap();
+        let copied = destination.join("a.prg");
+        assert_eq!(std::fs::read(&copied).unwrap(), b"read only");
+        let metadata = std::fs::metadata(&copied).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o444);
+        assert_eq!(metadata.modified().unwrap(), modified);
+
+        // 覆盖已有的只读文件
+        let options = CopyOptions {
+            conflict: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        copy_path_with_options(&file, &copied, &options).unwrap();
+        assert_eq!(
+            std::fs::metadata(&copied).unwrap().modified().unwrap(),
+            modified
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_non_utf8_paths_lossless() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/\xff.prg"));
+        let scanned = ScannedPath::from(path);
+        assert_eq!(scanned.raw.as_deref(), Some(&b"/tmp/\xff.prg"[..]));
+        assert!(scanned.path.ends_with(".prg"));
+    }
 }
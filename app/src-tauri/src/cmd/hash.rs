@@ -0,0 +1,416 @@
+//! 内容哈希与重复附件检测
+//!
+//! 附件以 attachments/{uuid}.{ext} 保存在 .prg 中，同一张图片粘贴两次就会存两份。
+//! 这里按内容计算哈希，找出一个工程内或者一个文件夹下所有 .prg 之间重复的附件。
+//! 先用 ZIP 中央目录里记录的大小分组，只有大小相同的附件才需要读取并计算哈希。
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::ipc::{InvokeBody, Request};
+use tauri::State;
+
+use super::fs::{scan_folder_parallel, FsError};
+use super::prg::{open_archive, parse_attachment_name};
+use super::scope::FsScope;
+use super::task::{CancellationToken, TaskRegistry};
+
+/// 哈希算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = FsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            _ => Err(FsError::Other(format!("不支持的哈希算法: {}", s))),
+        }
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 流式计算哈希，实现了 Write，可以直接 io::copy 进来
+pub enum Hasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Hasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    /// 十六进制小写
+    pub fn finalize(self) -> String {
+        match self {
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            Hasher::Sha256(hasher) => to_hex(&hasher.finalize()),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn hash_bytes_with(data: &[u8], algorithm: HashAlgorithm) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+pub fn hash_reader(mut reader: impl Read, algorithm: HashAlgorithm) -> std::io::Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+pub fn hash_file_with(path: &Path, algorithm: HashAlgorithm) -> Result<String, FsError> {
+    if path.is_dir() {
+        return Err(FsError::IsADirectory(path.display().to_string()));
+    }
+    let file = std::fs::File::open(path).map_err(|e| FsError::from_io(e, path))?;
+    hash_reader(std::io::BufReader::new(file), algorithm).map_err(|e| FsError::from_io(e, path))
+}
+
+/// .prg 中的一个附件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentRef {
+    /// 所在的 .prg 文件
+    pub path: String,
+    pub id: String,
+    pub ext: String,
+}
+
+/// 内容相同的一组附件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub hash: String,
+    /// 单个附件的大小
+    pub size: u64,
+    pub attachments: Vec<AttachmentRef>,
+}
+
+/// 从中央目录中列出附件和它们的大小，不读取内容
+fn list_attachments(path: &Path) -> Result<Vec<(AttachmentRef, u64)>, String> {
+    let mut archive = open_archive(path)?;
+    let path_string = path.to_string_lossy().to_string();
+    let mut attachments = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("无法读取 {}: {}", path_string, e))?;
+        if let Some((id, ext)) = parse_attachment_name(file.name()) {
+            attachments.push((
+                AttachmentRef {
+                    path: path_string.clone(),
+                    id,
+                    ext,
+                },
+                file.size(),
+            ));
+        }
+    }
+    Ok(attachments)
+}
+
+/// 计算一个 .prg 中指定附件的哈希
+fn hash_attachments(
+    path: &Path,
+    attachments: &[&AttachmentRef],
+    algorithm: HashAlgorithm,
+) -> Result<Vec<String>, String> {
+    let mut archive = open_archive(path)?;
+    attachments
+        .iter()
+        .map(|attachment| {
+            let name = format!("attachments/{}.{}", attachment.id, attachment.ext);
+            let file = archive
+                .by_name(&name)
+                .map_err(|e| format!("无法读取 {}: {}", name, e))?;
+            hash_reader(file, algorithm).map_err(|e| format!("无法读取 {}: {}", name, e))
+        })
+        .collect()
+}
+
+/// 在多个 .prg 中找出内容重复的附件，大小相同的才计算哈希
+/// 结果按可以节省的空间从大到小排列
+pub fn find_duplicates(
+    files: &[PathBuf],
+    algorithm: HashAlgorithm,
+    token: &CancellationToken,
+) -> Result<Vec<DuplicateGroup>, String> {
+    // 无法打开的文件跳过，不影响其他文件
+    let listed: Vec<(AttachmentRef, u64)> = files
+        .par_iter()
+        .filter_map(|path| list_attachments(path).ok())
+        .flatten()
+        .collect();
+
+    let mut size_counts: HashMap<u64, usize> = HashMap::new();
+    for (_, size) in &listed {
+        *size_counts.entry(*size).or_default() += 1;
+    }
+    let mut candidates: HashMap<&str, Vec<(&AttachmentRef, u64)>> = HashMap::new();
+    for (attachment, size) in &listed {
+        if size_counts[size] > 1 {
+            candidates
+                .entry(attachment.path.as_str())
+                .or_default()
+                .push((attachment, *size));
+        }
+    }
+
+    let hashed: Vec<(String, u64, AttachmentRef)> = candidates
+        .into_par_iter()
+        .filter_map(|(path, attachments)| {
+            if token.is_cancelled() {
+                return None;
+            }
+            let refs: Vec<&AttachmentRef> = attachments.iter().map(|(a, _)| *a).collect();
+            let hashes = hash_attachments(Path::new(path), &refs, algorithm).ok()?;
+            Some(
+                hashes
+                    .into_iter()
+                    .zip(attachments)
+                    .map(|(hash, (attachment, size))| (hash, size, attachment.clone()))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect();
+    if token.is_cancelled() {
+        return Err("已取消".to_string());
+    }
+
+    let mut groups: HashMap<(String, u64), Vec<AttachmentRef>> = HashMap::new();
+    for (hash, size, attachment) in hashed {
+        groups.entry((hash, size)).or_default().push(attachment);
+    }
+    let mut groups: Vec<DuplicateGroup> = groups
+        .into_iter()
+        .filter(|(_, attachments)| attachments.len() > 1)
+        .map(|((hash, size), mut attachments)| {
+            attachments.sort_by(|a, b| (&a.path, &a.id).cmp(&(&b.path, &b.id)));
+            DuplicateGroup {
+                hash,
+                size,
+                attachments,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        let saved = |g: &DuplicateGroup| g.size * (g.attachments.len() as u64 - 1);
+        saved(b).cmp(&saved(a)).then_with(|| a.hash.cmp(&b.hash))
+    });
+    Ok(groups)
+}
+
+/// 计算文件内容的哈希，默认 BLAKE3
+#[tauri::command]
+pub async fn hash_file(
+    scope: State<'_, FsScope>,
+    path: String,
+    algorithm: Option<HashAlgorithm>,
+) -> Result<String, FsError> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        hash_file_with(Path::new(&path), algorithm.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("计算哈希失败: {}", e)))?
+}
+
+/// 计算二进制数据的哈希
+/// 请求体是二进制数据，算法放在请求头 `algorithm` 中，默认 BLAKE3
+#[tauri::command]
+pub async fn hash_bytes(request: Request<'_>) -> Result<String, FsError> {
+    let algorithm = match request
+        .headers()
+        .get("algorithm")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(algorithm) => algorithm.parse()?,
+        None => HashAlgorithm::default(),
+    };
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err(FsError::Other("请求体必须是二进制数据".to_string()));
+    };
+    let data = data.clone();
+    tauri::async_runtime::spawn_blocking(move || hash_bytes_with(&data, algorithm))
+        .await
+        .map_err(|e| FsError::Other(format!("计算哈希失败: {}", e)))
+}
+
+/// 找出一个 .prg 中内容重复的附件
+#[tauri::command]
+pub async fn find_duplicate_attachments(
+    scope: State<'_, FsScope>,
+    path: String,
+    algorithm: Option<HashAlgorithm>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        // 单个文件打不开时应该报错，而不是返回空结果
+        open_archive(Path::new(&path))?;
+        find_duplicates(
+            &[PathBuf::from(path)],
+            algorithm.unwrap_or_default(),
+            &CancellationToken::default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("查找重复附件失败: {}", e))?
+}
+
+/// 找出一个文件夹下所有 .prg 之间内容重复的附件
+/// task_id: 传入后可以通过 cancel_task 取消
+#[tauri::command]
+pub async fn find_duplicate_attachments_in_folder(
+    scope: State<'_, FsScope>,
+    tasks: State<'_, TaskRegistry>,
+    path: String,
+    algorithm: Option<HashAlgorithm>,
+    task_id: Option<String>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    scope.check(Path::new(&path))?;
+    let scope = scope.snapshot();
+    let task = tasks.register(task_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        scan_folder_parallel(
+            Path::new(&path),
+            &[".prg".to_string()],
+            Some(&scope),
+            task.token(),
+            usize::MAX,
+            |batch| files.extend(batch),
+        )?;
+        find_duplicates(&files, algorithm.unwrap_or_default(), task.token())
+    })
+    .await
+    .map_err(|e| format!("查找重复附件失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::{PrgAttachment, PrgProject};
+
+    fn attachment(id: &str, data: &[u8]) -> PrgAttachment {
+        PrgAttachment {
+            id: id.to_string(),
+            ext: "png".to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn hashes_with_both_algorithms() {
+        assert_eq!(
+            hash_bytes_with(b"abc", HashAlgorithm::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_bytes_with(b"abc", HashAlgorithm::Blake3),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(
+            hash_reader(&b"abc"[..], HashAlgorithm::Blake3).unwrap(),
+            hash_bytes_with(b"abc", HashAlgorithm::Blake3)
+        );
+        assert_eq!(
+            "SHA-256".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Sha256
+        );
+    }
+
+    #[test]
+    fn finds_duplicate_attachments_across_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.prg");
+        let b = dir.path().join("b.prg");
+        PrgProject {
+            attachments: vec![
+                attachment("a1", b"same image"),
+                attachment("a2", b"same image"),
+                // 大小相同但内容不同
+                attachment("a3", b"other imag"),
+                attachment("a4", b"unique"),
+            ],
+            ..Default::default()
+        }
+        .save(&a)
+        .unwrap();
+        PrgProject {
+            attachments: vec![attachment("b1", b"same image")],
+            ..Default::default()
+        }
+        .save(&b)
+        .unwrap();
+
+        let token = CancellationToken::default();
+        let groups =
+            find_duplicates(std::slice::from_ref(&a), HashAlgorithm::Blake3, &token).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0]
+                .attachments
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>(),
+            ["a1", "a2"]
+        );
+
+        let groups = find_duplicates(&[a, b], HashAlgorithm::Sha256, &token).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size, 10);
+        assert_eq!(
+            groups[0].hash,
+            hash_bytes_with(b"same image", HashAlgorithm::Sha256)
+        );
+        assert_eq!(
+            groups[0]
+                .attachments
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>(),
+            ["a1", "a2", "b1"]
+        );
+    }
+}
@@ -0,0 +1,687 @@
+//! 工程的本地历史版本
+//!
+//! 每次保存后记录一份快照，存放在应用数据目录的 project-history 下：
+//! - objects/{前两位}/{blake3}：按内容寻址的 ZIP 条目内容，相同的附件、未改动的舞台在所有快照间只存一份
+//! - projects/{路径哈希}/{毫秒时间戳}-{快照 ID}.json：快照清单，按原顺序记录每个条目的名称和内容哈希
+//!
+//! 快照 ID 由所有条目的名称和内容决定，内容没有变化的保存不会产生新快照。
+//! 记录新快照时按保留策略清理旧快照（例如一天内每小时保留一份、一个月内每天保留一份），
+//! 不再被任何快照引用的内容随之删除。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::diff::{self, PrgDiff};
+use super::fs::write_file_atomic;
+use super::prg::{open_archive, parse_attachment_name, PrgProject};
+use super::scope::FsScope;
+
+const HISTORY_DIR: &str = "project-history";
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+const WEEK_MS: u64 = 7 * DAY_MS;
+
+/// 清理内容时不能有快照正在写入，所有修改历史记录的操作串行执行
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn history_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(HISTORY_DIR))
+        .map_err(|e| format!("无法获取数据目录: {}", e))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 快照中的一个 ZIP 条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SnapshotEntry {
+    name: String,
+    hash: String,
+    size: u64,
+}
+
+/// 快照清单，即 projects/{路径哈希}/ 下的 json 文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotManifest {
+    id: String,
+    time: u64,
+    path: String,
+    entries: Vec<SnapshotEntry>,
+}
+
+/// 一份历史快照的描述
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySnapshot {
+    pub id: String,
+    /// 毫秒时间戳
+    pub time: u64,
+    /// 还原后的文件大小（不含 ZIP 结构）
+    pub size: u64,
+    pub attachments: usize,
+}
+
+impl From<&SnapshotManifest> for HistorySnapshot {
+    fn from(manifest: &SnapshotManifest) -> Self {
+        HistorySnapshot {
+            id: manifest.id.clone(),
+            time: manifest.time,
+            size: manifest.entries.iter().map(|entry| entry.size).sum(),
+            attachments: manifest
+                .entries
+                .iter()
+                .filter(|entry| parse_attachment_name(&entry.name).is_some())
+                .count(),
+        }
+    }
+}
+
+/// record_prg_history 的结果，内容没有变化时 created 为 false，snapshot 是已有的最新快照
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecordResult {
+    pub snapshot: HistorySnapshot,
+    pub created: bool,
+}
+
+/// 保留策略
+/// 最近的 keepRecent 份总是保留；此外在 hourlyHours 小时内每小时、dailyDays 天内每天、
+/// weeklyWeeks 周内每周各保留最新的一份，更早的快照被删除
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    pub keep_recent: usize,
+    pub hourly_hours: u64,
+    pub daily_days: u64,
+    pub weekly_weeks: u64,
+    /// 本地时间相对 UTC 的偏移，决定按天划分时一天从几点开始
+    pub utc_offset_minutes: i64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_recent: 10,
+            hourly_hours: 24,
+            daily_days: 30,
+            weekly_weeks: 0,
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// 需要保留的快照，times 按从新到旧排列，返回对应的下标
+    fn retained(&self, times: &[u64], now: u64) -> HashSet<usize> {
+        let mut kept: HashSet<usize> = (0..times.len().min(self.keep_recent)).collect();
+        let offset = self.utc_offset_minutes * 60 * 1000;
+        for (bucket, span) in [
+            (HOUR_MS, self.hourly_hours * HOUR_MS),
+            (DAY_MS, self.daily_days * DAY_MS),
+            (WEEK_MS, self.weekly_weeks * WEEK_MS),
+        ] {
+            let mut seen = HashSet::new();
+            for (i, &time) in times.iter().enumerate() {
+                if now.saturating_sub(time) >= span {
+                    continue;
+                }
+                if seen.insert((time as i64 + offset).div_euclid(bucket as i64)) {
+                    kept.insert(i);
+                }
+            }
+        }
+        kept
+    }
+}
+
+/// prune_prg_history 的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPruneResult {
+    pub removed_snapshots: usize,
+    pub removed_objects: usize,
+    pub freed_bytes: u64,
+}
+
+/// 一个历史记录目录
+pub struct HistoryStore {
+    root: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(root: PathBuf) -> Self {
+        HistoryStore { root }
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    fn projects_dir(&self) -> PathBuf {
+        self.root.join("projects")
+    }
+
+    fn project_dir(&self, path: &Path) -> PathBuf {
+        // 同一个文件用不同的写法打开时也应该对应同一份历史
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let key = blake3::hash(path.as_os_str().as_encoded_bytes()).to_hex();
+        self.projects_dir().join(&key[..16])
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.objects_dir().join(&hash[..2]).join(hash)
+    }
+
+    fn put_object(&self, bytes: &[u8]) -> Result<String, String> {
+        let hash = blake3::hash(bytes).to_hex().to_string();
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            std::fs::create_dir_all(path.parent().unwrap())
+                .map_err(|e| format!("无法创建历史记录目录: {}", e))?;
+            write_file_atomic(&path, bytes, false)?;
+        }
+        Ok(hash)
+    }
+
+    fn get_object(&self, hash: &str) -> Result<Vec<u8>, String> {
+        let bytes = std::fs::read(self.object_path(hash))
+            .map_err(|e| format!("历史记录缺少内容 {}: {}", hash, e))?;
+        if blake3::hash(&bytes).to_hex().as_str() != hash {
+            return Err(format!("历史记录中的内容 {} 已损坏", hash));
+        }
+        Ok(bytes)
+    }
+
+    /// 某个工程的所有快照清单和对应文件，从新到旧排列
+    fn manifests(&self, dir: &Path) -> Result<Vec<(PathBuf, SnapshotManifest)>, String> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("无法读取历史记录: {}", e)),
+        };
+        let mut manifests = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            // 损坏的清单跳过，不影响其他快照
+            let Ok(content) = std::fs::read(&path) else {
+                continue;
+            };
+            if let Ok(manifest) = serde_json::from_slice::<SnapshotManifest>(&content) {
+                manifests.push((path, manifest));
+            }
+        }
+        manifests.sort_by_key(|(_, manifest)| std::cmp::Reverse(manifest.time));
+        Ok(manifests)
+    }
+
+    fn find(&self, path: &Path, id: &str) -> Result<SnapshotManifest, String> {
+        self.manifests(&self.project_dir(path))?
+            .into_iter()
+            .map(|(_, manifest)| manifest)
+            .find(|manifest| manifest.id == id)
+            .ok_or_else(|| format!("找不到历史版本: {}", id))
+    }
+
+    /// 按清单重新组装 .prg 文件，条目顺序与记录时相同
+    fn rebuild(&self, manifest: &SnapshotManifest) -> Result<Vec<u8>, String> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for entry in &manifest.entries {
+            let bytes = self.get_object(&entry.hash)?;
+            zip.start_file(entry.name.as_str(), options)
+                .map_err(|e| format!("无法写入 {}: {}", entry.name, e))?;
+            zip.write_all(&bytes)
+                .map_err(|e| format!("无法写入 {}: {}", entry.name, e))?;
+        }
+        Ok(zip
+            .finish()
+            .map_err(|e| format!("无法完成 ZIP 写入: {}", e))?
+            .into_inner())
+    }
+
+    /// 记录文件当前的内容，与最新的快照相同时不重复记录
+    pub fn record(
+        &self,
+        path: &Path,
+        policy: &RetentionPolicy,
+    ) -> Result<HistoryRecordResult, String> {
+        let mut archive = open_archive(path)?;
+        let mut entries = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let mut file = archive
+                .by_index(i)
+                .map_err(|e| format!("无法读取第 {} 个条目: {}", i, e))?;
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_string();
+            let mut bytes = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut bytes)
+                .map_err(|e| format!("无法读取 {}: {}", name, e))?;
+            entries.push((name, bytes));
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        for (name, bytes) in &entries {
+            hasher.update(name.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(blake3::hash(bytes).as_bytes());
+        }
+        let id = hasher.finalize().to_hex()[..16].to_string();
+
+        let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = self.project_dir(path);
+        if let Some((_, latest)) = self.manifests(&dir)?.into_iter().next() {
+            if latest.id == id {
+                return Ok(HistoryRecordResult {
+                    snapshot: HistorySnapshot::from(&latest),
+                    created: false,
+                });
+            }
+        }
+
+        let mut manifest = SnapshotManifest {
+            id,
+            time: now_ms(),
+            path: path.to_string_lossy().into_owned(),
+            entries: Vec::with_capacity(entries.len()),
+        };
+        for (name, bytes) in entries {
+            manifest.entries.push(SnapshotEntry {
+                hash: self.put_object(&bytes)?,
+                size: bytes.len() as u64,
+                name,
+            });
+        }
+        std::fs::create_dir_all(&dir).map_err(|e| format!("无法创建历史记录目录: {}", e))?;
+        let content = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("无法序列化快照清单: {}", e))?;
+        write_file_atomic(
+            &dir.join(format!("{}-{}.json", manifest.time, manifest.id)),
+            &content,
+            false,
+        )?;
+
+        if self.prune_project(&dir, policy, now_ms())? > 0 {
+            self.collect_garbage()?;
+        }
+        Ok(HistoryRecordResult {
+            snapshot: HistorySnapshot::from(&manifest),
+            created: true,
+        })
+    }
+
+    /// 所有快照，从新到旧排列
+    pub fn list(&self, path: &Path) -> Result<Vec<HistorySnapshot>, String> {
+        Ok(self
+            .manifests(&self.project_dir(path))?
+            .iter()
+            .map(|(_, manifest)| HistorySnapshot::from(manifest))
+            .collect())
+    }
+
+    /// 读取某个快照中的工程
+    pub fn open(&self, path: &Path, id: &str) -> Result<PrgProject, String> {
+        PrgProject::from_bytes(&self.rebuild(&self.find(path, id)?)?)
+    }
+
+    /// 把快照还原到 output，output 就是工程本身时先记录当前内容，还原操作本身也可以撤销
+    pub fn restore(
+        &self,
+        path: &Path,
+        id: &str,
+        output: &Path,
+        policy: &RetentionPolicy,
+    ) -> Result<(), String> {
+        let bytes = self.rebuild(&self.find(path, id)?)?;
+        // 确认能正常打开后再覆盖
+        PrgProject::from_bytes(&bytes)?;
+        let in_place = output == path;
+        if in_place && path.exists() {
+            self.record(path, policy)?;
+        }
+        write_file_atomic(output, &bytes, false)?;
+        if in_place {
+            self.record(path, policy)?;
+        }
+        Ok(())
+    }
+
+    /// 按保留策略删除一个工程的旧快照，返回删除的数量
+    fn prune_project(
+        &self,
+        dir: &Path,
+        policy: &RetentionPolicy,
+        now: u64,
+    ) -> Result<usize, String> {
+        let manifests = self.manifests(dir)?;
+        let times: Vec<u64> = manifests
+            .iter()
+            .map(|(_, manifest)| manifest.time)
+            .collect();
+        let kept = policy.retained(&times, now);
+        let mut removed = 0;
+        for (i, (path, _)) in manifests.iter().enumerate() {
+            if !kept.contains(&i) && std::fs::remove_file(path).is_ok() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 删除不再被任何快照引用的内容
+    fn collect_garbage(&self) -> Result<(usize, u64), String> {
+        let mut referenced = HashSet::new();
+        if let Ok(projects) = std::fs::read_dir(self.projects_dir()) {
+            for project in projects.flatten() {
+                for (_, manifest) in self.manifests(&project.path())? {
+                    referenced.extend(manifest.entries.into_iter().map(|entry| entry.hash));
+                }
+            }
+        }
+        let (mut removed, mut freed) = (0, 0);
+        let Ok(prefixes) = std::fs::read_dir(self.objects_dir()) else {
+            return Ok((0, 0));
+        };
+        for prefix in prefixes.flatten() {
+            for object in std::fs::read_dir(prefix.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                if referenced.contains(object.file_name().to_string_lossy().as_ref()) {
+                    continue;
+                }
+                let size = object.metadata().map(|m| m.len()).unwrap_or_default();
+                if std::fs::remove_file(object.path()).is_ok() {
+                    removed += 1;
+                    freed += size;
+                }
+            }
+            // 目录不为空时删除失败，忽略即可
+            let _ = std::fs::remove_dir(prefix.path());
+        }
+        Ok((removed, freed))
+    }
+
+    /// 按保留策略清理，path 为 None 时清理所有工程的历史
+    pub fn prune(
+        &self,
+        path: Option<&Path>,
+        policy: &RetentionPolicy,
+        now: u64,
+    ) -> Result<HistoryPruneResult, String> {
+        let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dirs: Vec<PathBuf> = match path {
+            Some(path) => vec![self.project_dir(path)],
+            None => std::fs::read_dir(self.projects_dir())
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .collect(),
+        };
+        let mut result = HistoryPruneResult::default();
+        for dir in dirs {
+            result.removed_snapshots += self.prune_project(&dir, policy, now)?;
+            let _ = std::fs::remove_dir(&dir);
+        }
+        (result.removed_objects, result.freed_bytes) = self.collect_garbage()?;
+        Ok(result)
+    }
+}
+
+fn store<R: Runtime>(app: &AppHandle<R>) -> Result<HistoryStore, String> {
+    Ok(HistoryStore::new(history_dir(app)?))
+}
+
+/// 记录 .prg 文件当前的内容，通常在每次保存后调用
+#[tauri::command]
+pub async fn record_prg_history<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    path: String,
+    policy: Option<RetentionPolicy>,
+) -> Result<HistoryRecordResult, String> {
+    scope.check(Path::new(&path))?;
+    let store = store(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        store.record(Path::new(&path), &policy.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("记录历史版本任务失败: {}", e))?
+}
+
+/// 列出 .prg 文件的历史版本，从新到旧排列
+#[tauri::command]
+pub async fn list_prg_history<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<Vec<HistorySnapshot>, String> {
+    scope.check(Path::new(&path))?;
+    let store = store(&app)?;
+    tauri::async_runtime::spawn_blocking(move || store.list(Path::new(&path)))
+        .await
+        .map_err(|e| format!("读取历史版本任务失败: {}", e))?
+}
+
+/// 对比两个历史版本，to 为空时与文件当前的内容对比
+#[tauri::command]
+pub async fn diff_prg_history<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    path: String,
+    from: String,
+    to: Option<String>,
+) -> Result<PrgDiff, String> {
+    scope.check(Path::new(&path))?;
+    let store = store(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let old = store.open(path, &from)?;
+        let new = match to {
+            Some(to) => store.open(path, &to)?,
+            None => diff::open_or_empty(path)?,
+        };
+        Ok(diff::diff(&old, &new))
+    })
+    .await
+    .map_err(|e| format!("对比历史版本任务失败: {}", e))?
+}
+
+/// 还原历史版本，output 为空时覆盖原文件（覆盖前会先记录当前内容）
+#[tauri::command]
+pub async fn restore_prg_history<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    path: String,
+    id: String,
+    output: Option<String>,
+    policy: Option<RetentionPolicy>,
+) -> Result<String, String> {
+    let output = output.unwrap_or_else(|| path.clone());
+    scope.check(Path::new(&path))?;
+    scope.check(Path::new(&output))?;
+    let store = store(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        store.restore(
+            Path::new(&path),
+            &id,
+            Path::new(&output),
+            &policy.unwrap_or_default(),
+        )?;
+        Ok(output)
+    })
+    .await
+    .map_err(|e| format!("还原历史版本任务失败: {}", e))?
+}
+
+/// 按保留策略清理历史版本，path 为空时清理所有工程
+#[tauri::command]
+pub async fn prune_prg_history<R: Runtime>(
+    app: AppHandle<R>,
+    path: Option<String>,
+    policy: Option<RetentionPolicy>,
+) -> Result<HistoryPruneResult, String> {
+    let store = store(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        store.prune(
+            path.as_deref().map(Path::new),
+            &policy.unwrap_or_default(),
+            now_ms(),
+        )
+    })
+    .await
+    .map_err(|e| format!("清理历史版本任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::PrgAttachment;
+    use serde_json::json;
+
+    fn project(text: &str) -> PrgProject {
+        PrgProject {
+            stage: vec![json!({ "_": "TextNode", "uuid": "a", "text": text })],
+            attachments: vec![PrgAttachment {
+                id: "img".to_string(),
+                ext: "png".to_string(),
+                data: vec![7; 1024],
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn count_objects(store: &HistoryStore) -> usize {
+        walkdir(&store.objects_dir())
+    }
+
+    fn walkdir(dir: &Path) -> usize {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| match entry.path().is_dir() {
+                true => walkdir(&entry.path()),
+                false => 1,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn records_projects_opened_through_the_dialog() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("opened.prg");
+        project("第一版").save(&file).unwrap();
+
+        let scope = FsScope::default();
+        assert!(scope.check(&file).is_err());
+        // 对话框插件允许选中的文件后，record_prg_history 的范围检查能够通过
+        scope.allow_picked(&tauri::scope::fs::Event::PathAllowed(file.clone()));
+        scope.check(&file).unwrap();
+
+        let store = HistoryStore::new(dir.path().join("history"));
+        let recorded = store.record(&file, &RetentionPolicy::default()).unwrap();
+        assert!(recorded.created);
+        assert_eq!(store.list(&file).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn records_restores_and_prunes_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history"));
+        let file = dir.path().join("a.prg");
+        let policy = RetentionPolicy::default();
+
+        project("第一版").save(&file).unwrap();
+        let first = store.record(&file, &policy).unwrap();
+        assert!(first.created);
+        assert_eq!(first.snapshot.attachments, 1);
+        assert!(!store.record(&file, &policy).unwrap().created);
+
+        project("第二版").save(&file).unwrap();
+        let second = store.record(&file, &policy).unwrap();
+        assert!(second.created);
+        assert_ne!(first.snapshot.id, second.snapshot.id);
+        // 附件和未改动的条目只存一份：5 个条目 + 新的舞台
+        let objects = count_objects(&store);
+        assert_eq!(objects, 6);
+
+        let list = store.list(&file).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, second.snapshot.id);
+
+        let old = store.open(&file, &first.snapshot.id).unwrap();
+        assert_eq!(old.stage[0]["text"], "第一版");
+        let copy = dir.path().join("copy.prg");
+        store
+            .restore(&file, &first.snapshot.id, &copy, &policy)
+            .unwrap();
+        assert_eq!(PrgProject::open(&copy).unwrap(), project("第一版"));
+
+        store
+            .restore(&file, &first.snapshot.id, &file, &policy)
+            .unwrap();
+        assert_eq!(PrgProject::open(&file).unwrap().stage[0]["text"], "第一版");
+        let list = store.list(&file).unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[0].id, first.snapshot.id);
+
+        let keep_latest = RetentionPolicy {
+            keep_recent: 1,
+            hourly_hours: 0,
+            daily_days: 0,
+            ..Default::default()
+        };
+        let result = store.prune(Some(&file), &keep_latest, now_ms()).unwrap();
+        assert_eq!(result.removed_snapshots, 2);
+        assert_eq!(result.removed_objects, 1);
+        assert_eq!(count_objects(&store), objects - 1);
+        assert_eq!(store.list(&file).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn retention_keeps_one_snapshot_per_bucket() {
+        let policy = RetentionPolicy {
+            keep_recent: 2,
+            hourly_hours: 24,
+            daily_days: 30,
+            ..Default::default()
+        };
+        let now = 100 * DAY_MS;
+        let minute = 60 * 1000;
+        let times = [
+            now - minute,
+            now - 2 * minute,
+            now - 3 * minute,
+            now - 2 * HOUR_MS - minute,
+            now - 2 * HOUR_MS - 2 * minute,
+            now - 3 * DAY_MS - minute,
+            now - 3 * DAY_MS - HOUR_MS,
+            now - 40 * DAY_MS,
+        ];
+        let mut kept: Vec<usize> = policy.retained(&times, now).into_iter().collect();
+        kept.sort();
+        // 最近两份，两小时前那个小时最新的一份，三天前那天最新的一份；四十天前的超出范围
+        assert_eq!(kept, vec![0, 1, 3, 5]);
+    }
+}
@@ -1,17 +1,101 @@
 use rmcp::{
-    model::CallToolRequestParams,
-    service::RunningService,
-    transport::{which_command, TokioChildProcess},
-    RoleClient, ServiceExt,
+    model::CallToolRequestParams, service::RunningService, transport::which_command, RoleClient,
+    ServiceExt,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::{collections::HashMap, sync::Arc};
-use tauri::State;
-use tokio::sync::{Mutex, RwLock};
+use std::{
+    collections::{HashMap, VecDeque},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{Emitter, Manager, Runtime, State, Window};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout},
+    sync::{oneshot, Mutex, RwLock},
+    task::JoinHandle,
+};
+
+use super::shell::{CommandOptions, ProcessExit};
+use super::shell_policy::{CommandRequest, ShellPolicy};
 
 type RunningClient = RunningService<RoleClient, ()>;
 type SharedClient = Arc<RwLock<RunningClient>>;
+type StatusNotifier = Arc<dyn Fn(McpServerStatus) + Send + Sync>;
+/// Records how an authorized process ended in the audit log.
+type Audit = Box<dyn FnOnce(Option<&ProcessExit>) + Send>;
+/// Checks the server command against the shell policy before every start and restart; may block
+/// on an approval dialog.
+type Authorizer = Arc<dyn Fn(&McpStdioConfig) -> Result<Audit, String> + Send + Sync>;
+
+pub const MCP_SERVER_STATUS_EVENT: &str = "mcp-server-status";
+
+/// Number of stderr lines kept for crash reports.
+const STDERR_TAIL_LINES: usize = 40;
+const STDERR_LINE_MAX_BYTES: usize = 2000;
+/// How long to wait for the stderr pipe to drain after the process exits.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long a stopped server gets to exit on its own before it is killed.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(3);
+/// A server that stays up this long gets its restart budget back.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum McpRestartMode {
+    #[default]
+    Never,
+    /// Restart unless the server exited with code 0.
+    OnFailure,
+    Always,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpRestartPolicy {
+    pub mode: McpRestartMode,
+    /// Consecutive restarts before giving up.
+    pub max_restarts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for McpRestartPolicy {
+    fn default() -> Self {
+        McpRestartPolicy {
+            mode: McpRestartMode::Never,
+            max_restarts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl McpRestartPolicy {
+    fn wants_restart(&self, exit: Option<&ProcessExit>) -> bool {
+        match self.mode {
+            McpRestartMode::Never => false,
+            McpRestartMode::OnFailure => exit.is_none_or(|exit| exit.code != Some(0)),
+            McpRestartMode::Always => true,
+        }
+    }
+
+    /// Delay before the restart following `restarts` earlier ones: doubles each time, capped at
+    /// `max_backoff_ms`.
+    fn backoff(&self, restarts: u32) -> Duration {
+        let factor = 1u64.checked_shl(restarts).unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -23,11 +107,53 @@ pub struct McpStdioConfig {
     pub cwd: Option<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub restart: McpRestartPolicy,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum McpServerState {
+    /// The server was restarted and is accepting requests again.
+    Running,
+    /// The server is down and will be started again after `retry_in_ms`.
+    Restarting,
+    /// The server exited and the restart policy does not restart it.
+    Exited,
+    /// The server kept crashing and used up `max_restarts`.
+    Failed,
+}
+
+/// Payload of the `mcp-server-status` event.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatus {
+    pub server_name: String,
+    pub state: McpServerState,
+    /// How the process ended; missing when it could not be started at all.
+    pub exit: Option<ProcessExit>,
+    /// Spawn or initialization error of a failed restart.
+    pub error: Option<String>,
+    /// Last lines the process wrote to stderr.
+    pub stderr_tail: Vec<String>,
+    pub restart_count: u32,
+    pub retry_in_ms: Option<u64>,
+}
+
+struct McpStdioServer {
+    id: u64,
+    /// None while the supervisor is restarting the process.
+    client: Option<SharedClient>,
+    /// Dropping this stops the supervisor.
+    _stop: oneshot::Sender<()>,
+}
+
+type Servers = Arc<Mutex<HashMap<String, McpStdioServer>>>;
+
 #[derive(Default)]
 pub struct McpStdioManager {
-    clients: Mutex<HashMap<String, SharedClient>>,
+    servers: Servers,
+    next_id: AtomicU64,
 }
 
 fn validate_config(config: McpStdioConfig) -> Result<McpStdioConfig, String> {
@@ -72,27 +198,327 @@ fn validate_config(config: McpStdioConfig) -> Result<McpStdioConfig, String> {
         args: config.args,
         cwd,
         env: config.env,
+        restart: config.restart,
     })
 }
 
-fn create_process(config: &McpStdioConfig) -> Result<TokioChildProcess, String> {
-    let mut command = which_command(&config.command).map_err(|error| {
-        format!(
-            "Unable to resolve MCP stdio command {:?} for server {}: {error}",
-            config.command, config.server_name
-        )
-    })?;
-    command.args(&config.args);
-    if let Some(cwd) = &config.cwd {
-        command.current_dir(cwd);
+/// Everything known about a process that ended or could not be started.
+#[derive(Debug, Default)]
+struct McpStdioCrash {
+    exit: Option<ProcessExit>,
+    error: Option<String>,
+    stderr_tail: Vec<String>,
+    /// The shell policy or the user refused to start the command; it is not restarted.
+    refused: bool,
+}
+
+impl McpStdioCrash {
+    fn message(&self) -> String {
+        let mut message = self.error.clone().unwrap_or_default();
+        if !self.stderr_tail.is_empty() {
+            message.push_str("\nstderr:\n");
+            message.push_str(&self.stderr_tail.join("\n"));
+        }
+        message
+    }
+}
+
+struct McpStdioProcess {
+    child: Child,
+    stderr_reader: JoinHandle<()>,
+    stderr_tail: Arc<std::sync::Mutex<VecDeque<String>>>,
+    /// Taken once the exit is known.
+    audit: Option<Audit>,
+}
+
+impl McpStdioProcess {
+    fn spawn(
+        config: &McpStdioConfig,
+        audit: Audit,
+    ) -> Result<(Self, ChildStdout, ChildStdin), String> {
+        match Self::spawn_child(config) {
+            Ok((child, stdout, stdin)) => {
+                let mut process = McpStdioProcess {
+                    child,
+                    stderr_reader: tokio::spawn(async {}),
+                    stderr_tail: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+                    audit: Some(audit),
+                };
+                if let Some(stderr) = process.child.stderr.take() {
+                    process.stderr_reader =
+                        tokio::spawn(collect_stderr(stderr, process.stderr_tail.clone()));
+                }
+                Ok((process, stdout, stdin))
+            }
+            Err(error) => {
+                audit(None);
+                Err(error)
+            }
+        }
+    }
+
+    fn spawn_child(config: &McpStdioConfig) -> Result<(Child, ChildStdout, ChildStdin), String> {
+        let mut command = which_command(&config.command).map_err(|error| {
+            format!(
+                "Unable to resolve MCP stdio command {:?} for server {}: {error}",
+                config.command, config.server_name
+            )
+        })?;
+        command.args(&config.args);
+        if let Some(cwd) = &config.cwd {
+            command.current_dir(cwd);
+        }
+        command
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.spawn().map_err(|error| {
+            format!(
+                "Unable to start MCP stdio server {} with command {:?}: {error}",
+                config.server_name, config.command
+            )
+        })?;
+        let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+            return Err(format!(
+                "MCP stdio server {} was started without stdio pipes",
+                config.server_name
+            ));
+        };
+        Ok((child, stdout, stdin))
+    }
+
+    fn finish(&mut self, exit: Option<&ProcessExit>) {
+        if let Some(audit) = self.audit.take() {
+            audit(exit);
+        }
+    }
+
+    async fn stderr_tail(&mut self) -> Vec<String> {
+        // A grandchild may keep the pipe open, so do not wait for EOF forever.
+        let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, &mut self.stderr_reader).await;
+        self.stderr_tail.lock().unwrap().iter().cloned().collect()
+    }
+
+    async fn crash(mut self, exit: Option<ProcessExit>, error: Option<String>) -> McpStdioCrash {
+        self.finish(exit.as_ref());
+        McpStdioCrash {
+            exit,
+            error,
+            stderr_tail: self.stderr_tail().await,
+        }
+    }
+
+    /// Kill the process and report how it ended.
+    async fn kill(mut self, error: String) -> McpStdioCrash {
+        let _ = self.child.start_kill();
+        let exit = self
+            .child
+            .wait()
+            .await
+            .ok()
+            .map(|status| ProcessExit::new(status, false));
+        self.crash(exit, Some(error)).await
     }
-    command.envs(&config.env);
-    TokioChildProcess::new(command).map_err(|error| {
+}
+
+async fn collect_stderr(stderr: ChildStderr, tail: Arc<std::sync::Mutex<VecDeque<String>>>) {
+    let mut reader = BufReader::new(stderr);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        line.truncate(STDERR_LINE_MAX_BYTES);
+        let text = String::from_utf8_lossy(&line).trim_end().to_string();
+        let mut tail = tail.lock().unwrap();
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(text);
+    }
+}
+
+/// Authorize and spawn the server, run the MCP handshake and list its tools.
+async fn connect(
+    config: &McpStdioConfig,
+    authorize: &Authorizer,
+) -> Result<(RunningClient, McpStdioProcess, Value), McpStdioCrash> {
+    let failed = |error| McpStdioCrash {
+        error: Some(error),
+        ..Default::default()
+    };
+    let audit = {
+        let (authorize, config) = (authorize.clone(), config.clone());
+        tokio::task::spawn_blocking(move || authorize(&config))
+            .await
+            .map_err(|error| failed(format!("Unable to authorize MCP stdio server: {error}")))?
+            .map_err(|error| McpStdioCrash {
+                refused: true,
+                ..failed(error)
+            })?
+    };
+    let (process, stdout, stdin) = McpStdioProcess::spawn(config, audit).map_err(failed)?;
+    let client = match ().serve((stdout, stdin)).await {
+        Ok(client) => client,
+        Err(error) => {
+            return Err(process
+                .kill(format!(
+                    "Unable to initialize MCP stdio server {}: {error}",
+                    config.server_name
+                ))
+                .await)
+        }
+    };
+    let tools = match client.list_all_tools().await {
+        Ok(tools) => tools,
+        Err(error) => {
+            return Err(process
+                .kill(format!(
+                    "Unable to list tools from MCP stdio server {}: {error}",
+                    config.server_name
+                ))
+                .await)
+        }
+    };
+    let tools = serde_json::to_value(tools).map_err(|error| {
         format!(
-            "Unable to start MCP stdio server {} with command {:?}: {error}",
-            config.server_name, config.command
+            "Unable to serialize tools from MCP stdio server {}: {error}",
+            config.server_name
         )
-    })
+    });
+    match tools {
+        Ok(tools) => Ok((client, process, tools)),
+        Err(error) => Err(process.kill(error).await),
+    }
+}
+
+/// Replace the client of server `id`. Returns false once the server was stopped or replaced.
+async fn set_client(
+    servers: &Servers,
+    server_name: &str,
+    id: u64,
+    client: Option<SharedClient>,
+) -> bool {
+    match servers.lock().await.get_mut(server_name) {
+        Some(server) if server.id == id => {
+            server.client = client;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Watch the server process, report crashes and restart it according to its restart policy.
+async fn supervise(
+    servers: Servers,
+    id: u64,
+    config: McpStdioConfig,
+    mut process: McpStdioProcess,
+    mut stop: oneshot::Receiver<()>,
+    notify: StatusNotifier,
+    authorize: Authorizer,
+) {
+    let server_name = config.server_name.clone();
+    let policy = &config.restart;
+    let mut restarts = 0;
+    loop {
+        let started = Instant::now();
+        let status = tokio::select! {
+            status = process.child.wait() => status,
+            _ = &mut stop => {
+                // The client was closed, which closes stdin; give the server a moment to exit.
+                let grace = tokio::time::timeout(STOP_GRACE_PERIOD, process.child.wait()).await;
+                let status = match grace {
+                    Ok(status) => status,
+                    Err(_) => process.child.kill().await.and(process.child.wait().await),
+                };
+                let exit = status.ok().map(|status| ProcessExit::new(status, false));
+                process.finish(exit.as_ref());
+                return;
+            }
+        };
+        if started.elapsed() >= STABLE_RUN {
+            restarts = 0;
+        }
+        let (exit, error) = match status {
+            Ok(status) => (Some(ProcessExit::new(status, false)), None),
+            Err(error) => (
+                None,
+                Some(format!(
+                    "Unable to wait for MCP stdio server {server_name}: {error}"
+                )),
+            ),
+        };
+        let mut crash = process.crash(exit, error).await;
+        if !set_client(&servers, &server_name, id, None).await {
+            return;
+        }
+
+        process = loop {
+            let status =
+                |state, restart_count, retry_in_ms, crash: &McpStdioCrash| McpServerStatus {
+                    server_name: server_name.clone(),
+                    state,
+                    exit: crash.exit.clone(),
+                    error: crash.error.clone(),
+                    stderr_tail: crash.stderr_tail.clone(),
+                    restart_count,
+                    retry_in_ms,
+                };
+            let wants_restart = !crash.refused && policy.wants_restart(crash.exit.as_ref());
+            if !wants_restart || restarts >= policy.max_restarts {
+                let state = if wants_restart {
+                    McpServerState::Failed
+                } else {
+                    McpServerState::Exited
+                };
+                let mut servers = servers.lock().await;
+                if servers
+                    .get(&server_name)
+                    .is_some_and(|server| server.id == id)
+                {
+                    servers.remove(&server_name);
+                    notify(status(state, restarts, None, &crash));
+                }
+                return;
+            }
+
+            let delay = policy.backoff(restarts);
+            restarts += 1;
+            notify(status(
+                McpServerState::Restarting,
+                restarts,
+                Some(delay.as_millis() as u64),
+                &crash,
+            ));
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut stop => return,
+            }
+
+            match connect(&config, &authorize).await {
+                Ok((client, next, _)) => {
+                    let client = Arc::new(RwLock::new(client));
+                    if !set_client(&servers, &server_name, id, Some(client.clone())).await {
+                        let _ = close_client(&server_name, client).await;
+                        return;
+                    }
+                    notify(status(
+                        McpServerState::Running,
+                        restarts,
+                        None,
+                        &McpStdioCrash::default(),
+                    ));
+                    break next;
+                }
+                Err(next) => crash = next,
+            }
+        };
+    }
 }
 
 async fn close_client(server_name: &str, client: SharedClient) -> Result<(), String> {
@@ -107,56 +533,61 @@ async fn close_client(server_name: &str, client: SharedClient) -> Result<(), Str
 
 impl McpStdioManager {
     async fn get_client(&self, server_name: &str) -> Result<SharedClient, String> {
-        self.clients
-            .lock()
-            .await
-            .get(server_name)
-            .cloned()
-            .ok_or_else(|| format!("MCP stdio server {server_name} is not running"))
+        match self.servers.lock().await.get(server_name) {
+            Some(McpStdioServer {
+                client: Some(client),
+                ..
+            }) => Ok(client.clone()),
+            Some(_) => Err(format!("MCP stdio server {server_name} is restarting")),
+            None => Err(format!("MCP stdio server {server_name} is not running")),
+        }
     }
 
-    async fn start(&self, config: McpStdioConfig) -> Result<Value, String> {
+    async fn start(
+        &self,
+        config: McpStdioConfig,
+        notify: StatusNotifier,
+        authorize: Authorizer,
+    ) -> Result<Value, String> {
         let config = validate_config(config)?;
-        let transport = create_process(&config)?;
-        let client = ().serve(transport).await.map_err(|error| {
-            format!(
-                "Unable to initialize MCP stdio server {}: {error}",
-                config.server_name
-            )
-        })?;
-        let tools = client.list_all_tools().await.map_err(|error| {
-            format!(
-                "Unable to list tools from MCP stdio server {}: {error}",
-                config.server_name
-            )
-        })?;
-        let tools = serde_json::to_value(tools).map_err(|error| {
-            format!(
-                "Unable to serialize tools from MCP stdio server {}: {error}",
-                config.server_name
-            )
-        })?;
+        let (client, process, tools) = connect(&config, &authorize)
+            .await
+            .map_err(|crash| crash.message())?;
 
         let client = Arc::new(RwLock::new(client));
-        let previous = self
-            .clients
-            .lock()
-            .await
-            .insert(config.server_name.clone(), client.clone());
-        if let Some(previous) = previous {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (stop_sender, stop) = oneshot::channel();
+        let previous = self.servers.lock().await.insert(
+            config.server_name.clone(),
+            McpStdioServer {
+                id,
+                client: Some(client.clone()),
+                _stop: stop_sender,
+            },
+        );
+        tokio::spawn(supervise(
+            self.servers.clone(),
+            id,
+            config.clone(),
+            process,
+            stop,
+            notify,
+            authorize,
+        ));
+        if let Some(previous) = previous.and_then(|previous| previous.client) {
             if let Err(error) = close_client(&config.server_name, previous).await {
                 let removed = {
-                    let mut clients = self.clients.lock().await;
-                    let is_current = clients
+                    let mut servers = self.servers.lock().await;
+                    let is_current = servers
                         .get(&config.server_name)
-                        .is_some_and(|current| Arc::ptr_eq(current, &client));
+                        .is_some_and(|current| current.id == id);
                     if is_current {
-                        clients.remove(&config.server_name)
+                        servers.remove(&config.server_name)
                     } else {
                         None
                     }
                 };
-                if let Some(removed) = removed {
+                if let Some(removed) = removed.and_then(|removed| removed.client) {
                     close_client(&config.server_name, removed).await.map_err(|close_error| {
                         format!("{error}; the replacement process also could not be stopped: {close_error}")
                     })?;
@@ -207,20 +638,48 @@ impl McpStdioManager {
     }
 
     async fn stop(&self, server_name: &str) -> Result<(), String> {
-        let client = self.clients.lock().await.remove(server_name);
-        if let Some(client) = client {
+        let server = self.servers.lock().await.remove(server_name);
+        if let Some(client) = server.and_then(|server| server.client) {
             close_client(server_name, client).await?;
         }
         Ok(())
     }
 }
 
+/// Start a server; crashes and restarts are reported through the `mcp-server-status` event.
+/// Every start and restart goes through `cmd::shell_policy` like `run_command`, with the caller
+/// recorded as `mcp:<server name>`.
 #[tauri::command]
-pub async fn mcp_stdio_start(
+pub async fn mcp_stdio_start<R: Runtime>(
+    window: Window<R>,
     manager: State<'_, McpStdioManager>,
     config: McpStdioConfig,
 ) -> Result<Value, String> {
-    manager.start(config).await
+    let app = window.app_handle().clone();
+    let notify: StatusNotifier = Arc::new(move |status| {
+        let _ = app.emit(MCP_SERVER_STATUS_EVENT, status);
+    });
+    let authorize: Authorizer = Arc::new(move |config: &McpStdioConfig| {
+        let options = CommandOptions {
+            cwd: config.cwd.clone(),
+            env: config.env.clone(),
+            ..Default::default()
+        };
+        let request = CommandRequest::new(
+            window.label(),
+            Some(format!("mcp:{}", config.server_name)),
+            &config.command,
+            &config.args,
+            &options,
+        );
+        let authorized = window.state::<ShellPolicy>().authorize(&window, request)?;
+        let window = window.clone();
+        let audit: Audit = Box::new(move |exit: Option<&ProcessExit>| {
+            window.state::<ShellPolicy>().finish(authorized, exit)
+        });
+        Ok(audit)
+    });
+    manager.start(config, notify, authorize).await
 }
 
 #[tauri::command]
@@ -253,6 +712,13 @@ pub async fn mcp_stdio_stop(
 mod tests {
     use super::*;
 
+    fn allow_all() -> Authorizer {
+        Arc::new(|_: &McpStdioConfig| {
+            let audit: Audit = Box::new(|_: Option<&ProcessExit>| {});
+            Ok(audit)
+        })
+    }
+
     fn config(command: &str) -> McpStdioConfig {
         McpStdioConfig {
             server_name: "files".to_string(),
@@ -260,6 +726,7 @@ mod tests {
             args: vec!["--root".to_string(), "workspace".to_string()],
             cwd: Some(" ".to_string()),
             env: HashMap::from([("MODE".to_string(), "read-only".to_string())]),
+            restart: McpRestartPolicy::default(),
         }
     }
 
@@ -294,4 +761,66 @@ mod tests {
             .await
             .expect("stop should be idempotent");
     }
+
+    #[test]
+    fn restart_policy_defaults_to_never_and_backs_off_exponentially() {
+        let config: McpStdioConfig =
+            serde_json::from_value(serde_json::json!({ "serverName": "files", "command": "npx" }))
+                .unwrap();
+        assert_eq!(config.restart.mode, McpRestartMode::Never);
+        assert!(!config.restart.wants_restart(None));
+
+        let policy: McpRestartPolicy = serde_json::from_value(serde_json::json!({
+            "mode": "onFailure",
+            "initialBackoffMs": 100,
+            "maxBackoffMs": 1000
+        }))
+        .unwrap();
+        assert_eq!(policy.max_restarts, 5);
+        let delays: Vec<u64> = (0..6)
+            .map(|restarts| policy.backoff(restarts).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(200), Duration::from_millis(1000));
+
+        let exit = |code| ProcessExit {
+            code: Some(code),
+            ..Default::default()
+        };
+        assert!(!policy.wants_restart(Some(&exit(0))));
+        assert!(policy.wants_restart(Some(&exit(1))));
+        assert!(policy.wants_restart(None));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reports_exit_code_and_stderr_of_a_crashed_server() {
+        let mut crashing = config("sh");
+        crashing.cwd = None;
+        crashing.args = vec![
+            "-c".to_string(),
+            "echo starting >&2; echo \"missing $MODE config\" >&2; exit 3".to_string(),
+        ];
+        let crash = match connect(&crashing, &allow_all()).await {
+            Ok(_) => panic!("a process that exits is not a usable server"),
+            Err(crash) => crash,
+        };
+        assert_eq!(crash.exit.as_ref().and_then(|exit| exit.code), Some(3));
+        assert_eq!(
+            crash.stderr_tail,
+            vec!["starting", "missing read-only config"]
+        );
+        assert!(crash.message().ends_with("missing read-only config"));
+    }
+
+    #[tokio::test]
+    async fn does_not_spawn_a_refused_server() {
+        let refuse: Authorizer = Arc::new(|_: &McpStdioConfig| Err("refused".to_string()));
+        let crash = match connect(&config("definitely-not-a-command"), &refuse).await {
+            Ok(_) => panic!("a refused server must not start"),
+            Err(crash) => crash,
+        };
+        assert!(crash.refused);
+        assert_eq!(crash.error.as_deref(), Some("refused"));
+    }
 }
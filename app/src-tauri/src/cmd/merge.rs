@@ -0,0 +1,639 @@
+//! .prg 的三方合并
+//!
+//! 按 UUID 对齐 base、ours、theirs 三个版本的舞台对象，逐个对象、逐个字段合并：
+//! 只有一边改动的字段直接采用改动，两边改成相同值的也没有冲突。
+//! 真正的冲突（两边都改了同一个节点的文字、一边删除一边修改等）保留我方（删除时保留修改的一方），
+//! 并在冲突对象旁边插入一个红色的冲突节点，写明两边的内容，打开文件后可以直接看到并手动处理。
+//!
+//! 舞台中的对象引用是按位置记录的（`{ "$": "/3" }`），合并后位置会变化，
+//! 所以先把引用换成 UUID，合并完成后再按新的位置写回。
+//!
+//! 用作 git 的合并驱动：
+//! ```text
+//! # .gitattributes
+//! *.prg merge=prg
+//! # git config
+//! git config merge.prg.driver "project-graph merge %O %A %B"
+//! ```
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use tauri::State;
+
+use super::prg::{PrgAttachment, PrgProject};
+use super::scope::FsScope;
+use super::stage;
+
+/// 合并过程中表示对其他舞台对象的引用
+const REF_KEY: &str = "$uuid";
+
+/// 冲突节点的 UUID 后缀
+const CONFLICT_SUFFIX: &str = "-merge-conflict";
+
+/// 引用都换成了 UUID 的舞台，对象之间互相独立
+#[derive(Debug, Default)]
+struct FlatStage {
+    order: Vec<String>,
+    objects: HashMap<String, Value>,
+}
+
+fn ref_target(value: &Value) -> Option<&str> {
+    value.as_object()?.get(REF_KEY)?.as_str()
+}
+
+/// 把内嵌的舞台对象和按位置的引用都换成 {"$uuid": id}
+fn replace_nested(stage: &[Value], value: &mut Value) {
+    let target = match value {
+        Value::Object(_) => stage::resolve(stage, value)
+            .filter(|target| stage::class_name(target).is_some())
+            .and_then(stage::uuid)
+            .map(str::to_string),
+        _ => None,
+    };
+    if let Some(id) = target {
+        *value = json!({ REF_KEY: id });
+        return;
+    }
+    match value {
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| replace_nested(stage, item)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| replace_nested(stage, item)),
+        _ => {}
+    }
+}
+
+fn flatten(stage: &[Value]) -> FlatStage {
+    let mut flat = FlatStage::default();
+    stage::for_each_object(stage, |object| {
+        let Some(id) = stage::uuid(object) else {
+            return;
+        };
+        let mut object = object.clone();
+        if let Value::Object(map) = &mut object {
+            map.values_mut()
+                .for_each(|value| replace_nested(stage, value));
+        }
+        flat.order.push(id.to_string());
+        flat.objects.insert(id.to_string(), object);
+    });
+    flat
+}
+
+/// 把 {"$uuid": id} 换回按位置的引用
+fn restore_refs(value: &mut Value, index: &HashMap<String, usize>) {
+    if let Some(position) = ref_target(value).and_then(|id| index.get(id)) {
+        *value = json!({ "$": format!("/{}", position) });
+        return;
+    }
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(|item| restore_refs(item, index)),
+        Value::Object(map) => map.values_mut().for_each(|item| restore_refs(item, index)),
+        _ => {}
+    }
+}
+
+fn unflatten(flat: FlatStage) -> Vec<Value> {
+    let index: HashMap<String, usize> = flat
+        .order
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.clone(), i))
+        .collect();
+    let mut objects = flat.objects;
+    flat.order
+        .iter()
+        .filter_map(|id| objects.remove(id))
+        .map(|mut object| {
+            if let Value::Object(map) = &mut object {
+                map.values_mut()
+                    .for_each(|value| restore_refs(value, &index));
+            }
+            object
+        })
+        .collect()
+}
+
+fn contains_ref(value: &Value, ids: &HashSet<&str>) -> bool {
+    if let Some(id) = ref_target(value) {
+        return !ids.contains(id);
+    }
+    match value {
+        Value::Array(items) => items.iter().any(|item| contains_ref(item, ids)),
+        Value::Object(map) => map.values().any(|item| contains_ref(item, ids)),
+        _ => false,
+    }
+}
+
+/// 去掉数组中指向不存在对象的引用
+fn remove_dangling(value: &mut Value, ids: &HashSet<String>) {
+    match value {
+        Value::Array(items) => {
+            items.retain(|item| ref_target(item).is_none_or(|id| ids.contains(id)));
+            items.iter_mut().for_each(|item| remove_dangling(item, ids));
+        }
+        Value::Object(map) => map.values_mut().for_each(|item| remove_dangling(item, ids)),
+        _ => {}
+    }
+}
+
+/// 合并中的冲突
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub uuid: String,
+    pub label: String,
+    pub reason: String,
+}
+
+/// merge_prg 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgMergeResult {
+    pub conflicts: Vec<MergeConflict>,
+    /// 合并后的舞台对象数量，不含冲突节点
+    pub objects: usize,
+}
+
+fn field_name(key: &str) -> &str {
+    match key {
+        "text" => "文字",
+        "details" => "详细信息",
+        "collisionBox" | "_collisionBoxNormal" => "位置",
+        "associationList" => "连接",
+        "children" => "子对象",
+        key => key,
+    }
+}
+
+/// 逐个字段三方合并，返回合并结果和冲突的字段（冲突的字段保留我方）
+fn merge_fields(
+    base: &Map<String, Value>,
+    ours: &Map<String, Value>,
+    theirs: &Map<String, Value>,
+) -> (Map<String, Value>, Vec<String>) {
+    let mut merged = ours.clone();
+    let mut conflicts = Vec::new();
+    let keys: Vec<&String> = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(*key)))
+        .chain(
+            base.keys()
+                .filter(|key| !ours.contains_key(*key) && !theirs.contains_key(*key)),
+        )
+        .collect();
+    for key in keys {
+        let (b, o, t) = (base.get(key), ours.get(key), theirs.get(key));
+        if o == t || b == t {
+            continue;
+        }
+        if b == o {
+            match t {
+                Some(t) => merged.insert(key.clone(), t.clone()),
+                None => merged.remove(key),
+            };
+        } else {
+            conflicts.push(key.clone());
+        }
+    }
+    (merged, conflicts)
+}
+
+fn label(id: &str, object: &Value) -> String {
+    let text = stage::object_text(object);
+    match text.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) => line.chars().take(40).collect(),
+        None => id.chars().take(8).collect(),
+    }
+}
+
+fn location(object: &Value) -> Option<(f64, f64)> {
+    let collision_box = object
+        .get("collisionBox")
+        .or_else(|| object.get("_collisionBoxNormal"))?;
+    let location = collision_box.get("shapes")?.get(0)?.get("location")?;
+    Some((location.get("x")?.as_f64()?, location.get("y")?.as_f64()?))
+}
+
+/// 对象的位置，连线等没有位置的对象使用第一个有位置的端点
+fn anchor(objects: &HashMap<String, Value>, object: &Value) -> (f64, f64) {
+    location(object)
+        .or_else(|| {
+            object
+                .get("associationList")?
+                .as_array()?
+                .iter()
+                .filter_map(ref_target)
+                .find_map(|id| location(objects.get(id)?))
+        })
+        .unwrap_or_default()
+}
+
+/// 冲突节点：放在冲突对象上方的红色文本节点
+fn conflict_node(id: &str, text: String, (x, y): (f64, f64)) -> Value {
+    json!({
+        "_": "TextNode",
+        "uuid": format!("{}{}", id, CONFLICT_SUFFIX),
+        "text": text,
+        "details": [],
+        "collisionBox": {
+            "_": "CollisionBox",
+            "shapes": [{
+                "_": "Rectangle",
+                "location": { "_": "Vector", "x": x, "y": y - 120.0 },
+                "size": { "_": "Vector", "x": 300, "y": 100 }
+            }]
+        },
+        "color": { "_": "Color", "r": 239, "g": 68, "b": 68, "a": 1 },
+        "sizeAdjust": "manual"
+    })
+}
+
+/// 一个对象的合并结果
+enum Merged {
+    Keep(Value),
+    Delete,
+    /// 有冲突：保留的对象和冲突节点上的说明
+    Conflict(Value, String, String),
+}
+
+fn merge_object(
+    id: &str,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+) -> Merged {
+    let keep = |value: Option<&Value>| value.cloned().map_or(Merged::Delete, Merged::Keep);
+    if ours == theirs || base == theirs {
+        return keep(ours);
+    }
+    if base == ours {
+        return keep(theirs);
+    }
+    match (ours, theirs) {
+        (Some(o), Some(t)) => {
+            let empty = Map::new();
+            let base_map = base.and_then(Value::as_object).unwrap_or(&empty);
+            let (Some(ours_map), Some(theirs_map)) = (o.as_object(), t.as_object()) else {
+                return Merged::Keep(o.clone());
+            };
+            let (merged, conflicts) = merge_fields(base_map, ours_map, theirs_map);
+            let merged = Value::Object(merged);
+            if conflicts.is_empty() {
+                return Merged::Keep(merged);
+            }
+            let name = label(id, &merged);
+            let text_changed = conflicts
+                .iter()
+                .any(|key| key == "text" || key == "details");
+            let mut fields: Vec<&str> = conflicts.iter().map(|key| field_name(key)).collect();
+            fields.dedup();
+            let reason = format!("{} 在两边都被修改", fields.join("、"));
+            let mut note = format!("⚠ 合并冲突：「{}」的{}，已保留我方的版本", name, reason);
+            if text_changed {
+                note.push_str(&format!(
+                    "\n我方：{}\n对方：{}",
+                    stage::object_text(o),
+                    stage::object_text(t)
+                ));
+            }
+            Merged::Conflict(merged, reason, note)
+        }
+        (Some(modified), None) | (None, Some(modified)) => {
+            let deleted_by = if ours.is_none() { "我方" } else { "对方" };
+            let kept_by = if ours.is_none() { "对方" } else { "我方" };
+            let reason = format!("在{}被删除，在{}被修改", deleted_by, kept_by);
+            let note = format!(
+                "⚠ 合并冲突：「{}」{}，已保留{}的版本",
+                label(id, modified),
+                reason,
+                kept_by
+            );
+            Merged::Conflict(modified.clone(), reason, note)
+        }
+        (None, None) => Merged::Delete,
+    }
+}
+
+/// 三方合并 tags：一边删除即删除，任意一边新增即新增
+fn merge_tags(base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = ours
+        .iter()
+        .filter(|tag| !base.contains(tag) || theirs.contains(tag))
+        .cloned()
+        .collect();
+    for tag in theirs {
+        if !base.contains(tag) && !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    tags
+}
+
+/// 整体三方合并：只有一边改动时采用改动，两边都改动时保留我方
+fn merge_whole<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> T {
+    if ours == base {
+        theirs.clone()
+    } else {
+        ours.clone()
+    }
+}
+
+/// 三方合并三个工程，返回合并结果和冲突
+pub fn merge(
+    base: &PrgProject,
+    ours: &PrgProject,
+    theirs: &PrgProject,
+) -> (PrgProject, Vec<MergeConflict>) {
+    let base_stage = flatten(&base.stage);
+    let ours_stage = flatten(&ours.stage);
+    let theirs_stage = flatten(&theirs.stage);
+
+    // 我方的顺序在前，对方新增的对象接在后面
+    let mut order = ours_stage.order.clone();
+    let mut seen: HashSet<String> = order.iter().cloned().collect();
+    for id in theirs_stage.order.iter().chain(&base_stage.order) {
+        if seen.insert(id.clone()) {
+            order.push(id.clone());
+        }
+    }
+
+    let mut merged = FlatStage::default();
+    let mut conflicts = Vec::new();
+    let mut notes = Vec::new();
+    for id in &order {
+        let result = merge_object(
+            id,
+            base_stage.objects.get(id),
+            ours_stage.objects.get(id),
+            theirs_stage.objects.get(id),
+        );
+        let object = match result {
+            Merged::Keep(object) => object,
+            Merged::Delete => continue,
+            Merged::Conflict(object, reason, note) => {
+                conflicts.push(MergeConflict {
+                    uuid: id.clone(),
+                    label: label(id, &object),
+                    reason,
+                });
+                notes.push((id.clone(), note));
+                object
+            }
+        };
+        merged.order.push(id.clone());
+        merged.objects.insert(id.clone(), object);
+    }
+
+    // 端点被删除的连线无法保留
+    loop {
+        let ids: HashSet<&str> = merged.order.iter().map(String::as_str).collect();
+        let dangling: Vec<String> = merged
+            .order
+            .iter()
+            .filter(|id| {
+                merged.objects[*id]
+                    .get("associationList")
+                    .is_some_and(|list| contains_ref(list, &ids))
+            })
+            .cloned()
+            .collect();
+        if dangling.is_empty() {
+            break;
+        }
+        for id in dangling {
+            let object = merged.objects.remove(&id).unwrap();
+            conflicts.push(MergeConflict {
+                label: label(&id, &object),
+                uuid: id.clone(),
+                reason: "连接的对象已被删除，连线被丢弃".to_string(),
+            });
+            merged.order.retain(|other| *other != id);
+        }
+    }
+    let ids: HashSet<String> = merged.order.iter().cloned().collect();
+    merged
+        .objects
+        .values_mut()
+        .for_each(|object| remove_dangling(object, &ids));
+
+    for (id, note) in notes {
+        let Some(object) = merged.objects.get(&id) else {
+            continue;
+        };
+        let node = conflict_node(&id, note, anchor(&merged.objects, object));
+        let node_id = format!("{}{}", id, CONFLICT_SUFFIX);
+        merged.order.push(node_id.clone());
+        merged.objects.insert(node_id, node);
+    }
+
+    let mut tags = merge_tags(&base.tags, &ours.tags, &theirs.tags);
+    tags.retain(|tag| merged.objects.contains_key(tag));
+
+    // 附件取并集，同名时以我方为准
+    let attachments: BTreeMap<String, PrgAttachment> = theirs
+        .attachments
+        .iter()
+        .chain(&ours.attachments)
+        .map(|attachment| (attachment.entry_name(), attachment.clone()))
+        .collect();
+
+    if ours.readme != base.readme && theirs.readme != base.readme && ours.readme != theirs.readme {
+        conflicts.push(MergeConflict {
+            uuid: String::new(),
+            label: "README".to_string(),
+            reason: "README 在两边都被修改，已保留我方的版本".to_string(),
+        });
+    }
+
+    let project = PrgProject {
+        stage: unflatten(merged),
+        tags,
+        references: merge_whole(&base.references, &ours.references, &theirs.references),
+        metadata: merge_whole(&base.metadata, &ours.metadata, &theirs.metadata),
+        readme: merge_whole(&base.readme, &ours.readme, &theirs.readme),
+        attachments: attachments.into_values().collect(),
+        thumbnail: merge_whole(&base.thumbnail, &ours.thumbnail, &theirs.thumbnail),
+    };
+    (project, conflicts)
+}
+
+/// 合并三个 .prg 文件并写入 output，base 不存在时（两边各自新建）视为空工程
+pub fn merge_files(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    output: &Path,
+) -> Result<PrgMergeResult, String> {
+    let base = super::diff::open_or_empty(base)?;
+    let ours = PrgProject::open(ours)?;
+    let theirs = PrgProject::open(theirs)?;
+    let (project, conflicts) = merge(&base, &ours, &theirs);
+    let objects = project
+        .stage
+        .iter()
+        .filter(|object| !stage::uuid(object).is_some_and(|id| id.ends_with(CONFLICT_SUFFIX)))
+        .count();
+    project.save(output)?;
+    Ok(PrgMergeResult { conflicts, objects })
+}
+
+/// 三方合并 .prg 文件，结果写入 output
+#[tauri::command]
+pub async fn merge_prg(
+    scope: State<'_, FsScope>,
+    base: String,
+    ours: String,
+    theirs: String,
+    output: String,
+) -> Result<PrgMergeResult, String> {
+    for path in [&base, &ours, &theirs, &output] {
+        scope.check(Path::new(path))?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        merge_files(
+            Path::new(&base),
+            Path::new(&ours),
+            Path::new(&theirs),
+            Path::new(&output),
+        )
+    })
+    .await
+    .map_err(|e| format!("合并任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn node(uuid: &str, text: &str, x: f64) -> Value {
+        json!({
+            "_": "TextNode",
+            "uuid": uuid,
+            "text": text,
+            "color": [0, 0, 0, 0],
+            "collisionBox": {
+                "_": "CollisionBox",
+                "shapes": [{
+                    "_": "Rectangle",
+                    "location": { "_": "Vector", "x": x, "y": 0 },
+                    "size": { "_": "Vector", "x": 100, "y": 40 }
+                }]
+            }
+        })
+    }
+
+    fn edge(uuid: &str, source: Value, target: Value) -> Value {
+        json!({
+            "_": "LineEdge",
+            "uuid": uuid,
+            "text": "",
+            "associationList": [source, target]
+        })
+    }
+
+    fn find<'a>(stage: &'a [Value], id: &str) -> &'a Value {
+        stage
+            .iter()
+            .find(|object| stage::uuid(object) == Some(id))
+            .unwrap()
+    }
+
+    fn endpoints(stage: &[Value], edge: &Value) -> Vec<String> {
+        edge["associationList"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|end| {
+                let end = stage::resolve(stage, end).unwrap();
+                stage::uuid(end).unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_by_uuid_and_marks_conflicts() {
+        let base = PrgProject {
+            stage: vec![
+                node("a", "甲", 0.0),
+                node("b", "乙", 100.0),
+                node("c", "丙", 200.0),
+                edge("e", json!({ "$": "/0" }), json!({ "$": "/1" })),
+            ],
+            ..Default::default()
+        };
+        let mut recolored = node("b", "乙", 100.0);
+        recolored["color"] = json!([255, 0, 0, 1]);
+        let ours = PrgProject {
+            stage: vec![
+                node("a", "甲我方", 0.0),
+                node("b", "乙", 150.0),
+                edge("e", json!({ "$": "/0" }), json!({ "$": "/1" })),
+                node("d", "丁", 300.0),
+            ],
+            ..Default::default()
+        };
+        // 对方的连线内嵌了第一次出现的节点，引用按位置指向它
+        let theirs = PrgProject {
+            stage: vec![
+                edge("f", json!({ "$": "/2" }), node("c", "丙改", 200.0)),
+                node("a", "甲对方", 0.0),
+                recolored,
+                edge("e", json!({ "$": "/1" }), json!({ "$": "/2" })),
+            ],
+            attachments: vec![PrgAttachment {
+                id: "img".to_string(),
+                ext: "png".to_string(),
+                data: vec![1, 2, 3],
+            }],
+            ..Default::default()
+        };
+
+        let (merged, conflicts) = merge(&base, &ours, &theirs);
+        let stage = &merged.stage;
+
+        let mut reasons: Vec<(&str, &str)> = conflicts
+            .iter()
+            .map(|conflict| (conflict.uuid.as_str(), conflict.reason.as_str()))
+            .collect();
+        reasons.sort();
+        assert_eq!(
+            reasons,
+            vec![
+                ("a", "文字 在两边都被修改"),
+                ("c", "在我方被删除，在对方被修改"),
+            ]
+        );
+        let note = find(stage, "a-merge-conflict");
+        assert!(note["text"]
+            .as_str()
+            .unwrap()
+            .contains("我方：甲我方\n对方：甲对方"));
+        assert_eq!(note["color"]["r"], 239);
+        assert_eq!(find(stage, "a")["text"], "甲我方");
+        assert!(stage::uuid(find(stage, "c-merge-conflict")).is_some());
+
+        // 移动和改颜色来自不同的一边，自动合并
+        let b = find(stage, "b");
+        assert_eq!(b["color"], json!([255, 0, 0, 1]));
+        assert_eq!(location(b), Some((150.0, 0.0)));
+
+        assert_eq!(endpoints(stage, find(stage, "e")), vec!["a", "b"]);
+        assert_eq!(endpoints(stage, find(stage, "f")), vec!["b", "c"]);
+        assert_eq!(find(stage, "c")["text"], "丙改");
+        assert!(stage.iter().any(|object| stage::uuid(object) == Some("d")));
+        assert_eq!(merged.attachments, theirs.attachments);
+        assert_eq!(
+            stage
+                .iter()
+                .filter(|object| stage::uuid(object).is_some())
+                .count(),
+            8
+        );
+    }
+}
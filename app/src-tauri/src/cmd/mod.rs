@@ -1,6 +1,26 @@
 pub mod device;
+pub mod diagram;
+pub mod diff;
 pub mod fs;
+pub mod hash;
+pub mod history;
 #[cfg(desktop)]
 pub mod mcp;
+pub mod merge;
 pub mod paddle;
+pub mod prg;
+#[cfg(desktop)]
+pub mod pty;
+pub mod repair;
+pub mod scope;
+#[cfg(desktop)]
+pub mod search;
 pub mod shell;
+pub mod shell_policy;
+pub mod stage;
+pub mod task;
+pub mod thumbnail;
+pub mod transfer;
+pub mod trash;
+#[cfg(desktop)]
+pub mod watch;
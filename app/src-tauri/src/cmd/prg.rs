@@ -0,0 +1,694 @@
+//! .prg 工程文件的读写
+//!
+//! .prg 是一个 ZIP 容器，内部包含：
+//! - stage.msgpack：序列化后的舞台对象数组
+//! - tags.msgpack：标签（舞台对象 UUID 列表）
+//! - reference.msgpack：反向引用数据
+//! - metadata.msgpack：文件元数据（版本号、扩展信息等）
+//! - README.md：可选的说明文档
+//! - attachments/{uuid}.{ext}：附件
+//! - thumbnail.png：可选的缩略图
+//!
+//! 与前端 `Project.tsx` 中的 `parseProjectFile` / `getFileContent` 保持同一格式。
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use tauri::State;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::scope::FsScope;
+
+pub const STAGE_ENTRY: &str = "stage.msgpack";
+pub const TAGS_ENTRY: &str = "tags.msgpack";
+pub const REFERENCE_ENTRY: &str = "reference.msgpack";
+pub const METADATA_ENTRY: &str = "metadata.msgpack";
+pub const README_ENTRY: &str = "README.md";
+pub const THUMBNAIL_ENTRY: &str = "thumbnail.png";
+pub const ATTACHMENTS_PREFIX: &str = "attachments/";
+
+/// 没有 metadata.msgpack 或其内容无效时使用的版本号
+pub const DEFAULT_VERSION: &str = "2.0.0";
+
+/// 扩展（插件）的元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionMetadata {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub author: String,
+}
+
+/// metadata.msgpack 的内容
+/// 未识别的字段会原样保留，保存时写回
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrgMetadata {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<ExtensionMetadata>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for PrgMetadata {
+    fn default() -> Self {
+        Self {
+            version: DEFAULT_VERSION.to_string(),
+            extension: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+/// reference.msgpack 的内容
+/// sections：被引用的 Section 名称 -> 引用它的文件列表
+/// files：引用了整个文件的其他文件列表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrgReferences {
+    #[serde(default)]
+    pub sections: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// 附件，对应 attachments/{id}.{ext}
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrgAttachment {
+    pub id: String,
+    pub ext: String,
+    pub data: Vec<u8>,
+}
+
+impl PrgAttachment {
+    pub fn entry_name(&self) -> String {
+        format!("{}{}.{}", ATTACHMENTS_PREFIX, self.id, self.ext)
+    }
+}
+
+/// 一个完整的 .prg 工程
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrgProject {
+    /// 序列化后的舞台对象，结构由前端的 @graphif/serializer 决定
+    pub stage: Vec<Value>,
+    pub tags: Vec<String>,
+    pub references: PrgReferences,
+    pub metadata: PrgMetadata,
+    pub readme: Option<String>,
+    pub attachments: Vec<PrgAttachment>,
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+fn attachment_name_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^attachments/([a-zA-Z0-9-]+)\.([a-zA-Z0-9]+)$").unwrap())
+}
+
+/// 解析附件条目名，返回 (id, ext)
+/// 不符合 attachments/{uuid}.{ext} 规范时返回 None
+pub fn parse_attachment_name(name: &str) -> Option<(String, String)> {
+    let caps = attachment_name_regex().captures(name.trim())?;
+    Some((caps[1].to_string(), caps[2].to_string()))
+}
+
+/// 根据扩展名推断附件的 MIME 类型，与前端 mime.getType() 的常见结果一致
+pub fn mime_from_ext(ext: &str) -> &'static str {
+    match ext.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/vnd.microsoft.icon",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 解码 msgpack
+pub fn decode_msgpack<T: serde::de::DeserializeOwned>(
+    name: &str,
+    bytes: &[u8],
+) -> Result<T, String> {
+    rmp_serde::from_slice(bytes).map_err(|e| format!("无法解析 {}: {}", name, e))
+}
+
+/// 编码 msgpack，结构体按字段名编码为 map，与前端 @msgpack/msgpack 的结果一致
+pub fn encode_msgpack<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<Vec<u8>, String> {
+    rmp_serde::to_vec_named(value).map_err(|e| format!("无法编码 {}: {}", name, e))
+}
+
+/// 读取 ZIP 中的一个条目
+pub fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("无法读取 {}: {}", name, e)),
+    };
+    let mut buf = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut buf)
+        .map_err(|e| format!("无法读取 {}: {}", name, e))?;
+    Ok(Some(buf))
+}
+
+/// 打开 .prg 文件对应的 ZIP
+pub fn open_archive(path: &Path) -> Result<ZipArchive<std::io::BufReader<std::fs::File>>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    ZipArchive::new(std::io::BufReader::new(file))
+        .map_err(|e| format!("不是有效的 .prg 文件: {}", e))
+}
+
+impl PrgProject {
+    /// 从任意 ZIP 读取器中解析工程
+    pub fn read_from<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Self, String> {
+        let mut project = PrgProject::read_without_binaries(archive)?;
+        project.thumbnail = read_entry(archive, THUMBNAIL_ENTRY)?;
+
+        let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+        for name in names {
+            if let Some((id, ext)) = parse_attachment_name(&name) {
+                if let Some(data) = read_entry(archive, &name)? {
+                    project.attachments.push(PrgAttachment { id, ext, data });
+                }
+            }
+        }
+        project.attachments.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(project)
+    }
+
+    /// 只解析 msgpack 条目和 README，不读取附件和缩略图
+    fn read_without_binaries<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Self, String> {
+        let mut project = PrgProject::default();
+        if let Some(bytes) = read_entry(archive, STAGE_ENTRY)? {
+            project.stage = decode_msgpack(STAGE_ENTRY, &bytes)?;
+        }
+        if let Some(bytes) = read_entry(archive, TAGS_ENTRY)? {
+            project.tags = decode_msgpack(TAGS_ENTRY, &bytes)?;
+        }
+        if let Some(bytes) = read_entry(archive, REFERENCE_ENTRY)? {
+            project.references = decode_msgpack(REFERENCE_ENTRY, &bytes)?;
+        }
+        if let Some(bytes) = read_entry(archive, METADATA_ENTRY)? {
+            // 格式不正确时使用默认值，与前端 isValidMetadata 的处理保持一致
+            project.metadata = decode_msgpack(METADATA_ENTRY, &bytes).unwrap_or_default();
+        }
+        if let Some(bytes) = read_entry(archive, README_ENTRY)? {
+            project.readme = Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        Ok(project)
+    }
+
+    /// 从内存中的 .prg 文件内容解析工程
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))
+            .map_err(|e| format!("不是有效的 .prg 文件: {}", e))?;
+        Self::read_from(&mut archive)
+    }
+
+    /// 从磁盘读取 .prg 文件
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Self::read_from(&mut open_archive(path)?)
+    }
+
+    /// 写入 ZIP，条目顺序与前端保存的一致，全部不压缩
+    pub fn write_to<W: Write + Seek>(&self, writer: W) -> Result<W, String> {
+        let mut zip = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut add = |name: &str, bytes: &[u8]| -> Result<(), String> {
+            zip.start_file(name, options)
+                .map_err(|e| format!("无法写入 {}: {}", name, e))?;
+            zip.write_all(bytes)
+                .map_err(|e| format!("无法写入 {}: {}", name, e))
+        };
+
+        add(STAGE_ENTRY, &encode_msgpack(STAGE_ENTRY, &self.stage)?)?;
+        add(TAGS_ENTRY, &encode_msgpack(TAGS_ENTRY, &self.tags)?)?;
+        add(
+            REFERENCE_ENTRY,
+            &encode_msgpack(REFERENCE_ENTRY, &self.references)?,
+        )?;
+        add(
+            METADATA_ENTRY,
+            &encode_msgpack(METADATA_ENTRY, &self.metadata)?,
+        )?;
+        if let Some(readme) = &self.readme {
+            add(README_ENTRY, readme.as_bytes())?;
+        }
+        for attachment in &self.attachments {
+            add(&attachment.entry_name(), &attachment.data)?;
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            add(THUMBNAIL_ENTRY, thumbnail)?;
+        }
+
+        zip.finish()
+            .map_err(|e| format!("无法完成 ZIP 写入: {}", e))
+    }
+
+    /// 序列化为 .prg 文件内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        Ok(self.write_to(Cursor::new(Vec::new()))?.into_inner())
+    }
+
+    /// 原子地保存到磁盘，写入中途失败不会损坏原文件
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        Ok(super::fs::write_file_atomic(
+            path,
+            &self.to_bytes()?,
+            false,
+        )?)
+    }
+}
+
+/// 附件的描述信息，不含内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgAttachmentInfo {
+    pub id: String,
+    pub ext: String,
+    pub mime: String,
+    pub size: u64,
+}
+
+/// open_prg 的返回值
+/// 附件和缩略图只返回描述信息，内容通过 read_prg_attachment / read_prg_thumbnail 按需读取，
+/// 避免把整个文件编码成 base64 传给前端
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgDocument {
+    pub stage: Vec<Value>,
+    pub tags: Vec<String>,
+    pub references: PrgReferences,
+    pub metadata: PrgMetadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readme: Option<String>,
+    pub attachments: Vec<PrgAttachmentInfo>,
+    pub has_thumbnail: bool,
+}
+
+/// 保存时传入的附件
+/// data 为空时从 copy_from 指向的 .prg 文件（通常就是正在覆盖的旧文件）中复制原内容
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgAttachmentInput {
+    pub id: String,
+    pub ext: String,
+    #[serde(default)]
+    pub data: Option<Vec<u8>>,
+}
+
+/// save_prg 的参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgSaveRequest {
+    pub stage: Vec<Value>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub references: PrgReferences,
+    #[serde(default)]
+    pub metadata: PrgMetadata,
+    #[serde(default)]
+    pub readme: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<PrgAttachmentInput>,
+    #[serde(default)]
+    pub thumbnail: Option<Vec<u8>>,
+    /// 未提供内容的附件从这个文件中复制
+    #[serde(default)]
+    pub copy_from: Option<String>,
+}
+
+/// validate_prg 的结果
+/// errors：无法打开的问题；warnings：可以打开但与规范不符的问题
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgValidation {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+fn read_document(path: &Path) -> Result<PrgDocument, String> {
+    let mut archive = open_archive(path)?;
+    let mut attachments = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("无法读取 ZIP 目录: {}", e))?;
+        if let Some((id, ext)) = parse_attachment_name(file.name()) {
+            attachments.push(PrgAttachmentInfo {
+                mime: mime_from_ext(&ext).to_string(),
+                id,
+                ext,
+                size: file.size(),
+            });
+        }
+    }
+    attachments.sort_by(|a, b| a.id.cmp(&b.id));
+    let has_thumbnail = archive.index_for_name(THUMBNAIL_ENTRY).is_some();
+
+    let project = PrgProject::read_without_binaries(&mut archive)?;
+
+    Ok(PrgDocument {
+        stage: project.stage,
+        tags: project.tags,
+        references: project.references,
+        metadata: project.metadata,
+        readme: project.readme,
+        attachments,
+        has_thumbnail,
+    })
+}
+
+/// 检查 .prg 文件是否符合格式规范
+pub fn validate(path: &Path) -> PrgValidation {
+    let mut report = PrgValidation::default();
+    let mut archive = match open_archive(path) {
+        Ok(archive) => archive,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+
+    match read_entry(&mut archive, STAGE_ENTRY) {
+        Ok(Some(bytes)) => {
+            if let Err(e) = decode_msgpack::<Vec<Value>>(STAGE_ENTRY, &bytes) {
+                report.errors.push(e);
+            }
+        }
+        Ok(None) => report.errors.push(format!("缺少 {}", STAGE_ENTRY)),
+        Err(e) => report.errors.push(e),
+    }
+    match read_entry(&mut archive, TAGS_ENTRY) {
+        Ok(Some(bytes)) => {
+            if let Err(e) = decode_msgpack::<Vec<String>>(TAGS_ENTRY, &bytes) {
+                report.errors.push(e);
+            }
+        }
+        Ok(None) => report.warnings.push(format!("缺少 {}", TAGS_ENTRY)),
+        Err(e) => report.errors.push(e),
+    }
+    match read_entry(&mut archive, REFERENCE_ENTRY) {
+        Ok(Some(bytes)) => {
+            if let Err(e) = decode_msgpack::<PrgReferences>(REFERENCE_ENTRY, &bytes) {
+                report.errors.push(e);
+            }
+        }
+        Ok(None) => report.warnings.push(format!("缺少 {}", REFERENCE_ENTRY)),
+        Err(e) => report.errors.push(e),
+    }
+    match read_entry(&mut archive, METADATA_ENTRY) {
+        Ok(Some(bytes)) => {
+            if let Err(e) = decode_msgpack::<PrgMetadata>(METADATA_ENTRY, &bytes) {
+                report
+                    .warnings
+                    .push(format!("{}，将使用默认版本 {}", e, DEFAULT_VERSION));
+            }
+        }
+        Ok(None) => report.warnings.push(format!(
+            "缺少 {}，将使用默认版本 {}",
+            METADATA_ENTRY, DEFAULT_VERSION
+        )),
+        Err(e) => report.errors.push(e),
+    }
+
+    let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+    for name in names {
+        let known = matches!(
+            name.as_str(),
+            STAGE_ENTRY
+                | TAGS_ENTRY
+                | REFERENCE_ENTRY
+                | METADATA_ENTRY
+                | README_ENTRY
+                | THUMBNAIL_ENTRY
+        );
+        if known || name.ends_with('/') {
+            continue;
+        }
+        if name.starts_with(ATTACHMENTS_PREFIX) {
+            if parse_attachment_name(&name).is_none() {
+                report
+                    .warnings
+                    .push(format!("附件文件名不符合规范，将被忽略: {}", name));
+            } else if let Err(e) = read_entry(&mut archive, &name) {
+                report.errors.push(e);
+            }
+        } else {
+            report
+                .warnings
+                .push(format!("未知的条目，将被忽略: {}", name));
+        }
+    }
+
+    report.valid = report.errors.is_empty();
+    report
+}
+
+/// 根据保存请求组装工程，没有内容的附件从 copy_from 中复制
+pub fn build_project(request: PrgSaveRequest) -> Result<PrgProject, String> {
+    let mut source = None;
+    let mut attachments = Vec::with_capacity(request.attachments.len());
+    for input in request.attachments {
+        // 不符合规范的附件在下次打开时会被忽略，保存前直接报错，避免丢失数据
+        let name = format!("{}{}.{}", ATTACHMENTS_PREFIX, input.id, input.ext);
+        if parse_attachment_name(&name) != Some((input.id.clone(), input.ext.clone())) {
+            return Err(format!("附件名不符合规范: {}", name));
+        }
+        let data = match input.data {
+            Some(data) => data,
+            None => {
+                if source.is_none() {
+                    let copy_from = request
+                        .copy_from
+                        .as_ref()
+                        .ok_or_else(|| format!("附件 {} 没有内容，且未指定 copyFrom", input.id))?;
+                    source = Some(open_archive(Path::new(copy_from))?);
+                }
+                read_entry(source.as_mut().unwrap(), &name)?
+                    .ok_or_else(|| format!("原文件中不存在附件 {}", name))?
+            }
+        };
+        attachments.push(PrgAttachment {
+            id: input.id,
+            ext: input.ext,
+            data,
+        });
+    }
+
+    Ok(PrgProject {
+        stage: request.stage,
+        tags: request.tags,
+        references: request.references,
+        metadata: request.metadata,
+        readme: request.readme,
+        attachments,
+        thumbnail: request.thumbnail,
+    })
+}
+
+/// 打开 .prg 文件，返回舞台数据和附件列表
+#[tauri::command]
+pub async fn open_prg(scope: State<'_, FsScope>, path: String) -> Result<PrgDocument, String> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || read_document(Path::new(&path)))
+        .await
+        .map_err(|e| format!("读取任务失败: {}", e))?
+}
+
+/// 读取 .prg 文件中的一个附件，以二进制形式返回
+#[tauri::command]
+pub fn read_prg_attachment(
+    scope: State<'_, FsScope>,
+    path: String,
+    id: String,
+) -> Result<tauri::ipc::Response, String> {
+    scope.check(Path::new(&path))?;
+    let mut archive = open_archive(Path::new(&path))?;
+    let name = archive
+        .file_names()
+        .find(|name| parse_attachment_name(name).is_some_and(|(entry_id, _)| entry_id == id))
+        .map(|name| name.to_string())
+        .ok_or_else(|| format!("附件不存在: {}", id))?;
+    let data = read_entry(&mut archive, &name)?.unwrap_or_default();
+    Ok(tauri::ipc::Response::new(data))
+}
+
+/// 读取 .prg 文件中的缩略图，以二进制形式返回
+#[tauri::command]
+pub fn read_prg_thumbnail(
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<tauri::ipc::Response, String> {
+    scope.check(Path::new(&path))?;
+    let mut archive = open_archive(Path::new(&path))?;
+    let data =
+        read_entry(&mut archive, THUMBNAIL_ENTRY)?.ok_or_else(|| "文件中没有缩略图".to_string())?;
+    Ok(tauri::ipc::Response::new(data))
+}
+
+/// 检查 .prg 文件的格式
+#[tauri::command]
+pub async fn validate_prg(
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<PrgValidation, String> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || validate(Path::new(&path)))
+        .await
+        .map_err(|e| format!("检查任务失败: {}", e))
+}
+
+/// 保存 .prg 文件
+#[tauri::command]
+pub async fn save_prg(
+    scope: State<'_, FsScope>,
+    path: String,
+    project: PrgSaveRequest,
+) -> Result<(), String> {
+    scope.check(Path::new(&path))?;
+    if let Some(copy_from) = &project.copy_from {
+        scope.check(Path::new(copy_from))?;
+    }
+    tauri::async_runtime::spawn_blocking(move || build_project(project)?.save(Path::new(&path)))
+        .await
+        .map_err(|e| format!("保存任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_project() -> PrgProject {
+        PrgProject {
+            stage: vec![json!({
+                "_": "TextNode",
+                "uuid": "a",
+                "text": "你好",
+                "collisionBox": {
+                    "_": "CollisionBox",
+                    "shapes": [{
+                        "_": "Rectangle",
+                        "location": { "_": "Vector", "x": 1.5, "y": -2 },
+                        "size": { "_": "Vector", "x": 100, "y": 40 }
+                    }]
+                }
+            })],
+            tags: vec!["a".to_string()],
+            references: PrgReferences {
+                sections: BTreeMap::from([("章节".to_string(), vec!["other".to_string()])]),
+                files: vec![],
+            },
+            metadata: PrgMetadata {
+                version: "2.1.0".to_string(),
+                extension: None,
+                extra: Map::from_iter([("custom".to_string(), json!(true))]),
+            },
+            readme: Some("# readme".to_string()),
+            attachments: vec![PrgAttachment {
+                id: "0b6e2f7c-1d2a-4c1e-9f3a-1234567890ab".to_string(),
+                ext: "png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G'],
+            }],
+            thumbnail: Some(vec![1, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn round_trips_all_entries() {
+        let project = sample_project();
+        let bytes = project.to_bytes().expect("project should encode");
+        let decoded = PrgProject::from_bytes(&bytes).expect("project should decode");
+        assert_eq!(decoded, project);
+    }
+
+    #[test]
+    fn keeps_object_key_order() {
+        // 前端的反序列化按位置把字段传给构造函数，键的顺序变了颜色等就会错乱
+        let mut project = sample_project();
+        project.stage = vec![json!({ "_": "Color", "r": 1, "g": 2, "b": 3, "a": 0.5 })];
+        let bytes = project.to_bytes().expect("project should encode");
+        let decoded = PrgProject::from_bytes(&bytes).expect("project should decode");
+        let keys: Vec<&str> = decoded.stage[0]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(keys, ["_", "r", "g", "b", "a"]);
+    }
+
+    #[test]
+    fn parses_attachment_names_like_the_frontend() {
+        assert_eq!(
+            parse_attachment_name("attachments/abc-123.png"),
+            Some(("abc-123".to_string(), "png".to_string()))
+        );
+        assert_eq!(parse_attachment_name("attachments/a.b.png"), None);
+        assert_eq!(parse_attachment_name("attachments/.png"), None);
+        assert_eq!(parse_attachment_name("stage.msgpack"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_attachment_names_before_saving() {
+        let request = |id: &str, ext: &str| PrgSaveRequest {
+            stage: vec![],
+            tags: vec![],
+            references: PrgReferences::default(),
+            metadata: PrgMetadata::default(),
+            readme: None,
+            attachments: vec![PrgAttachmentInput {
+                id: id.to_string(),
+                ext: ext.to_string(),
+                data: Some(vec![1]),
+            }],
+            thumbnail: None,
+            copy_from: None,
+        };
+        assert!(build_project(request("abc-123", "png")).is_ok());
+        assert!(build_project(request("a.b", "png")).is_err());
+        assert!(build_project(request("abc", "")).is_err());
+        assert!(build_project(request(" abc", "png")).is_err());
+        assert!(build_project(request("../abc", "png")).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_default_metadata() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        zip.start_file(STAGE_ENTRY, options).unwrap();
+        zip.write_all(&encode_msgpack(STAGE_ENTRY, &Vec::<Value>::new()).unwrap())
+            .unwrap();
+        zip.start_file(METADATA_ENTRY, options).unwrap();
+        zip.write_all(&encode_msgpack(METADATA_ENTRY, &json!({ "foo": 1 })).unwrap())
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let project = PrgProject::from_bytes(&bytes).expect("project should decode");
+        assert_eq!(project.metadata.version, DEFAULT_VERSION);
+        assert!(project.stage.is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+//! 伪终端（PTY）会话
+//!
+//! run_command / spawn_command 的输出是管道，交互式的程序（REPL、vim、top 等）无法使用。
+//! 这里在 PTY 中启动 shell，前端把按键原样写入，输出的原始字节通过 Channel 发送，由终端组件解析
+//!
+//! - open_pty：启动会话，与 spawn_command 一样运行前按 cmd::shell_policy 检查
+//! - write_pty / resize_pty / close_pty：与会话交互
+//!
+//! 会话只能由启动它的窗口操作，窗口关闭时会话会被结束。
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+use tauri::{Manager, Runtime, State, Window};
+
+use super::shell::{CommandOptions, ProcessExit};
+use super::shell_policy::{CommandRequest, ShellPolicy};
+
+/// open_pty 通过 Channel 发送的事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event", content = "data")]
+pub enum PtyEvent {
+    /// 终端输出的原始字节（base64），可能在 UTF-8 字符或转义序列的中间断开
+    Output(String),
+    /// shell 已结束，之后不会再有其他事件
+    Exit(ProcessExit),
+}
+
+struct PtySession {
+    window: String,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+}
+
+impl PtySession {
+    fn kill(&self) {
+        let _ = self.killer.lock().unwrap().kill();
+    }
+}
+
+type SessionMap = Arc<Mutex<HashMap<u32, Arc<PtySession>>>>;
+
+/// open_pty 启动的会话
+#[derive(Default)]
+pub struct PtyRegistry {
+    sessions: SessionMap,
+    next_id: AtomicU32,
+}
+
+impl PtyRegistry {
+    /// 只能操作本窗口启动的会话，否则其他窗口可以绕过 cmd::shell_policy 在已批准的 shell 中输入
+    fn get(&self, id: u32, window: &str) -> Result<Arc<PtySession>, String> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .filter(|session| session.window == window)
+            .cloned()
+            .ok_or_else(|| format!("终端 {} 不存在或已经结束", id))
+    }
+
+    /// 结束某个窗口启动的所有会话
+    pub fn kill_window(&self, window: &str) {
+        for session in self.sessions.lock().unwrap().values() {
+            if session.window == window {
+                session.kill();
+            }
+        }
+    }
+}
+
+/// 没有指定 shell 时使用的程序
+pub fn default_shell() -> String {
+    #[cfg(windows)]
+    {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "powershell.exe".to_string())
+    }
+    #[cfg(not(windows))]
+    {
+        std::env::var("SHELL")
+            .ok()
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| "/bin/sh".to_string())
+    }
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+fn pty_exit(status: portable_pty::ExitStatus) -> ProcessExit {
+    match status.signal() {
+        Some(signal) => ProcessExit {
+            signal_name: Some(signal.to_string()),
+            ..Default::default()
+        },
+        None => ProcessExit {
+            code: Some(status.exit_code() as i32),
+            ..Default::default()
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtySessionInfo {
+    /// 传给 write_pty / resize_pty / close_pty 的 ID
+    pub id: u32,
+    /// 操作系统的进程 ID
+    pub pid: Option<u32>,
+    /// 实际启动的 shell
+    pub shell: String,
+}
+
+/// 在 PTY 中启动 shell
+/// shell 为空时使用用户的默认 shell，options 中只有 cwd、env、clearEnv 生效
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn open_pty<R: Runtime>(
+    window: Window<R>,
+    shell: Option<String>,
+    shell_args: Option<Vec<String>>,
+    cols: u16,
+    rows: u16,
+    options: Option<CommandOptions>,
+    on_event: Channel<PtyEvent>,
+    caller: Option<String>,
+) -> Result<PtySessionInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let shell = shell.unwrap_or_else(default_shell);
+        let shell_args = shell_args.unwrap_or_default();
+        let options = options.unwrap_or_default();
+        let policy = window.state::<ShellPolicy>();
+        let request = CommandRequest::new(window.label(), caller, &shell, &shell_args, &options);
+        let authorized = policy.authorize(&window, request)?;
+
+        let pair = match native_pty_system().openpty(pty_size(cols, rows)) {
+            Ok(pair) => pair,
+            Err(e) => {
+                policy.finish(authorized, None);
+                return Err(format!("无法打开终端: {}", e));
+            }
+        };
+        let mut cmd = CommandBuilder::new(authorized.program());
+        cmd.args(&shell_args);
+        if options.clear_env {
+            cmd.env_clear();
+        }
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        for (key, value) in &options.env {
+            cmd.env(key, value);
+        }
+        match &options.cwd {
+            Some(cwd) => cmd.cwd(cwd),
+            None => {
+                if let Ok(home) = window.path().home_dir() {
+                    cmd.cwd(home);
+                }
+            }
+        }
+        let child = pair.slave.spawn_command(cmd);
+        // 关闭这一侧的 slave，shell 结束后读取才会结束
+        drop(pair.slave);
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                policy.finish(authorized, None);
+                return Err(format!("无法启动 {}: {}", shell, e));
+            }
+        };
+        let io = pair
+            .master
+            .try_clone_reader()
+            .and_then(|reader| Ok((reader, pair.master.take_writer()?)));
+        let (mut reader, writer) = match io {
+            Ok(io) => io,
+            Err(e) => {
+                let _ = child.kill();
+                policy.finish(authorized, None);
+                return Err(format!("无法连接终端: {}", e));
+            }
+        };
+
+        let registry = window.state::<PtyRegistry>();
+        let id = registry.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let pid = child.process_id();
+        let session = Arc::new(PtySession {
+            window: window.label().to_string(),
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            killer: Mutex::new(child.clone_killer()),
+        });
+        registry.sessions.lock().unwrap().insert(id, session);
+
+        let output = {
+            let on_event = on_event.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 8192];
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            let data = general_purpose::STANDARD.encode(&buf[..n]);
+                            let _ = on_event.send(PtyEvent::Output(data));
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                        // Linux 上 slave 全部关闭后读取返回 EIO
+                        Err(_) => break,
+                    }
+                }
+            })
+        };
+
+        let sessions = registry.sessions.clone();
+        let app = window.app_handle().clone();
+        std::thread::spawn(move || {
+            let exit = child.wait().map(pty_exit).unwrap_or_default();
+            // Windows 的 ConPTY 要关闭 master 之后读取才会结束
+            sessions.lock().unwrap().remove(&id);
+            let _ = output.join();
+            app.state::<ShellPolicy>().finish(authorized, Some(&exit));
+            let _ = on_event.send(PtyEvent::Exit(exit));
+        });
+
+        Ok(PtySessionInfo { id, pid, shell })
+    })
+    .await
+    .map_err(|e| format!("打开终端任务失败: {}", e))?
+}
+
+/// 写入终端，即用户的按键或粘贴的内容
+#[tauri::command]
+pub async fn write_pty<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, PtyRegistry>,
+    id: u32,
+    data: String,
+) -> Result<(), String> {
+    let session = registry.get(id, window.label())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut writer = session.writer.lock().unwrap();
+        writer
+            .write_all(data.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("无法写入终端 {}: {}", id, e))
+    })
+    .await
+    .map_err(|e| format!("写入终端任务失败: {}", e))?
+}
+
+/// 调整终端的行列数，shell 会收到 SIGWINCH
+#[tauri::command]
+pub fn resize_pty<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, PtyRegistry>,
+    id: u32,
+    cols: u16,
+    rows: u16,
+) -> Result<(), String> {
+    registry
+        .get(id, window.label())?
+        .master
+        .lock()
+        .unwrap()
+        .resize(pty_size(cols, rows))
+        .map_err(|e| format!("无法调整终端 {} 的大小: {}", id, e))
+}
+
+/// 结束终端中的 shell，之后会收到 Exit 事件
+#[tauri::command]
+pub fn close_pty<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, PtyRegistry>,
+    id: u32,
+) -> Result<(), String> {
+    registry.get(id, window.label())?.kill();
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_an_interactive_shell_in_a_pty() {
+        let pair = native_pty_system().openpty(pty_size(80, 24)).unwrap();
+        let mut cmd = CommandBuilder::new("sh");
+        cmd.env("PS1", "");
+        let mut child = pair.slave.spawn_command(cmd).unwrap();
+        drop(pair.slave);
+        let mut reader = pair.master.try_clone_reader().unwrap();
+        let mut writer = pair.master.take_writer().unwrap();
+
+        pair.master.resize(pty_size(120, 40)).unwrap();
+        writer
+            .write_all(b"[ -t 0 ] && echo tty-$(stty size | tr ' ' x); exit 7\n")
+            .unwrap();
+        let exit = pty_exit(child.wait().unwrap());
+        drop(writer);
+        drop(pair.master);
+
+        let mut output = Vec::new();
+        let mut buf = [0u8; 1024];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        assert!(String::from_utf8_lossy(&output).contains("tty-40x120"));
+        assert_eq!(exit.code, Some(7));
+    }
+
+    #[test]
+    fn only_the_owning_window_can_use_a_session() {
+        let pair = native_pty_system().openpty(pty_size(80, 24)).unwrap();
+        let mut child = pair.slave.spawn_command(CommandBuilder::new("sh")).unwrap();
+        drop(pair.slave);
+        let registry = PtyRegistry::default();
+        registry.sessions.lock().unwrap().insert(
+            1,
+            Arc::new(PtySession {
+                window: "main".to_string(),
+                writer: Mutex::new(pair.master.take_writer().unwrap()),
+                master: Mutex::new(pair.master),
+                killer: Mutex::new(child.clone_killer()),
+            }),
+        );
+
+        assert!(registry.get(1, "other").is_err());
+        registry.get(1, "main").unwrap().kill();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,644 @@
+//! .prg 文件的完整性检查与修复
+//!
+//! 文件被截断或某个条目损坏时，前端会直接打开失败。
+//! verify_prg 逐个条目检查 CRC 并解码 msgpack，报告具体哪些部分损坏；
+//! repair_prg 把能用的部分救出来写入一个新文件，原文件不会被修改。
+//!
+//! ZIP 的中央目录位于文件末尾，文件被截断后就无法按目录读取，
+//! 这时从头扫描每个条目的本地文件头来找回条目。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use zip::ZipArchive;
+
+use super::prg::{
+    decode_msgpack, parse_attachment_name, PrgAttachment, PrgMetadata, PrgProject, PrgReferences,
+    METADATA_ENTRY, README_ENTRY, REFERENCE_ENTRY, STAGE_ENTRY, TAGS_ENTRY, THUMBNAIL_ENTRY,
+};
+use super::scope::FsScope;
+
+const LOCAL_HEADER_SIGNATURE: &[u8] = b"PK\x03\x04";
+const DATA_DESCRIPTOR_SIGNATURE: &[u8] = b"PK\x07\x08";
+
+/// 从 ZIP 中读出的一个条目
+/// 读取失败时 data 中是已经读出的部分
+struct RawEntry {
+    name: String,
+    data: Vec<u8>,
+    error: Option<String>,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn find(bytes: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| position + from)
+}
+
+/// 把一个条目单独包装成只有一个条目的 ZIP，交给 zip 解压并校验 CRC
+fn read_single_entry(
+    name: &[u8],
+    flags: u16,
+    method: u16,
+    crc: u32,
+    uncompressed_size: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, (Vec<u8>, String)> {
+    let flags = flags & (1 << 11);
+    let mut zip = Vec::with_capacity(data.len() + name.len() * 2 + 100);
+    let header = |zip: &mut Vec<u8>| {
+        zip.extend_from_slice(&20u16.to_le_bytes());
+        zip.extend_from_slice(&flags.to_le_bytes());
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(&0x21u16.to_le_bytes());
+        zip.extend_from_slice(&crc.to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&uncompressed_size.to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+    };
+
+    zip.extend_from_slice(LOCAL_HEADER_SIGNATURE);
+    header(&mut zip);
+    zip.extend_from_slice(name);
+    zip.extend_from_slice(data);
+
+    let directory_offset = zip.len();
+    zip.extend_from_slice(b"PK\x01\x02");
+    zip.extend_from_slice(&20u16.to_le_bytes());
+    header(&mut zip);
+    // 注释长度、磁盘号、内部属性、外部属性、本地文件头偏移
+    zip.extend_from_slice(&[0; 16]);
+    zip.extend_from_slice(name);
+    let directory_size = zip.len() - directory_offset;
+
+    zip.extend_from_slice(b"PK\x05\x06");
+    zip.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    zip.extend_from_slice(&(directory_size as u32).to_le_bytes());
+    zip.extend_from_slice(&(directory_offset as u32).to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+
+    let mut archive = ZipArchive::new(Cursor::new(zip)).map_err(|e| (Vec::new(), e.to_string()))?;
+    let mut file = archive
+        .by_index(0)
+        .map_err(|e| (Vec::new(), e.to_string()))?;
+    let mut buf = Vec::new();
+    match file.read_to_end(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(e) => Err((buf, e.to_string())),
+    }
+}
+
+/// 中央目录损坏时，从头扫描本地文件头找回条目
+fn scan_local_entries(bytes: &[u8]) -> Vec<RawEntry> {
+    let mut entries = Vec::new();
+    let mut position = 0;
+    while let Some(start) = find(bytes, LOCAL_HEADER_SIGNATURE, position) {
+        let header = (
+            u16_at(bytes, start + 6),
+            u16_at(bytes, start + 8),
+            u32_at(bytes, start + 14),
+            u32_at(bytes, start + 18),
+            u32_at(bytes, start + 22),
+            u16_at(bytes, start + 26),
+            u16_at(bytes, start + 28),
+        );
+        let (
+            Some(flags),
+            Some(method),
+            Some(mut crc),
+            Some(mut compressed_size),
+            Some(mut uncompressed_size),
+            Some(name_len),
+            Some(extra_len),
+        ) = header
+        else {
+            break;
+        };
+        let name_start = start + 30;
+        let data_start = name_start + name_len as usize + extra_len as usize;
+        let Some(name) = bytes.get(name_start..name_start + name_len as usize) else {
+            break;
+        };
+
+        let mut truncated = false;
+        if flags & (1 << 3) != 0 {
+            // 大小和 CRC 写在数据之后的数据描述符中，找到大小与位置吻合的那个
+            let mut search = data_start;
+            let descriptor = loop {
+                let Some(found) = find(bytes, DATA_DESCRIPTOR_SIGNATURE, search) else {
+                    break None;
+                };
+                if u32_at(bytes, found + 8) == Some((found - data_start) as u32) {
+                    break Some(found);
+                }
+                search = found + 1;
+            };
+            match descriptor.and_then(|found| {
+                Some((
+                    u32_at(bytes, found + 4)?,
+                    u32_at(bytes, found + 8)?,
+                    u32_at(bytes, found + 12)?,
+                ))
+            }) {
+                Some(sizes) => (crc, compressed_size, uncompressed_size) = sizes,
+                None => {
+                    truncated = true;
+                    compressed_size = (find(bytes, LOCAL_HEADER_SIGNATURE, data_start)
+                        .unwrap_or(bytes.len())
+                        .saturating_sub(data_start)) as u32;
+                }
+            }
+        }
+        let data_end = (data_start + compressed_size as usize).min(bytes.len());
+        truncated |= data_end < data_start + compressed_size as usize;
+        let data = bytes.get(data_start..data_end).unwrap_or_default();
+
+        let name_text = String::from_utf8_lossy(name).into_owned();
+        if !name_text.ends_with('/') {
+            let (data, error) =
+                match read_single_entry(name, flags, method, crc, uncompressed_size, data) {
+                    Ok(data) => (data, None),
+                    Err((data, e)) => (data, Some(e)),
+                };
+            let entry = RawEntry {
+                name: name_text,
+                data,
+                error: if truncated {
+                    Some("数据不完整".to_string())
+                } else {
+                    error
+                },
+            };
+            entries.push(entry);
+        }
+        position = data_end.max(start + 4);
+    }
+    entries
+}
+
+/// 读取所有条目，返回中央目录的错误（完好时为 None）和条目列表
+fn read_entries(bytes: &[u8]) -> (Option<String>, Vec<RawEntry>) {
+    let mut archive = match ZipArchive::new(Cursor::new(bytes)) {
+        Ok(archive) => archive,
+        Err(e) => return (Some(e.to_string()), scan_local_entries(bytes)),
+    };
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let name = match archive.by_index_raw(i) {
+            Ok(file) => file.name().to_string(),
+            Err(e) => {
+                entries.push(RawEntry {
+                    name: format!("#{}", i),
+                    data: Vec::new(),
+                    error: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+        if name.ends_with('/') {
+            continue;
+        }
+        let mut data = Vec::new();
+        let error = match archive.by_index(i) {
+            Ok(mut file) => file.read_to_end(&mut data).err().map(|e| e.to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+        entries.push(RawEntry { name, data, error });
+    }
+    (None, entries)
+}
+
+/// 按条目类型解码，检查内容是否有效
+fn check_content(name: &str, data: &[u8]) -> Result<(), String> {
+    match name {
+        STAGE_ENTRY => decode_msgpack::<Vec<Value>>(name, data).map(drop),
+        TAGS_ENTRY => decode_msgpack::<Vec<String>>(name, data).map(drop),
+        REFERENCE_ENTRY => decode_msgpack::<PrgReferences>(name, data).map(drop),
+        METADATA_ENTRY => decode_msgpack::<PrgMetadata>(name, data).map(drop),
+        README_ENTRY => std::str::from_utf8(data)
+            .map(drop)
+            .map_err(|e| format!("{} 不是有效的 UTF-8: {}", name, e)),
+        THUMBNAIL_ENTRY => image::load_from_memory(data)
+            .map(drop)
+            .map_err(|e| format!("无法解析缩略图: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// 条目的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrgEntryState {
+    Ok,
+    /// CRC 校验失败、数据不完整或内容无法解码
+    Corrupt,
+    /// 必需的条目不存在
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgEntryReport {
+    pub name: String,
+    pub state: PrgEntryState,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// verify_prg 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgVerifyReport {
+    /// 所有条目都完好
+    pub ok: bool,
+    /// 中央目录的错误，为空时表示中央目录完好
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory_error: Option<String>,
+    pub entries: Vec<PrgEntryReport>,
+}
+
+fn entry_error(entry: &RawEntry) -> Option<String> {
+    match &entry.error {
+        Some(e) => Some(format!("无法读取 {}: {}", entry.name, e)),
+        None => check_content(&entry.name, &entry.data).err(),
+    }
+}
+
+fn build_report(directory_error: Option<String>, entries: &[RawEntry]) -> PrgVerifyReport {
+    let mut reports: Vec<PrgEntryReport> = entries
+        .iter()
+        .map(|entry| {
+            let error = entry_error(entry);
+            PrgEntryReport {
+                name: entry.name.clone(),
+                state: if error.is_some() {
+                    PrgEntryState::Corrupt
+                } else {
+                    PrgEntryState::Ok
+                },
+                size: entry.data.len() as u64,
+                error,
+            }
+        })
+        .collect();
+    if !entries.iter().any(|entry| entry.name == STAGE_ENTRY) {
+        reports.push(PrgEntryReport {
+            name: STAGE_ENTRY.to_string(),
+            state: PrgEntryState::Missing,
+            size: 0,
+            error: Some(format!("缺少 {}", STAGE_ENTRY)),
+        });
+    }
+    PrgVerifyReport {
+        ok: directory_error.is_none()
+            && reports
+                .iter()
+                .all(|report| report.state == PrgEntryState::Ok),
+        directory_error,
+        entries: reports,
+    }
+}
+
+/// 检查 .prg 文件的完整性
+pub fn verify(path: &Path) -> Result<PrgVerifyReport, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("无法读取文件: {}", e))?;
+    let (directory_error, entries) = read_entries(&bytes);
+    Ok(build_report(directory_error, &entries))
+}
+
+/// 从损坏的舞台对象数组中逐个解码，返回损坏位置之前的对象
+fn salvage_stage(bytes: &[u8]) -> Vec<Value> {
+    let (len, header) = match bytes.first() {
+        Some(&marker @ 0x90..=0x9f) => ((marker & 0x0f) as usize, 1),
+        Some(0xdc) => match bytes.get(1..3) {
+            Some(len) => (u16::from_be_bytes([len[0], len[1]]) as usize, 3),
+            None => return Vec::new(),
+        },
+        Some(0xdd) => match bytes.get(1..5) {
+            Some(len) => (u32::from_be_bytes(len.try_into().unwrap()) as usize, 5),
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    let mut deserializer = rmp_serde::Deserializer::new(&bytes[header..]);
+    let mut stage = Vec::new();
+    while stage.len() < len {
+        match serde::Deserialize::deserialize(&mut deserializer) {
+            Ok(value) => stage.push(value),
+            Err(_) => break,
+        }
+    }
+    stage
+}
+
+/// repair_prg 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrgRepairReport {
+    /// 修复后的文件
+    pub output: String,
+    /// 原文件的检查结果
+    pub report: PrgVerifyReport,
+    /// 完整保留下来的条目
+    pub recovered: Vec<String>,
+    /// 无法恢复、被丢弃的条目
+    pub lost: Vec<String>,
+    /// 修复后的舞台对象数量
+    pub stage_objects: usize,
+    /// 舞台数据已损坏，只恢复了损坏位置之前的对象
+    pub stage_partial: bool,
+}
+
+/// 默认的输出位置：原文件旁边的 {name}.repaired.prg，重名时加上序号
+fn default_output(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = std::ffi::OsString::from(format!("{}.repaired.prg", stem));
+    let mut n = 1;
+    loop {
+        let candidate = path.with_file_name(super::fs::numbered_name(&name, n));
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// 把 .prg 文件中还能用的部分写入 output
+pub fn repair(path: &Path, output: Option<&Path>) -> Result<PrgRepairReport, String> {
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| default_output(path));
+    if output == path {
+        return Err("不能覆盖原文件，请选择新的位置".to_string());
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("无法读取文件: {}", e))?;
+    let (directory_error, entries) = read_entries(&bytes);
+    let report = build_report(directory_error, &entries);
+
+    let mut project = PrgProject::default();
+    let mut recovered = Vec::new();
+    let mut lost = Vec::new();
+    let mut stage_partial = false;
+    let mut seen = HashSet::new();
+    for (entry, entry_report) in entries.iter().zip(&report.entries) {
+        // 扫描本地文件头时同名条目可能出现多次，只取第一个完好的
+        if entry_report.state != PrgEntryState::Ok {
+            if entry.name == STAGE_ENTRY && !seen.contains(STAGE_ENTRY) {
+                let stage = salvage_stage(&entry.data);
+                if !stage.is_empty() {
+                    project.stage = stage;
+                    stage_partial = true;
+                }
+            }
+            lost.push(entry.name.clone());
+            continue;
+        }
+        if !seen.insert(entry.name.clone()) {
+            continue;
+        }
+        let data = &entry.data;
+        let kept = match entry.name.as_str() {
+            STAGE_ENTRY => {
+                project.stage = decode_msgpack(STAGE_ENTRY, data)?;
+                stage_partial = false;
+                true
+            }
+            TAGS_ENTRY => {
+                project.tags = decode_msgpack(TAGS_ENTRY, data)?;
+                true
+            }
+            REFERENCE_ENTRY => {
+                project.references = decode_msgpack(REFERENCE_ENTRY, data)?;
+                true
+            }
+            METADATA_ENTRY => {
+                project.metadata = decode_msgpack(METADATA_ENTRY, data)?;
+                true
+            }
+            README_ENTRY => {
+                project.readme = Some(String::from_utf8_lossy(data).into_owned());
+                true
+            }
+            THUMBNAIL_ENTRY => {
+                project.thumbnail = Some(data.clone());
+                true
+            }
+            name => match parse_attachment_name(name) {
+                Some((id, ext)) => {
+                    project.attachments.push(PrgAttachment {
+                        id,
+                        ext,
+                        data: data.clone(),
+                    });
+                    true
+                }
+                None => false,
+            },
+        };
+        if kept {
+            recovered.push(entry.name.clone());
+        } else {
+            lost.push(entry.name.clone());
+        }
+    }
+    // 部分成功的 stage.msgpack 之后如果还有完好的副本，损坏的那份不算丢失
+    lost.retain(|name| !recovered.contains(name));
+    project.attachments.sort_by(|a, b| a.id.cmp(&b.id));
+
+    // 舞台对象不完整时，去掉指向已丢失对象的标签
+    if !seen.contains(STAGE_ENTRY) {
+        let uuids: HashSet<&str> = project
+            .stage
+            .iter()
+            .filter_map(|object| object.get("uuid").and_then(Value::as_str))
+            .collect();
+        project.tags.retain(|tag| uuids.contains(tag.as_str()));
+    }
+
+    project.save(&output)?;
+    Ok(PrgRepairReport {
+        output: output.to_string_lossy().into_owned(),
+        report,
+        recovered,
+        lost,
+        stage_objects: project.stage.len(),
+        stage_partial,
+    })
+}
+
+/// 检查 .prg 文件的完整性：ZIP 的 CRC 以及每个 msgpack 条目能否解码
+#[tauri::command]
+pub async fn verify_prg(
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<PrgVerifyReport, String> {
+    scope.check(Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || verify(Path::new(&path)))
+        .await
+        .map_err(|e| format!("检查任务失败: {}", e))?
+}
+
+/// 修复 .prg 文件，把还能用的部分写入新文件
+/// output 为空时写到原文件旁边的 {name}.repaired.prg
+#[tauri::command]
+pub async fn repair_prg(
+    scope: State<'_, FsScope>,
+    path: String,
+    output: Option<String>,
+) -> Result<PrgRepairReport, String> {
+    scope.check(Path::new(&path))?;
+    match &output {
+        Some(output) => scope.check(Path::new(output))?,
+        None => scope.check(&default_output(Path::new(&path)))?,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        repair(Path::new(&path), output.as_deref().map(Path::new))
+    })
+    .await
+    .map_err(|e| format!("修复任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::encode_msgpack;
+    use serde_json::json;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    fn png() -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image::RgbaImage::new(4, 4)
+            .write_to(&mut out, image::ImageFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    fn project() -> PrgProject {
+        PrgProject {
+            stage: vec![
+                json!({ "_": "TextNode", "uuid": "a", "text": "一" }),
+                json!({ "_": "TextNode", "uuid": "b", "text": "二" }),
+                json!({ "_": "TextNode", "uuid": "c", "text": "三" }),
+            ],
+            tags: vec!["a".to_string(), "c".to_string()],
+            attachments: vec![PrgAttachment {
+                id: "0b6e2f7c".to_string(),
+                ext: "bin".to_string(),
+                data: b"attachment".to_vec(),
+            }],
+            thumbnail: Some(png()),
+            ..Default::default()
+        }
+    }
+
+    fn state(report: &PrgVerifyReport, name: &str) -> PrgEntryState {
+        report
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.state)
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_stage_when_thumbnail_is_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.prg");
+        let mut bytes = project().to_bytes().unwrap();
+        // 缩略图是最后一个条目，改掉 PNG 签名之后的一个字节
+        let thumbnail = find(&bytes, b"\x89PNG", 0).unwrap();
+        bytes[thumbnail + 20] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let report = verify(&path).unwrap();
+        assert!(!report.ok);
+        assert!(report.directory_error.is_none());
+        assert_eq!(state(&report, THUMBNAIL_ENTRY), PrgEntryState::Corrupt);
+        assert_eq!(state(&report, STAGE_ENTRY), PrgEntryState::Ok);
+
+        let repaired = repair(&path, None).unwrap();
+        assert_eq!(
+            Path::new(&repaired.output),
+            dir.path().join("a.repaired.prg")
+        );
+        assert_eq!(repaired.lost, vec![THUMBNAIL_ENTRY.to_string()]);
+        let restored = PrgProject::open(Path::new(&repaired.output)).unwrap();
+        assert_eq!(
+            restored,
+            PrgProject {
+                thumbnail: None,
+                ..project()
+            }
+        );
+        assert!(verify(Path::new(&repaired.output)).unwrap().ok);
+        assert!(repair(&path, Some(&path)).is_err());
+    }
+
+    #[test]
+    fn recovers_entries_from_truncated_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("b.prg");
+        let source = project();
+        // 流式写入会把大小放在数据描述符中，和前端保存的文件一样
+        let mut zip = ZipWriter::new_stream(Vec::new());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let stage = encode_msgpack(STAGE_ENTRY, &source.stage).unwrap();
+        // 舞台数据在第三个对象中间被截断
+        zip.start_file(STAGE_ENTRY, options).unwrap();
+        zip.write_all(&stage[..stage.len() - 4]).unwrap();
+        zip.start_file(TAGS_ENTRY, options).unwrap();
+        zip.write_all(&encode_msgpack(TAGS_ENTRY, &source.tags).unwrap())
+            .unwrap();
+        let attachment = &source.attachments[0];
+        zip.start_file(attachment.entry_name(), options).unwrap();
+        zip.write_all(&attachment.data).unwrap();
+        zip.start_file(THUMBNAIL_ENTRY, options).unwrap();
+        zip.write_all(source.thumbnail.as_ref().unwrap()).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        // 截掉中央目录和缩略图的一部分
+        let thumbnail = find(&bytes, THUMBNAIL_ENTRY.as_bytes(), 0).unwrap();
+        std::fs::write(&path, &bytes[..thumbnail + 40]).unwrap();
+
+        let report = verify(&path).unwrap();
+        assert!(report.directory_error.is_some());
+        assert_eq!(state(&report, STAGE_ENTRY), PrgEntryState::Corrupt);
+        assert_eq!(state(&report, TAGS_ENTRY), PrgEntryState::Ok);
+        assert_eq!(state(&report, &attachment.entry_name()), PrgEntryState::Ok);
+        assert_eq!(state(&report, THUMBNAIL_ENTRY), PrgEntryState::Corrupt);
+
+        let output = dir.path().join("out.prg");
+        let repaired = repair(&path, Some(&output)).unwrap();
+        assert!(repaired.stage_partial);
+        assert_eq!(repaired.stage_objects, 2);
+        let restored = PrgProject::open(&output).unwrap();
+        assert_eq!(restored.stage, source.stage[..2]);
+        assert_eq!(restored.tags, vec!["a".to_string()]);
+        assert_eq!(restored.attachments, source.attachments);
+        assert_eq!(restored.thumbnail, None);
+    }
+}
@@ -0,0 +1,425 @@
+//! cmd::fs 命令可以访问的路径范围
+//!
+//! 前端传来的路径都是不可信的（webview 中注入的脚本、扩展都能调用命令），
+//! 所以 cmd::fs 中的命令只允许访问以下位置：
+//! - 应用数据目录
+//! - 通过系统打开的项目文件
+//! - 用户在系统对话框中打开或保存的文件、拖入窗口的文件，其中的项目文件会被保存下来
+//! - 用户在系统对话框中选择或确认过的文件夹
+//!
+//! 路径会先规范化（解析符号链接），再检查是否位于某个允许的位置之下，
+//! 所以 `..` 和指向范围外的符号链接都无法越界。
+//!
+//! 允许的位置、shell 策略和审计日志保存在受保护的目录中，
+//! 即使它位于应用数据目录之下，cmd::fs 和 fs 插件也都不能访问，
+//! 否则前端可以改写这些文件，在下次启动时获得更多权限。
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use super::fs::FsError;
+
+/// 受保护的目录，位于应用配置目录中
+const PROTECTED_DIR: &str = "protected";
+
+/// 保存用户添加的文件夹和打开过的项目文件，位于受保护的目录中
+const SCOPE_FILE: &str = "fs-scopes.json";
+
+/// 最多保存多少个打开过的项目文件，超出时丢弃最早的
+const MAX_SAVED_PROJECTS: usize = 100;
+
+/// 允许访问的位置的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScopeKind {
+    /// 应用数据目录，不能移除
+    AppData,
+    /// 通过系统或对话框打开、拖入窗口的文件，其中的 .prg 文件会被保存下来
+    Project,
+    /// 用户选择的文件夹，会被保存下来
+    Folder,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeEntry {
+    pub path: PathBuf,
+    pub kind: ScopeKind,
+}
+
+/// 保存在 SCOPE_FILE 中的内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedScopes {
+    #[serde(default)]
+    folders: Vec<PathBuf>,
+    #[serde(default)]
+    projects: Vec<PathBuf>,
+}
+
+/// 允许访问的位置
+#[derive(Default)]
+pub struct FsScope {
+    entries: RwLock<Vec<ScopeEntry>>,
+    /// 保存允许的位置的文件，为空时不保存
+    file: Option<PathBuf>,
+    /// 受保护的目录，规范化后的路径
+    protected: Option<PathBuf>,
+}
+
+/// 受保护的目录，保存 cmd::scope 和 cmd::shell_policy 的配置
+pub fn protected_dir<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(PROTECTED_DIR))
+}
+
+/// 规范化路径：解析符号链接，不存在的部分原样拼接在最近一个存在的上级后面
+/// 拒绝相对路径和包含 `..` 的路径
+pub fn normalize(path: &Path) -> Result<PathBuf, FsError> {
+    if !path.is_absolute() {
+        return Err(FsError::OutsideScope(format!(
+            "{}（必须是绝对路径）",
+            path.display()
+        )));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(FsError::OutsideScope(format!(
+            "{}（不能包含 ..）",
+            path.display()
+        )));
+    }
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(mut resolved) => {
+                resolved.extend(rest.iter().rev());
+                return Ok(resolved);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        rest.push(name);
+                        existing = parent;
+                    }
+                    _ => return Err(FsError::from_io(e, path)),
+                }
+            }
+            Err(e) => return Err(FsError::from_io(e, path)),
+        }
+    }
+}
+
+impl FsScope {
+    /// 从受保护的目录中读取保存的位置，并允许访问应用数据目录
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> Self {
+        FsScope::open(protected_dir(app), app.path().app_data_dir().ok())
+    }
+
+    fn open(protected: Option<PathBuf>, app_data: Option<PathBuf>) -> Self {
+        // 先创建出来，规范化后的路径才不会因为目录是否存在而变化
+        let protected = protected.and_then(|dir| {
+            std::fs::create_dir_all(&dir).ok()?;
+            normalize(&dir).ok()
+        });
+        let scope = FsScope {
+            entries: RwLock::default(),
+            file: protected.as_ref().map(|dir| dir.join(SCOPE_FILE)),
+            protected,
+        };
+        if let Some(dir) = app_data {
+            scope.insert(&dir, ScopeKind::AppData);
+        }
+        let saved: SavedScopes = scope
+            .file
+            .as_ref()
+            .and_then(|file| std::fs::read(file).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        for path in saved.folders {
+            scope.insert(&path, ScopeKind::Folder);
+        }
+        for path in saved.projects {
+            scope.insert(&path, ScopeKind::Project);
+        }
+        scope
+    }
+
+    /// 受保护的目录，fs 插件的范围中也要禁止访问
+    pub fn protected(&self) -> Option<&Path> {
+        self.protected.as_deref()
+    }
+
+    fn is_protected(&self, path: &Path) -> bool {
+        self.protected
+            .as_ref()
+            .is_some_and(|dir| path.starts_with(dir))
+    }
+
+    fn insert(&self, path: &Path, kind: ScopeKind) -> Option<PathBuf> {
+        let path = normalize(path).ok()?;
+        if self.is_protected(&path) {
+            return None;
+        }
+        let mut entries = self.entries.write().unwrap();
+        if !entries.iter().any(|entry| entry.path == path) {
+            entries.push(ScopeEntry {
+                path: path.clone(),
+                kind,
+            });
+        }
+        Some(path)
+    }
+
+    fn save(&self) -> Result<(), FsError> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let entries = self.entries();
+        let paths = |kind| {
+            entries
+                .iter()
+                .filter(move |entry| entry.kind == kind)
+                .map(|entry| entry.path.clone())
+        };
+        let projects: Vec<PathBuf> = paths(ScopeKind::Project)
+            .filter(|path| path.extension().is_some_and(|ext| ext == "prg"))
+            .collect();
+        let saved = SavedScopes {
+            folders: paths(ScopeKind::Folder).collect(),
+            projects: projects[projects.len().saturating_sub(MAX_SAVED_PROJECTS)..].to_vec(),
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| FsError::from_io(e, dir))?;
+        }
+        let content =
+            serde_json::to_vec_pretty(&saved).map_err(|e| FsError::Other(e.to_string()))?;
+        super::fs::write_file_atomic(file, &content, false)
+    }
+
+    /// 允许访问一个位置，Folder 和项目文件会被保存下来
+    pub fn allow(&self, path: &Path, kind: ScopeKind) -> Result<PathBuf, FsError> {
+        let path = self
+            .insert(path, kind)
+            .ok_or_else(|| FsError::OutsideScope(path.display().to_string()))?;
+        if kind != ScopeKind::AppData {
+            self.save()?;
+        }
+        Ok(path)
+    }
+
+    /// 移除一个位置，应用数据目录不能被移除
+    pub fn remove(&self, path: &Path) -> Result<bool, FsError> {
+        let path = normalize(path)?;
+        let removed = {
+            let mut entries = self.entries.write().unwrap();
+            let before = entries.len();
+            entries.retain(|entry| entry.kind == ScopeKind::AppData || entry.path != path);
+            entries.len() != before
+        };
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// 对话框插件会把用户选中的路径加入 fs 插件的范围，这里同样允许访问
+    pub fn allow_picked(&self, event: &tauri::scope::fs::Event) {
+        if let tauri::scope::fs::Event::PathAllowed(path) = event {
+            let _ = self.allow(path, ScopeKind::Project);
+        }
+    }
+
+    /// 当前允许访问的位置的副本，不会被保存
+    /// 后台任务遍历文件夹时用它检查遇到的符号链接
+    pub fn snapshot(&self) -> FsScope {
+        FsScope {
+            entries: RwLock::new(self.entries()),
+            file: None,
+            protected: self.protected.clone(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<ScopeEntry> {
+        self.entries.read().unwrap().clone()
+    }
+
+    /// 检查路径是否在允许的范围内，返回规范化后的路径
+    /// 受保护的目录即使位于允许的位置之下也不能访问
+    pub fn check(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let resolved = normalize(path)?;
+        let allowed = !self.is_protected(&resolved)
+            && self
+                .entries
+                .read()
+                .unwrap()
+                .iter()
+                .any(|entry| resolved.starts_with(&entry.path));
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(FsError::OutsideScope(path.display().to_string()))
+        }
+    }
+}
+
+/// 跟随对话框插件允许的路径，用户在系统对话框中打开或保存的文件都能被 cmd::fs 访问
+/// 同时在 fs 插件的范围中禁止访问受保护的目录
+pub fn track_picked_paths<R: Runtime>(app: &AppHandle<R>) {
+    use tauri_plugin_fs::FsExt;
+
+    let Some(fs_scope) = app.try_fs_scope() else {
+        return;
+    };
+    if let Some(dir) = app.state::<FsScope>().protected() {
+        let _ = fs_scope.forbid_directory(dir, true);
+    }
+    let app = app.clone();
+    fs_scope.listen(move |event| app.state::<FsScope>().allow_picked(event));
+}
+
+/// 列出所有允许访问的位置
+#[tauri::command]
+pub fn get_fs_scopes(scope: State<'_, FsScope>) -> Vec<ScopeEntry> {
+    scope.entries()
+}
+
+/// 添加允许访问的文件夹
+/// path 为空时弹出系统的文件夹选择对话框，否则弹出确认对话框，
+/// 只有用户亲自选择或确认过的文件夹才会被添加
+/// 返回添加的文件夹，用户取消时返回 None
+#[cfg(desktop)]
+#[tauri::command]
+pub async fn add_fs_scope<R: Runtime>(
+    app: AppHandle<R>,
+    path: Option<String>,
+) -> Result<Option<String>, FsError> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let dialog = app.clone();
+    let picked = tauri::async_runtime::spawn_blocking(move || match path {
+        Some(path) => dialog
+            .dialog()
+            .message(format!("是否允许 Project Graph 读写以下文件夹？\n{}", path))
+            .title("访问文件夹")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show()
+            .then(|| PathBuf::from(path)),
+        None => dialog
+            .dialog()
+            .file()
+            .set_title("选择允许访问的文件夹")
+            .blocking_pick_folder()
+            .and_then(|folder| folder.into_path().ok()),
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("无法打开对话框: {}", e)))?;
+
+    match picked {
+        Some(path) => {
+            let added = app.state::<FsScope>().allow(&path, ScopeKind::Folder)?;
+            Ok(Some(added.to_string_lossy().to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// 移除允许访问的位置
+/// 返回是否存在该位置
+#[tauri::command]
+pub fn remove_fs_scope(scope: State<'_, FsScope>, path: String) -> Result<bool, FsError> {
+    scope.remove(Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_paths_outside_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let allowed = dir.path().join("allowed");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), b"secret").unwrap();
+
+        let scope = FsScope::default();
+        scope.allow(&allowed, ScopeKind::Folder).unwrap();
+
+        assert!(scope.check(&allowed.join("a.prg")).is_ok());
+        assert!(scope.check(&allowed.join("new/sub/b.md")).is_ok());
+        assert!(matches!(
+            scope.check(&outside.join("secret.txt")),
+            Err(FsError::OutsideScope(_))
+        ));
+        assert!(matches!(
+            scope.check(&allowed.join("../outside/secret.txt")),
+            Err(FsError::OutsideScope(_))
+        ));
+        assert!(matches!(
+            scope.check(Path::new("relative.txt")),
+            Err(FsError::OutsideScope(_))
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, allowed.join("link")).unwrap();
+            assert!(matches!(
+                scope.check(&allowed.join("link/secret.txt")),
+                Err(FsError::OutsideScope(_))
+            ));
+        }
+
+        assert!(scope.remove(&allowed).unwrap());
+        assert!(scope.check(&allowed.join("a.prg")).is_err());
+    }
+
+    #[test]
+    fn remembers_picked_projects_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_data = dir.path().join("data");
+        let protected = app_data.join("protected");
+        let picked = dir.path().join("picked.prg");
+        let notes = dir.path().join("notes.md");
+        let folder = dir.path().join("folder");
+        std::fs::create_dir_all(&folder).unwrap();
+
+        let scope = FsScope::open(Some(protected.clone()), Some(app_data.clone()));
+        assert!(scope.check(&picked).is_err());
+        scope.allow_picked(&tauri::scope::fs::Event::PathForbidden(picked.clone()));
+        assert!(scope.check(&picked).is_err());
+        scope.allow_picked(&tauri::scope::fs::Event::PathAllowed(picked.clone()));
+        scope.allow_picked(&tauri::scope::fs::Event::PathAllowed(notes.clone()));
+        scope.allow(&folder, ScopeKind::Folder).unwrap();
+        assert!(scope.check(&picked).is_ok());
+        assert!(scope.check(&notes).is_ok());
+
+        // 下次启动时只恢复项目文件和文件夹
+        let reloaded = FsScope::open(Some(protected.clone()), Some(app_data.clone()));
+        assert!(reloaded.check(&picked).is_ok());
+        assert!(reloaded.check(&folder.join("a.md")).is_ok());
+        assert!(reloaded.check(&notes).is_err());
+    }
+
+    #[test]
+    fn denies_the_protected_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_data = dir.path().join("data");
+        let protected = app_data.join("protected");
+        let scope = FsScope::open(Some(protected.clone()), Some(app_data.clone()));
+
+        assert!(scope.check(&app_data.join("settings.json")).is_ok());
+        assert!(matches!(
+            scope.check(&protected.join(SCOPE_FILE)),
+            Err(FsError::OutsideScope(_))
+        ));
+        assert!(scope.check(&protected).is_err());
+        assert!(scope.allow(&protected, ScopeKind::Folder).is_err());
+        assert!(scope.snapshot().check(&protected.join("x")).is_err());
+    }
+}
@@ -0,0 +1,684 @@
+//! 跨文件的全文搜索索引
+//!
+//! 索引选定文件夹下所有 .prg 和 Markdown 文件中的文字：
+//! - .prg 以舞台对象为单位（TextNode、Section、连线标签等），搜索结果可以定位到 UUID
+//! - Markdown 以段落为单位，搜索结果可以定位到行号
+//!
+//! 分词时中日韩文字同时按单字和相邻两字切分，其他文字按单词切分并转为小写，
+//! 排序使用 BM25。索引以文件修改时间和大小判断是否需要重建，保存在应用缓存目录中。
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use super::fs::{scan_folder_parallel, write_file_atomic};
+use super::prg::{decode_msgpack, open_archive, read_entry, STAGE_ENTRY};
+use super::scope::FsScope;
+use super::stage::text_items;
+use super::task::CancellationToken;
+
+/// 索引文件格式变化时递增，旧的索引会被丢弃
+const INDEX_VERSION: u32 = 1;
+const INDEX_FILE_NAME: &str = "search-index.msgpack";
+const INDEXED_EXTS: [&str; 3] = [".prg", ".md", ".markdown"];
+
+/// BM25 参数
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// 摘要中命中位置前后保留的字符数
+const SNIPPET_CONTEXT: usize = 30;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // 平假名、片假名
+        | 0x3400..=0x4DBF    // CJK 扩展 A
+        | 0x4E00..=0x9FFF    // CJK 统一表意文字
+        | 0xAC00..=0xD7AF    // 韩文音节
+        | 0xF900..=0xFAFF    // CJK 兼容表意文字
+        | 0x20000..=0x2FA1F) // CJK 扩展 B 及以后
+}
+
+/// 分词
+/// for_query 为 true 时，两个字以上的中日韩文字只切分为两字词，提高查询精度
+pub fn tokenize(text: &str, for_query: bool) -> Vec<String> {
+    fn flush_cjk(run: &mut Vec<char>, tokens: &mut Vec<String>, for_query: bool) {
+        if run.len() == 1 || !for_query {
+            tokens.extend(run.iter().map(|c| c.to_string()));
+        }
+        tokens.extend(run.windows(2).map(|pair| pair.iter().collect()));
+        run.clear();
+    }
+
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run = Vec::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            cjk_run.push(c);
+        } else {
+            if !cjk_run.is_empty() {
+                flush_cjk(&mut cjk_run, &mut tokens, for_query);
+            }
+            if c.is_alphanumeric() {
+                word.extend(c.to_lowercase());
+            } else if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    if !cjk_run.is_empty() {
+        flush_cjk(&mut cjk_run, &mut tokens, for_query);
+    }
+    tokens
+}
+
+/// 被索引的最小单位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedItem {
+    /// .prg 中的舞台对象 UUID
+    uuid: Option<String>,
+    /// Markdown 中段落起始行号，从 1 开始
+    line: Option<usize>,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    modified: u64,
+    size: u64,
+    items: Vec<IndexedItem>,
+    /// 词 -> [(item 下标, 词频)]，加载时重新计算，不写入磁盘
+    #[serde(skip)]
+    terms: HashMap<String, Vec<(usize, u32)>>,
+    #[serde(skip)]
+    item_lengths: Vec<u32>,
+}
+
+impl IndexedFile {
+    fn new(modified: u64, size: u64, items: Vec<IndexedItem>) -> Self {
+        let mut file = IndexedFile {
+            modified,
+            size,
+            items,
+            terms: HashMap::new(),
+            item_lengths: Vec::new(),
+        };
+        file.build_terms();
+        file
+    }
+
+    fn build_terms(&mut self) {
+        self.terms.clear();
+        self.item_lengths.clear();
+        for (index, item) in self.items.iter().enumerate() {
+            let tokens = tokenize(&item.text, false);
+            self.item_lengths.push(tokens.len() as u32);
+            let mut counts: HashMap<String, u32> = HashMap::new();
+            for token in tokens {
+                *counts.entry(token).or_default() += 1;
+            }
+            for (token, count) in counts {
+                self.terms.entry(token).or_default().push((index, count));
+            }
+        }
+    }
+}
+
+/// 搜索索引
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    roots: Vec<String>,
+    files: BTreeMap<String, IndexedFile>,
+    /// 词 -> 包含它的 item 数量，用于计算 IDF
+    #[serde(skip)]
+    doc_freq: HashMap<String, u32>,
+    #[serde(skip)]
+    item_count: u64,
+    #[serde(skip)]
+    total_length: u64,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        SearchIndex {
+            version: INDEX_VERSION,
+            roots: Vec::new(),
+            files: BTreeMap::new(),
+            doc_freq: HashMap::new(),
+            item_count: 0,
+            total_length: 0,
+        }
+    }
+}
+
+/// update 的统计
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndexStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub files: usize,
+    /// 无法解析、被跳过的文件
+    pub failed: Vec<String>,
+}
+
+/// 一条搜索结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub score: f64,
+    pub snippet: String,
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((modified, metadata.len()))
+}
+
+fn extract_prg(path: &Path) -> Result<Vec<IndexedItem>, String> {
+    let mut archive = open_archive(path)?;
+    let Some(bytes) = read_entry(&mut archive, STAGE_ENTRY)? else {
+        return Ok(Vec::new());
+    };
+    let stage: Vec<serde_json::Value> = decode_msgpack(STAGE_ENTRY, &bytes)?;
+    Ok(text_items(&stage)
+        .into_iter()
+        .map(|(uuid, text)| IndexedItem {
+            uuid: Some(uuid),
+            line: None,
+            text,
+        })
+        .collect())
+}
+
+/// Markdown 按空行分段
+fn extract_markdown(path: &Path) -> Result<Vec<IndexedItem>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut items = Vec::new();
+    let mut paragraph = Vec::new();
+    let mut start = 0;
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                items.push(IndexedItem {
+                    uuid: None,
+                    line: Some(start + 1),
+                    text: paragraph.join("\n"),
+                });
+                paragraph.clear();
+            }
+        } else {
+            if paragraph.is_empty() {
+                start = index;
+            }
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        items.push(IndexedItem {
+            uuid: None,
+            line: Some(start + 1),
+            text: paragraph.join("\n"),
+        });
+    }
+    Ok(items)
+}
+
+fn extract(path: &Path) -> Result<Vec<IndexedItem>, String> {
+    let is_prg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("prg"));
+    if is_prg {
+        extract_prg(path)
+    } else {
+        extract_markdown(path)
+    }
+}
+
+/// 截取命中位置附近的文字
+fn snippet(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    // to_lowercase 可能改变字节长度，按字符位置换算
+    let hit = terms
+        .iter()
+        .filter_map(|term| lower.find(term.as_str()))
+        .min()
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0);
+    let chars: Vec<char> = text.chars().collect();
+    let start = hit.saturating_sub(SNIPPET_CONTEXT);
+    let end = (hit + SNIPPET_CONTEXT * 2).min(chars.len());
+    let mut result: String = chars[start..end]
+        .iter()
+        .map(|c| if c.is_whitespace() { ' ' } else { *c })
+        .collect();
+    if start > 0 {
+        result.insert(0, '…');
+    }
+    if end < chars.len() {
+        result.push('…');
+    }
+    result
+}
+
+impl SearchIndex {
+    fn add_stats(&mut self, file: &IndexedFile) {
+        for (token, postings) in &file.terms {
+            *self.doc_freq.entry(token.clone()).or_default() += postings.len() as u32;
+        }
+        self.item_count += file.items.len() as u64;
+        self.total_length += file.item_lengths.iter().map(|l| *l as u64).sum::<u64>();
+    }
+
+    fn remove_stats(&mut self, file: &IndexedFile) {
+        for (token, postings) in &file.terms {
+            if let Some(freq) = self.doc_freq.get_mut(token) {
+                *freq = freq.saturating_sub(postings.len() as u32);
+                if *freq == 0 {
+                    self.doc_freq.remove(token);
+                }
+            }
+        }
+        self.item_count = self.item_count.saturating_sub(file.items.len() as u64);
+        self.total_length = self
+            .total_length
+            .saturating_sub(file.item_lengths.iter().map(|l| *l as u64).sum::<u64>());
+    }
+
+    fn insert_file(&mut self, path: String, file: IndexedFile) {
+        self.add_stats(&file);
+        if let Some(previous) = self.files.insert(path, file) {
+            self.remove_stats(&previous);
+        }
+    }
+
+    fn remove_file(&mut self, path: &str) -> bool {
+        match self.files.remove(path) {
+            Some(previous) => {
+                self.remove_stats(&previous);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 从磁盘加载，文件不存在或版本不匹配时返回空索引
+    pub fn load(path: &Path) -> Self {
+        let loaded = std::fs::read(path)
+            .ok()
+            .and_then(|bytes| rmp_serde::from_slice::<SearchIndex>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+        let Some(loaded) = loaded else {
+            return SearchIndex::default();
+        };
+        let mut index = SearchIndex {
+            roots: loaded.roots,
+            ..Default::default()
+        };
+        let files: Vec<(String, IndexedFile)> = loaded
+            .files
+            .into_par_iter()
+            .map(|(path, mut file)| {
+                file.build_terms();
+                (path, file)
+            })
+            .collect();
+        for (path, file) in files {
+            index.insert_file(path, file);
+        }
+        index
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("无法创建缓存目录: {}", e))?;
+        }
+        let bytes =
+            rmp_serde::to_vec_named(self).map_err(|e| format!("无法编码搜索索引: {}", e))?;
+        Ok(write_file_atomic(path, &bytes, false)?)
+    }
+
+    /// 设置索引的根目录并增量更新：只重新解析修改时间或大小变化的文件，移除已删除的文件
+    /// scope: 跳过目标不在范围内的符号链接；为 None 时不限制
+    pub fn update(
+        &mut self,
+        roots: Vec<String>,
+        scope: Option<&FsScope>,
+        token: &CancellationToken,
+    ) -> Result<SearchIndexStats, String> {
+        let exts: Vec<String> = INDEXED_EXTS.iter().map(|ext| ext.to_string()).collect();
+        let mut found: Vec<PathBuf> = Vec::new();
+        for root in &roots {
+            scan_folder_parallel(Path::new(root), &exts, scope, token, usize::MAX, |batch| {
+                found.extend(batch)
+            })?;
+        }
+
+        let mut stats = SearchIndexStats::default();
+        let found: HashMap<String, PathBuf> = found
+            .into_iter()
+            .map(|path| (path.to_string_lossy().to_string(), path))
+            .collect();
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !found.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.remove_file(&path);
+            stats.removed += 1;
+        }
+
+        let changed: Vec<(&String, &PathBuf, (u64, u64))> = found
+            .iter()
+            .filter_map(|(key, path)| {
+                let stamp = file_stamp(path)?;
+                let unchanged = self
+                    .files
+                    .get(key)
+                    .is_some_and(|file| (file.modified, file.size) == stamp);
+                (!unchanged).then_some((key, path, stamp))
+            })
+            .collect();
+        let extracted: Vec<(String, Result<IndexedFile, String>)> = changed
+            .into_par_iter()
+            .map(|(key, path, (modified, size))| {
+                if token.is_cancelled() {
+                    return (key.clone(), Err("已取消".to_string()));
+                }
+                let file = extract(path).map(|items| IndexedFile::new(modified, size, items));
+                (key.clone(), file)
+            })
+            .collect();
+        if token.is_cancelled() {
+            return Err("已取消".to_string());
+        }
+        for (path, file) in extracted {
+            match file {
+                Ok(file) => {
+                    if self.files.contains_key(&path) {
+                        stats.updated += 1;
+                    } else {
+                        stats.added += 1;
+                    }
+                    self.insert_file(path, file);
+                }
+                Err(_) => {
+                    // 解析失败的文件从索引中移除，下次修改后再尝试
+                    self.remove_file(&path);
+                    stats.failed.push(path);
+                }
+            }
+        }
+
+        self.roots = roots;
+        stats.files = self.files.len();
+        Ok(stats)
+    }
+
+    /// 查询，所有查询词都出现的条目才算命中，按 BM25 得分从高到低排序
+    pub fn query(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms = tokenize(query, true);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() || self.item_count == 0 {
+            return Vec::new();
+        }
+        let average_length = self.total_length as f64 / self.item_count as f64;
+        let idf: Vec<f64> = terms
+            .iter()
+            .map(|term| {
+                let df = *self.doc_freq.get(term).unwrap_or(&0) as f64;
+                ((self.item_count as f64 - df + 0.5) / (df + 0.5) + 1.0).ln()
+            })
+            .collect();
+
+        let mut hits: Vec<SearchHit> = self
+            .files
+            .par_iter()
+            .flat_map_iter(|(path, file)| {
+                let mut scores: HashMap<usize, (usize, f64)> = HashMap::new();
+                for (term, idf) in terms.iter().zip(&idf) {
+                    let Some(postings) = file.terms.get(term) else {
+                        return Vec::new();
+                    };
+                    for (item, tf) in postings {
+                        let tf = *tf as f64;
+                        let length = file.item_lengths[*item] as f64;
+                        let score = idf * tf * (BM25_K1 + 1.0)
+                            / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length));
+                        let entry = scores.entry(*item).or_default();
+                        entry.0 += 1;
+                        entry.1 += score;
+                    }
+                }
+                scores
+                    .into_iter()
+                    .filter(|(_, (matched, _))| *matched == terms.len())
+                    .map(|(item, (_, score))| {
+                        let item = &file.items[item];
+                        SearchHit {
+                            path: path.clone(),
+                            uuid: item.uuid.clone(),
+                            line: item.line,
+                            score,
+                            snippet: snippet(&item.text, &terms),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// 搜索索引的全局状态，首次使用时从缓存目录加载
+/// 更新在副本上进行，完成后再替换，更新期间仍然可以搜索
+#[derive(Default)]
+pub struct SearchIndexState {
+    index: Mutex<Option<Arc<SearchIndex>>>,
+    /// 同一时间只允许一个更新或清空
+    updating: Mutex<()>,
+}
+
+fn index_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(INDEX_FILE_NAME))
+        .map_err(|e| format!("无法获取缓存目录: {}", e))
+}
+
+/// 当前的索引和它在磁盘上的位置
+fn current_index<R: Runtime>(app: &AppHandle<R>) -> Result<(Arc<SearchIndex>, PathBuf), String> {
+    let path = index_path(app)?;
+    let state = app.state::<SearchIndexState>();
+    let index = state
+        .index
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(SearchIndex::load(&path)))
+        .clone();
+    Ok((index, path))
+}
+
+/// 在锁外用 f 生成新的索引，然后替换当前的索引
+fn replace_index<R: Runtime, T>(
+    app: &AppHandle<R>,
+    f: impl FnOnce(&SearchIndex, &Path) -> Result<(SearchIndex, T), String>,
+) -> Result<T, String> {
+    let state = app.state::<SearchIndexState>();
+    let _updating = state.updating.lock().unwrap();
+    let (current, path) = current_index(app)?;
+    let (index, result) = f(&current, &path)?;
+    *state.index.lock().unwrap() = Some(Arc::new(index));
+    Ok(result)
+}
+
+/// 设置要索引的文件夹并增量更新索引
+/// 每个文件夹都必须在 cmd::scope 允许的范围内
+/// task_id: 传入后可以通过 cancel_task 取消
+#[tauri::command]
+pub async fn update_search_index<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    tasks: State<'_, super::task::TaskRegistry>,
+    roots: Vec<String>,
+    task_id: Option<String>,
+) -> Result<SearchIndexStats, String> {
+    for root in &roots {
+        scope.check(Path::new(root))?;
+    }
+    let scope = scope.snapshot();
+    let task = tasks.register(task_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        replace_index(&app, |current, path| {
+            let mut index = current.clone();
+            let stats = index.update(roots, Some(&scope), task.token())?;
+            index.save(path)?;
+            Ok((index, stats))
+        })
+    })
+    .await
+    .map_err(|e| format!("更新搜索索引失败: {}", e))?
+}
+
+/// 在索引中搜索
+/// limit: 最多返回多少条结果，默认 50
+#[tauri::command]
+pub async fn search_files<R: Runtime>(
+    app: AppHandle<R>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (index, _) = current_index(&app)?;
+        Ok(index.query(&query, limit.unwrap_or(50)))
+    })
+    .await
+    .map_err(|e| format!("搜索失败: {}", e))?
+}
+
+/// 当前索引的文件夹
+#[tauri::command]
+pub async fn get_search_index_roots<R: Runtime>(app: AppHandle<R>) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (index, _) = current_index(&app)?;
+        Ok(index.roots.clone())
+    })
+    .await
+    .map_err(|e| format!("读取搜索索引失败: {}", e))?
+}
+
+/// 清空索引
+#[tauri::command]
+pub async fn clear_search_index<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        replace_index(&app, |_, path| match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("无法删除搜索索引: {}", e))
+            }
+            _ => Ok((SearchIndex::default(), ())),
+        })
+    })
+    .await
+    .map_err(|e| format!("清空搜索索引失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::PrgProject;
+    use serde_json::json;
+
+    #[test]
+    fn tokenizes_cjk_and_latin_text() {
+        assert_eq!(
+            tokenize("Project 图谱工具", false),
+            vec!["project", "图", "谱", "工", "具", "图谱", "谱工", "工具"]
+        );
+        assert_eq!(tokenize("图谱", true), vec!["图谱"]);
+        assert_eq!(tokenize("图", true), vec!["图"]);
+    }
+
+    #[test]
+    fn indexes_prg_and_markdown_incrementally() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let project = PrgProject {
+            stage: vec![
+                json!({ "_": "TextNode", "uuid": "n1", "text": "知识图谱的构建方法" }),
+                json!({ "_": "TextNode", "uuid": "n2", "text": "unrelated" }),
+            ],
+            ..Default::default()
+        };
+        project.save(&root.join("a.prg")).unwrap();
+        std::fs::write(
+            root.join("b.md"),
+            "# Title\n\nSome notes about 图谱 layout\n",
+        )
+        .unwrap();
+
+        let token = CancellationToken::default();
+        let mut index = SearchIndex::default();
+        let stats = index
+            .update(vec![root.to_string_lossy().to_string()], None, &token)
+            .unwrap();
+        assert_eq!(stats.added, 2);
+
+        let hits = index.query("图谱", 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().any(|hit| hit.uuid.as_deref() == Some("n1")));
+        assert!(hits.iter().any(|hit| hit.line == Some(3)));
+
+        let hits = index.query("LAYOUT 图谱", 10);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].snippet.contains("layout"));
+
+        let stats = index
+            .update(vec![root.to_string_lossy().to_string()], None, &token)
+            .unwrap();
+        assert_eq!((stats.added, stats.updated, stats.removed), (0, 0, 0));
+
+        std::fs::remove_file(root.join("b.md")).unwrap();
+        let stats = index
+            .update(vec![root.to_string_lossy().to_string()], None, &token)
+            .unwrap();
+        assert_eq!(stats.removed, 1);
+        assert_eq!(index.query("layout", 10).len(), 0);
+
+        let path = root.join("cache/index.msgpack");
+        index.save(&path).unwrap();
+        assert_eq!(SearchIndex::load(&path).query("构建", 10).len(), 1);
+    }
+}
@@ -1,54 +1,726 @@
-use serde::Serialize;
-use std::process::Command;
+//! 运行外部命令
+//!
+//! - run_command：运行到结束后一次性返回输出，可以指定工作目录、环境变量、超时和输出上限，
+//!   输入输出都可以是二进制（base64）
+//! - spawn_command：在后台运行，stdout / stderr 按行通过 Channel 发送给前端，
+//!   之后可以用 write_process_stdin、close_process_stdin、kill_process 与进程交互
+//!
+//! 运行前都会经过 cmd::shell_policy 的权限检查，并写入审计日志。
+//!
+//! 子进程运行在单独的进程组中，超时或被结束时整个进程组一起结束，脚本启动的子进程不会残留。
 
-#[derive(Debug, Serialize)]
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{Manager, Runtime, State, Window};
+
+use super::shell_policy::{Authorized, CommandRequest, ShellPolicy};
+
+/// run_command 默认的输出上限，stdout 和 stderr 分别计算
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+/// 等待子进程时检查超时和结束请求的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// run_command / spawn_command 的选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOptions {
+    /// 工作目录
+    pub cwd: Option<String>,
+    /// 额外的环境变量
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// 为 true 时不继承应用的环境变量，只使用 env
+    #[serde(default)]
+    pub clear_env: bool,
+    /// 超时（毫秒），超时后结束整个进程组
+    pub timeout_ms: Option<u64>,
+    /// run_command 中 stdout、stderr 各自最多保留的字节数，超出的部分被丢弃
+    pub max_output_bytes: Option<usize>,
+    /// run_command 返回的 stdout 的编码，图片等二进制输出使用 base64
+    #[serde(default)]
+    pub stdout_encoding: OutputEncoding,
+}
+
+/// run_command 返回 stdout 的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputEncoding {
+    /// 按 UTF-8 解码，无效的字节替换为 U+FFFD
+    #[default]
+    Text,
+    /// 原始字节的 base64，截断时不加截断标记
+    Base64,
+}
+
+impl CommandOptions {
+    fn command(&self, program: &OsStr, args: &[String]) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        cmd.envs(&self.env);
+        cmd.stdin(Stdio::piped());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        new_process_group(&mut cmd);
+        cmd
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout_ms
+            .map(|ms| Instant::now() + Duration::from_millis(ms))
+    }
+}
+
+#[cfg(unix)]
+fn new_process_group(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+#[cfg(windows)]
+fn new_process_group(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn new_process_group(_cmd: &mut Command) {}
+
+/// 结束子进程所在的整个进程组
+#[cfg(unix)]
+fn kill_process_group(child: &Child) {
+    // 子进程是进程组的组长，负的 pid 表示整个进程组
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_process_group(child: &Child) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let _ = Command::new("taskkill")
+        .args(["/PID", &child.id().to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .status();
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_process_group(_child: &Child) {}
+
+fn kill_tree(child: &mut Child) {
+    kill_process_group(child);
+    let _ = child.kill();
+}
+
+/// 结束进程的信号，仅 Unix
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => return None,
+    })
+}
+
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> Option<&'static str> {
+    None
+}
+
+/// 进程结束的方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessExit {
+    /// 退出码，被信号结束时为 None
+    pub code: Option<i32>,
+    /// 结束进程的信号编号，仅 Unix
+    pub signal: Option<i32>,
+    /// 信号名，例如 SIGKILL、SIGSEGV
+    pub signal_name: Option<String>,
+    /// 是否因为超时被结束
+    pub timed_out: bool,
+}
+
+impl ProcessExit {
+    pub(crate) fn new(status: ExitStatus, timed_out: bool) -> Self {
+        let signal = exit_signal(&status);
+        ProcessExit {
+            code: status.code(),
+            signal,
+            signal_name: signal.and_then(signal_name).map(str::to_string),
+            timed_out,
+        }
+    }
+}
+
+/// 等待子进程结束
+/// 超过 deadline 或 kill 被设置时结束整个进程组，返回退出状态和是否超时
+fn wait_child(
+    child: &mut Child,
+    deadline: Option<Instant>,
+    kill: &AtomicBool,
+) -> std::io::Result<(ExitStatus, bool)> {
+    let mut timed_out = false;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, timed_out));
+        }
+        if !timed_out && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            timed_out = true;
+            kill_tree(child);
+        } else if kill.swap(false, Ordering::Relaxed) {
+            kill_tree(child);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 读取全部输出，超过 limit 的部分读取后丢弃，返回保留的内容和丢弃的字节数
+fn read_capped(mut reader: impl Read, limit: usize) -> (Vec<u8>, u64) {
+    let mut kept = Vec::new();
+    let mut dropped = 0u64;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let take = limit.saturating_sub(kept.len()).min(n);
+        kept.extend_from_slice(&buf[..take]);
+        dropped += (n - take) as u64;
+    }
+    (kept, dropped)
+}
+
+/// 转为字符串，有内容被丢弃时在末尾加上截断标记
+fn output_text(bytes: &[u8], dropped: u64) -> String {
+    let mut text = String::from_utf8_lossy(bytes).into_owned();
+    if dropped > 0 {
+        text.push_str(&format!("\n[输出过长，已截断 {} 字节]", dropped));
+    }
+    text
+}
+
+/// 在单独的线程中写入 stdin，写完后关闭
+/// 子进程不读取 stdin 就退出时写入会失败（EPIPE），直接忽略
+fn write_stdin(mut stdin: ChildStdin, data: Vec<u8>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(&data);
+    })
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RunCommandResult {
     pub code: Option<i32>,
     pub stdout: String,
     pub stderr: String,
+    pub signal: Option<i32>,
+    pub signal_name: Option<String>,
+    pub timed_out: bool,
+    /// stdout 或 stderr 是否超过上限被截断
+    pub truncated: bool,
+}
+
+impl RunCommandResult {
+    fn failed(message: String) -> Self {
+        RunCommandResult {
+            stderr: message,
+            ..Default::default()
+        }
+    }
+}
+
+/// 运行命令直到结束，返回全部输出
+/// stdin 在单独的线程中写入，同时读取 stdout、stderr，子进程边读边写也不会互相等待
+pub fn run(
+    program: impl AsRef<OsStr>,
+    args: &[String],
+    stdin: Option<Vec<u8>>,
+    options: &CommandOptions,
+) -> RunCommandResult {
+    let mut child = match options.command(program.as_ref(), args).spawn() {
+        Ok(child) => child,
+        Err(e) => return RunCommandResult::failed(e.to_string()),
+    };
+
+    // 先开始读取输出，避免子进程因为输出管道写满而停住
+    let limit = options.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| std::thread::spawn(move || read_capped(out, limit)));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| std::thread::spawn(move || read_capped(err, limit)));
+
+    // 没有输入时直接关闭 stdin，读取到结尾的程序才能结束
+    let writer = match (stdin, child.stdin.take()) {
+        (Some(data), Some(child_stdin)) => Some(write_stdin(child_stdin, data)),
+        _ => None,
+    };
+
+    let waited = wait_child(&mut child, options.deadline(), &AtomicBool::new(false));
+    // 子进程结束后管道的读端随之关闭，写入线程不会一直阻塞
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    let join = |handle: Option<JoinHandle<(Vec<u8>, u64)>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    let (stdout, stdout_dropped) = join(stdout);
+    let (stderr, stderr_dropped) = join(stderr);
+
+    let exit = match waited {
+        Ok((status, timed_out)) => ProcessExit::new(status, timed_out),
+        Err(e) => return RunCommandResult::failed(e.to_string()),
+    };
+    RunCommandResult {
+        code: exit.code,
+        stdout: match options.stdout_encoding {
+            OutputEncoding::Text => output_text(&stdout, stdout_dropped),
+            OutputEncoding::Base64 => general_purpose::STANDARD.encode(&stdout),
+        },
+        stderr: output_text(&stderr, stderr_dropped),
+        signal: exit.signal,
+        signal_name: exit.signal_name,
+        timed_out: exit.timed_out,
+        truncated: stdout_dropped > 0 || stderr_dropped > 0,
+    }
 }
 
+/// 运行命令直到结束，返回全部输出
+/// 运行前按 cmd::shell_policy 检查，caller 是前端声明的调用方，记录在审计日志中
+/// 二进制输入通过 stdin_base64 传入，与 stdin 只能指定一个
 #[tauri::command]
-pub fn run_command(program: String, cmd_args: Vec<String>, stdin: Option<String>) -> RunCommandResult {
-    let mut cmd = Command::new(&program);
-    cmd.args(&cmd_args);
+pub async fn run_command<R: Runtime>(
+    window: Window<R>,
+    program: String,
+    cmd_args: Vec<String>,
+    stdin: Option<String>,
+    stdin_base64: Option<String>,
+    options: Option<CommandOptions>,
+    caller: Option<String>,
+) -> RunCommandResult {
+    tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let stdin = match (stdin, stdin_base64) {
+            (Some(_), Some(_)) => {
+                return RunCommandResult::failed("stdin 和 stdinBase64 只能指定一个".to_string())
+            }
+            (Some(text), None) => Some(text.into_bytes()),
+            (None, Some(data)) => match general_purpose::STANDARD.decode(data) {
+                Ok(bytes) => Some(bytes),
+                Err(e) => return RunCommandResult::failed(format!("stdinBase64 无效: {}", e)),
+            },
+            (None, None) => None,
+        };
+        let policy = window.state::<ShellPolicy>();
+        let request = CommandRequest::new(window.label(), caller, &program, &cmd_args, &options);
+        let authorized = match policy.authorize(&window, request) {
+            Ok(authorized) => authorized,
+            Err(message) => return RunCommandResult::failed(message),
+        };
+        let result = run(authorized.program(), &cmd_args, stdin, &options);
+        let exit = ProcessExit {
+            code: result.code,
+            signal: result.signal,
+            signal_name: result.signal_name.clone(),
+            timed_out: result.timed_out,
+        };
+        policy.finish(authorized, Some(&exit));
+        result
+    })
+    .await
+    .unwrap_or_else(|e| RunCommandResult::failed(format!("运行命令任务失败: {}", e)))
+}
+
+/// spawn_command 通过 Channel 发送的事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event", content = "data")]
+pub enum ProcessEvent {
+    /// stdout 的一行，不含换行符
+    Stdout(String),
+    Stderr(String),
+    /// 进程已结束，之后不会再有其他事件
+    Exit(ProcessExit),
+}
+
+/// 后台运行中的进程
+struct RunningProcess {
+    window: String,
+    stdin: Mutex<Option<ChildStdin>>,
+    kill: AtomicBool,
+}
+
+type ProcessMap = Arc<Mutex<HashMap<u32, Arc<RunningProcess>>>>;
+
+/// spawn_command 启动的进程
+#[derive(Default)]
+pub struct ProcessRegistry {
+    processes: ProcessMap,
+    next_id: AtomicU32,
+}
+
+impl ProcessRegistry {
+    /// 只能操作本窗口启动的进程
+    fn get(&self, id: u32, window: &str) -> Result<Arc<RunningProcess>, String> {
+        self.processes
+            .lock()
+            .unwrap()
+            .get(&id)
+            .filter(|process| process.window == window)
+            .cloned()
+            .ok_or_else(|| format!("进程 {} 不存在或已经结束", id))
+    }
+
+    /// 结束某个窗口启动的所有进程
+    pub fn kill_window(&self, window: &str) {
+        for process in self.processes.lock().unwrap().values() {
+            if process.window == window {
+                process.kill.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// 按行读取，每行通过 send 发送
+fn stream_lines(reader: impl Read, send: impl Fn(String)) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+                send(String::from_utf8_lossy(&line).into_owned());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpawnedProcess {
+    /// 传给 write_process_stdin / kill_process 的 ID
+    pub id: u32,
+    /// 操作系统的进程 ID
+    pub pid: u32,
+}
 
-    cmd.stdin(std::process::Stdio::piped());
-    cmd.stdout(std::process::Stdio::piped());
-    cmd.stderr(std::process::Stdio::piped());
+/// 在后台启动命令，输出按行通过 on_event 发送，进程结束时发送 Exit 事件
+/// 启动它的窗口关闭时进程会被结束
+/// 与 run_command 一样运行前按 cmd::shell_policy 检查，进程结束时写入审计日志
+#[tauri::command]
+pub async fn spawn_command<R: Runtime>(
+    window: Window<R>,
+    program: String,
+    cmd_args: Vec<String>,
+    options: Option<CommandOptions>,
+    on_event: Channel<ProcessEvent>,
+    caller: Option<String>,
+) -> Result<SpawnedProcess, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let request = CommandRequest::new(window.label(), caller, &program, &cmd_args, &options);
+        let authorized = window.state::<ShellPolicy>().authorize(&window, request)?;
+        spawn(window, &cmd_args, &options, on_event, authorized)
+    })
+    .await
+    .map_err(|e| format!("启动命令任务失败: {}", e))?
+}
 
-    let mut child = match cmd.spawn() {
+fn spawn<R: Runtime>(
+    window: Window<R>,
+    cmd_args: &[String],
+    options: &CommandOptions,
+    on_event: Channel<ProcessEvent>,
+    authorized: Authorized,
+) -> Result<SpawnedProcess, String> {
+    let policy = window.state::<ShellPolicy>();
+    let mut child = match options
+        .command(authorized.program().as_os_str(), cmd_args)
+        .spawn()
+    {
         Ok(child) => child,
         Err(e) => {
-            return RunCommandResult {
-                code: None,
-                stdout: String::new(),
-                stderr: e.to_string(),
-            };
+            let message = format!("无法启动 {}: {}", authorized.program().display(), e);
+            policy.finish(authorized, None);
+            return Err(message);
         }
     };
+    let registry = window.state::<ProcessRegistry>();
+    let id = registry.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let pid = child.id();
+    let process = Arc::new(RunningProcess {
+        window: window.label().to_string(),
+        stdin: Mutex::new(child.stdin.take()),
+        kill: AtomicBool::new(false),
+    });
+    registry
+        .processes
+        .lock()
+        .unwrap()
+        .insert(id, process.clone());
 
-    if let Some(stdin_data) = &stdin {
-        use std::io::Write;
-        if let Some(ref mut child_stdin) = child.stdin {
-            let _ = child_stdin.write_all(stdin_data.as_bytes());
-        }
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        let on_event = on_event.clone();
+        readers.push(std::thread::spawn(move || {
+            stream_lines(stdout, |line| {
+                let _ = on_event.send(ProcessEvent::Stdout(line));
+            })
+        }));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let on_event = on_event.clone();
+        readers.push(std::thread::spawn(move || {
+            stream_lines(stderr, |line| {
+                let _ = on_event.send(ProcessEvent::Stderr(line));
+            })
+        }));
     }
 
-    let output = match child.wait_with_output() {
-        Ok(output) => output,
-        Err(e) => {
-            return RunCommandResult {
-                code: None,
-                stdout: String::new(),
-                stderr: e.to_string(),
-            };
+    let processes = registry.processes.clone();
+    let deadline = options.deadline();
+    let app = window.app_handle().clone();
+    std::thread::spawn(move || {
+        let exit = match wait_child(&mut child, deadline, &process.kill) {
+            Ok((status, timed_out)) => ProcessExit::new(status, timed_out),
+            Err(_) => ProcessExit::default(),
+        };
+        // 输出全部发送完之后再发送 Exit
+        for reader in readers {
+            let _ = reader.join();
         }
+        processes.lock().unwrap().remove(&id);
+        app.state::<ShellPolicy>().finish(authorized, Some(&exit));
+        let _ = on_event.send(ProcessEvent::Exit(exit));
+    });
+
+    Ok(SpawnedProcess { id, pid })
+}
+
+/// 向后台进程的 stdin 写入
+/// 二进制数据通过 data_base64 传入，与 data 只能指定一个
+#[tauri::command]
+pub async fn write_process_stdin<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, ProcessRegistry>,
+    id: u32,
+    data: Option<String>,
+    data_base64: Option<String>,
+) -> Result<(), String> {
+    let process = registry.get(id, window.label())?;
+    let data = match (data, data_base64) {
+        (Some(text), None) => text.into_bytes(),
+        (None, Some(data)) => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("dataBase64 无效: {}", e))?,
+        _ => return Err("data 和 dataBase64 必须指定一个".to_string()),
     };
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut stdin = process.stdin.lock().unwrap();
+        let stdin = stdin
+            .as_mut()
+            .ok_or_else(|| format!("进程 {} 的 stdin 已关闭", id))?;
+        stdin
+            .write_all(&data)
+            .and_then(|_| stdin.flush())
+            .map_err(|e| format!("无法写入进程 {} 的 stdin: {}", id, e))
+    })
+    .await
+    .map_err(|e| format!("写入 stdin 任务失败: {}", e))?
+}
 
-    RunCommandResult {
-        code: output.status.code(),
-        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+/// 关闭后台进程的 stdin，读取到结尾的程序会因此结束
+#[tauri::command]
+pub fn close_process_stdin<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, ProcessRegistry>,
+    id: u32,
+) -> Result<(), String> {
+    registry
+        .get(id, window.label())?
+        .stdin
+        .lock()
+        .unwrap()
+        .take();
+    Ok(())
+}
+
+/// 结束后台进程及其进程组，结束后仍会发送 Exit 事件
+#[tauri::command]
+pub fn kill_process<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, ProcessRegistry>,
+    id: u32,
+) -> Result<(), String> {
+    registry
+        .get(id, window.label())?
+        .kill
+        .store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str, options: CommandOptions) -> RunCommandResult {
+        run(
+            "sh",
+            &["-c".to_string(), script.to_string()],
+            None,
+            &options,
+        )
+    }
+
+    #[test]
+    fn applies_options_and_reports_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = sh(
+            "pwd; echo $GREETING; echo err >&2; exit 3",
+            CommandOptions {
+                cwd: Some(dir.path().to_string_lossy().into_owned()),
+                env: HashMap::from([("GREETING".to_string(), "你好".to_string())]),
+                ..Default::default()
+            },
+        );
+        let cwd = std::fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(result.stdout, format!("{}\n你好\n", cwd.display()));
+        assert_eq!(result.stderr, "err\n");
+        assert_eq!(result.code, Some(3));
+        assert_eq!(result.signal, None);
+
+        let result = sh("kill -TERM $$", CommandOptions::default());
+        assert_eq!(result.code, None);
+        assert_eq!(result.signal_name.as_deref(), Some("SIGTERM"));
+
+        let result = sh(
+            "head -c 100 /dev/zero | tr '\\0' a",
+            CommandOptions {
+                max_output_bytes: Some(10),
+                ..Default::default()
+            },
+        );
+        assert!(result.truncated);
+        assert_eq!(result.stdout, "aaaaaaaaaa\n[输出过长，已截断 90 字节]");
+    }
+
+    #[test]
+    fn timeout_kills_the_process_group() {
+        let started = Instant::now();
+        // 后台的 sleep 继承了输出管道，不结束整个进程组的话读取输出会一直等下去
+        let result = sh(
+            "sleep 30 & echo started; wait",
+            CommandOptions {
+                timeout_ms: Some(200),
+                ..Default::default()
+            },
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.timed_out);
+        assert_eq!(result.stdout, "started\n");
+        assert_eq!(result.signal_name.as_deref(), Some("SIGKILL"));
+    }
+
+    #[test]
+    fn streams_binary_stdin_without_deadlock() {
+        // 远大于管道缓冲区，先写完 stdin 再读输出的话 cat 会停在写 stdout 上
+        let input: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 256) as u8).collect();
+        let result = run(
+            "cat",
+            &[],
+            Some(input.clone()),
+            &CommandOptions {
+                timeout_ms: Some(10_000),
+                stdout_encoding: OutputEncoding::Base64,
+                ..Default::default()
+            },
+        );
+        assert!(!result.timed_out);
+        assert_eq!(result.code, Some(0));
+        assert_eq!(
+            general_purpose::STANDARD.decode(result.stdout).unwrap(),
+            input
+        );
+
+        // 不读取 stdin 就退出的程序
+        let result = run("true", &[], Some(input), &CommandOptions::default());
+        assert_eq!(result.code, Some(0));
+    }
+
+    #[test]
+    fn only_the_owning_window_can_use_a_process() {
+        let registry = ProcessRegistry::default();
+        registry.processes.lock().unwrap().insert(
+            1,
+            Arc::new(RunningProcess {
+                window: "main".to_string(),
+                stdin: Mutex::new(None),
+                kill: AtomicBool::new(false),
+            }),
+        );
+
+        assert!(registry.get(1, "other").is_err());
+        assert!(registry.get(1, "main").is_ok());
     }
 }
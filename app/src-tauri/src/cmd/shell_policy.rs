@@ -0,0 +1,608 @@
+//! run_command / spawn_command 的权限策略和审计日志
+//!
+//! 与 cmd::scope 一样，前端传来的命令都是不可信的（AI 工具、扩展都能调用），所以每次运行前：
+//! 1. 按 PATH 找到程序的完整路径
+//! 2. 按规则决定允许、拒绝还是询问用户：匹配到拒绝规则时拒绝，其次是询问、允许，都没有匹配时使用默认动作
+//! 3. 询问时弹出系统对话框，而不是交给 webview 回答，否则页面中的脚本可以替自己批准
+//!
+//! 每一次运行（包括被拒绝的）都会追加到审计日志中，日志只追加，不提供修改和删除的命令。
+
+use globset::GlobBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime, State, Window};
+
+use super::shell::{CommandOptions, ProcessExit};
+
+/// 保存策略的文件
+const POLICY_FILE: &str = "shell-policy.json";
+
+/// 审计日志，每行一个 JSON
+const AUDIT_FILE: &str = "shell-audit.log";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    #[default]
+    Ask,
+}
+
+/// 一条规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyRule {
+    pub action: PolicyAction,
+    /// 程序的 glob，包含路径分隔符时匹配完整路径，否则只匹配文件名
+    pub program: String,
+    /// 参数的正则，匹配用空格连接后的所有参数，为空时匹配任意参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
+}
+
+impl PolicyRule {
+    fn validate(&self) -> Result<(), String> {
+        glob(&self.program)?;
+        if let Some(args) = &self.args {
+            Regex::new(args).map_err(|e| format!("无效的参数规则 {}: {}", args, e))?;
+        }
+        Ok(())
+    }
+
+    /// 无效的规则不匹配任何命令，保存策略时已经检查过
+    fn matches(&self, program: &Path, args: &[String]) -> bool {
+        let target = if self.program.contains(['/', '\\']) {
+            Some(program.as_os_str())
+        } else {
+            program.file_name()
+        };
+        let program_matches = target
+            .zip(glob(&self.program).ok())
+            .is_some_and(|(target, glob)| glob.is_match(target));
+        let args_matches = match &self.args {
+            Some(pattern) => Regex::new(pattern).is_ok_and(|re| re.is_match(&args.join(" "))),
+            None => true,
+        };
+        program_matches && args_matches
+    }
+}
+
+fn glob(pattern: &str) -> Result<globset::GlobMatcher, String> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(cfg!(windows))
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format!("无效的程序规则 {}: {}", pattern, e))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShellPolicyConfig {
+    /// 没有匹配任何规则时的动作
+    pub default_action: PolicyAction,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl ShellPolicyConfig {
+    pub fn evaluate(&self, program: &Path, args: &[String]) -> PolicyAction {
+        let matched: Vec<PolicyAction> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(program, args))
+            .map(|rule| rule.action)
+            .collect();
+        [PolicyAction::Deny, PolicyAction::Ask, PolicyAction::Allow]
+            .into_iter()
+            .find(|action| matched.contains(action))
+            .unwrap_or(self.default_action)
+    }
+}
+
+/// 按 PATH 查找程序，带路径的程序相对于 cwd，找不到时原样返回
+pub fn resolve_program(program: &str, cwd: Option<&str>, path_var: Option<OsString>) -> PathBuf {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return match cwd {
+            Some(cwd) if path.is_relative() => Path::new(cwd).join(path),
+            _ => path.to_path_buf(),
+        };
+    }
+    let extensions: Vec<String> = if cfg!(windows) {
+        let pathext =
+            std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        std::iter::once(String::new())
+            .chain(pathext.split(';').map(str::to_string))
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    path_var
+        .or_else(|| std::env::var_os("PATH"))
+        .and_then(|path_var| {
+            std::env::split_paths(&path_var)
+                .flat_map(|dir| {
+                    extensions
+                        .iter()
+                        .map(move |ext| dir.join(format!("{}{}", program, ext)))
+                })
+                .find(|candidate| candidate.is_file())
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// 运行与否是如何决定的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Decision {
+    /// 规则允许
+    Allowed,
+    /// 用户同意
+    Approved,
+    /// 规则拒绝
+    Denied,
+    /// 用户拒绝或超时未回答
+    Rejected,
+}
+
+/// 审计日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// 毫秒时间戳
+    pub time: u64,
+    /// 发起调用的窗口
+    pub window: String,
+    /// 前端声明的调用方，例如 extension:xxx、ai-tool
+    pub caller: Option<String>,
+    pub program: String,
+    pub resolved_program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub decision: Decision,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+}
+
+/// 只追加的审计日志
+#[derive(Default)]
+pub struct AuditLog {
+    file: Option<PathBuf>,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        AuditLog {
+            file,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), String> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(entry).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("无法创建日志目录: {}", e))?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .and_then(|mut log| log.write_all(&line))
+            .map_err(|e| format!("无法写入审计日志: {}", e))
+    }
+
+    /// 最近的 limit 条记录，从旧到新排列
+    pub fn tail(&self, limit: usize) -> Result<Vec<AuditEntry>, String> {
+        let Some(file) = &self.file else {
+            return Ok(Vec::new());
+        };
+        let log = match std::fs::File::open(file) {
+            Ok(log) => log,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("无法读取审计日志: {}", e)),
+        };
+        let entries: Vec<AuditEntry> = BufReader::new(log)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        Ok(entries[entries.len().saturating_sub(limit)..].to_vec())
+    }
+}
+
+/// 一次待运行的命令
+#[derive(Debug, Clone)]
+pub struct CommandRequest {
+    pub window: String,
+    pub caller: Option<String>,
+    pub program: String,
+    pub resolved_program: PathBuf,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+}
+
+impl CommandRequest {
+    pub fn new(
+        window: &str,
+        caller: Option<String>,
+        program: &str,
+        args: &[String],
+        options: &CommandOptions,
+    ) -> Self {
+        let path_var = options
+            .env
+            .get("PATH")
+            .map(OsString::from)
+            .or_else(|| options.clear_env.then(OsString::new));
+        CommandRequest {
+            window: window.to_string(),
+            caller,
+            program: program.to_string(),
+            resolved_program: resolve_program(program, options.cwd.as_deref(), path_var),
+            args: args.to_vec(),
+            cwd: options.cwd.clone(),
+        }
+    }
+}
+
+/// 用户对询问的回答
+#[derive(Debug, Clone, Copy)]
+struct ApprovalAnswer {
+    approved: bool,
+    /// 为 true 时为这个程序添加一条允许的规则，以后不再询问
+    remember: bool,
+}
+
+/// 已经允许运行的命令，运行结束后调用 ShellPolicy::finish 写入审计日志
+pub struct Authorized {
+    request: CommandRequest,
+    decision: Decision,
+    started: Instant,
+}
+
+impl Authorized {
+    /// 经过检查的程序的完整路径，运行时直接使用它
+    /// 再按名字启动的话，系统的查找顺序（例如 Windows 先找应用所在目录）可能找到另一个程序
+    pub fn program(&self) -> &Path {
+        &self.request.resolved_program
+    }
+}
+
+/// run_command / spawn_command 的权限策略
+#[derive(Default)]
+pub struct ShellPolicy {
+    config: RwLock<ShellPolicyConfig>,
+    /// 保存策略的文件，为空时不保存
+    file: Option<PathBuf>,
+    audit: AuditLog,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl ShellPolicy {
+    /// 策略和审计日志都放在受保护目录中，前端的文件接口无法改写它们
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> Self {
+        let dir = super::scope::protected_dir(app);
+        let file = dir.as_ref().map(|dir| dir.join(POLICY_FILE));
+        let config = file
+            .as_ref()
+            .and_then(|file| std::fs::read(file).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        ShellPolicy {
+            config: RwLock::new(config),
+            file,
+            audit: AuditLog::new(dir.map(|dir| dir.join(AUDIT_FILE))),
+        }
+    }
+
+    pub fn config(&self) -> ShellPolicyConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: ShellPolicyConfig) -> Result<(), String> {
+        for rule in &config.rules {
+            rule.validate()?;
+        }
+        if let Some(file) = &self.file {
+            if let Some(dir) = file.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| format!("无法创建配置目录: {}", e))?;
+            }
+            let content = serde_json::to_vec_pretty(&config).map_err(|e| e.to_string())?;
+            super::fs::write_file_atomic(file, &content, false)?;
+        }
+        *self.config.write().unwrap() = config;
+        Ok(())
+    }
+
+    /// 在系统对话框中询问用户，关闭对话框视为拒绝
+    /// 同意后再询问是否记住，两个对话框都只用标准的是/否按钮，结果不依赖按钮的文字
+    fn ask<R: Runtime>(&self, window: &Window<R>, request: &CommandRequest) -> ApprovalAnswer {
+        use tauri_plugin_dialog::{
+            DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+        };
+
+        let mut detail = Vec::new();
+        if let Some(caller) = &request.caller {
+            detail.push(format!("调用方：{}", caller));
+        }
+        detail.push(format!(
+            "命令：{}",
+            std::iter::once(request.resolved_program.to_string_lossy().into_owned())
+                .chain(request.args.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+        if let Some(cwd) = &request.cwd {
+            detail.push(format!("工作目录：{}", cwd));
+        }
+        let confirm = |message: String| {
+            window
+                .dialog()
+                .message(message)
+                .title("运行命令")
+                .kind(MessageDialogKind::Warning)
+                .buttons(MessageDialogButtons::YesNo)
+                .blocking_show_with_result()
+                == MessageDialogResult::Yes
+        };
+        let approved = confirm(format!("是否允许运行外部命令？\n{}", detail.join("\n")));
+        let remember = approved
+            && confirm(format!(
+                "以后运行 {} 时是否不再询问？",
+                request.resolved_program.display()
+            ));
+        ApprovalAnswer { approved, remember }
+    }
+
+    fn remember(&self, program: &Path, approved: bool) -> Result<(), String> {
+        let mut config = self.config();
+        config.rules.push(PolicyRule {
+            action: if approved {
+                PolicyAction::Allow
+            } else {
+                PolicyAction::Deny
+            },
+            program: globset::escape(&program.to_string_lossy()),
+            args: None,
+        });
+        self.set_config(config)
+    }
+
+    /// 按策略决定是否允许运行，需要询问时阻塞直到用户回答
+    /// 被拒绝时写入审计日志并返回错误
+    pub fn authorize<R: Runtime>(
+        &self,
+        window: &Window<R>,
+        request: CommandRequest,
+    ) -> Result<Authorized, String> {
+        let action = self
+            .config
+            .read()
+            .unwrap()
+            .evaluate(&request.resolved_program, &request.args);
+        let decision = match action {
+            PolicyAction::Allow => Decision::Allowed,
+            PolicyAction::Deny => Decision::Denied,
+            PolicyAction::Ask => {
+                let answer = self.ask(window, &request);
+                if answer.remember {
+                    self.remember(&request.resolved_program, answer.approved)?;
+                }
+                if answer.approved {
+                    Decision::Approved
+                } else {
+                    Decision::Rejected
+                }
+            }
+        };
+        let authorized = Authorized {
+            request,
+            decision,
+            started: Instant::now(),
+        };
+        match decision {
+            Decision::Allowed | Decision::Approved => Ok(authorized),
+            Decision::Denied | Decision::Rejected => {
+                let message = format!(
+                    "{}：{}",
+                    if decision == Decision::Denied {
+                        "策略不允许运行此命令"
+                    } else {
+                        "用户拒绝运行此命令"
+                    },
+                    authorized.request.resolved_program.display()
+                );
+                self.finish(authorized, None);
+                Err(message)
+            }
+        }
+    }
+
+    /// 写入审计日志，exit 为空表示没有运行
+    pub fn finish(&self, authorized: Authorized, exit: Option<&ProcessExit>) {
+        let Authorized {
+            request,
+            decision,
+            started,
+        } = authorized;
+        let entry = AuditEntry {
+            time: now_ms(),
+            window: request.window,
+            caller: request.caller,
+            program: request.program,
+            resolved_program: request.resolved_program.to_string_lossy().into_owned(),
+            args: request.args,
+            cwd: request.cwd,
+            decision,
+            exit_code: exit.and_then(|exit| exit.code),
+            signal: exit.and_then(|exit| exit.signal),
+            timed_out: exit.is_some_and(|exit| exit.timed_out),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        if let Err(e) = self.audit.append(&entry) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// 读取当前的策略
+#[tauri::command]
+pub fn get_shell_policy(policy: State<'_, ShellPolicy>) -> ShellPolicyConfig {
+    policy.config()
+}
+
+/// 修改策略，需要用户在系统对话框中确认
+/// 返回是否已修改，用户取消时返回 false
+#[cfg(desktop)]
+#[tauri::command]
+pub async fn set_shell_policy<R: Runtime>(
+    app: AppHandle<R>,
+    config: ShellPolicyConfig,
+) -> Result<bool, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    for rule in &config.rules {
+        rule.validate()?;
+    }
+    let dialog = app.clone();
+    let confirmed = tauri::async_runtime::spawn_blocking(move || {
+        dialog
+            .dialog()
+            .message("是否允许修改运行外部命令的权限策略？")
+            .title("命令权限")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("无法打开对话框: {}", e))?;
+    if !confirmed {
+        return Ok(false);
+    }
+    app.state::<ShellPolicy>().set_config(config)?;
+    Ok(true)
+}
+
+/// 读取最近的审计日志，从旧到新排列
+#[tauri::command]
+pub async fn get_shell_audit_log<R: Runtime>(
+    app: AppHandle<R>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<ShellPolicy>().audit.tail(limit.unwrap_or(200))
+    })
+    .await
+    .map_err(|e| format!("读取审计日志任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: PolicyAction, program: &str, args: Option<&str>) -> PolicyRule {
+        PolicyRule {
+            action,
+            program: program.to_string(),
+            args: args.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn deny_rules_take_precedence() {
+        let config = ShellPolicyConfig {
+            default_action: PolicyAction::Ask,
+            rules: vec![
+                rule(PolicyAction::Allow, "git", None),
+                rule(PolicyAction::Deny, "git", Some(r"^push\b")),
+                rule(PolicyAction::Allow, "/opt/tools/*", None),
+                rule(PolicyAction::Deny, "rm", None),
+            ],
+        };
+        let args = |args: &str| -> Vec<String> { args.split(' ').map(str::to_string).collect() };
+        let git = Path::new("/usr/bin/git");
+        assert_eq!(config.evaluate(git, &args("status")), PolicyAction::Allow);
+        assert_eq!(
+            config.evaluate(git, &args("push origin main")),
+            PolicyAction::Deny
+        );
+        assert_eq!(
+            config.evaluate(Path::new("/opt/tools/dot"), &args("-Tsvg")),
+            PolicyAction::Allow
+        );
+        // 带路径的规则不匹配子目录
+        assert_eq!(
+            config.evaluate(Path::new("/opt/tools/sub/dot"), &[]),
+            PolicyAction::Ask
+        );
+        assert_eq!(
+            config.evaluate(Path::new("/bin/rm"), &args("-rf /")),
+            PolicyAction::Deny
+        );
+        assert!(rule(PolicyAction::Allow, "[", None).validate().is_err());
+    }
+
+    #[test]
+    fn resolves_programs_and_appends_audit_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("tool"), b"").unwrap();
+        assert_eq!(
+            resolve_program("tool", None, Some(bin.clone().into_os_string())),
+            bin.join("tool")
+        );
+        assert_eq!(
+            resolve_program("./tool", Some("/work"), None),
+            Path::new("/work/./tool")
+        );
+        assert_eq!(
+            resolve_program("missing", None, Some(bin.into_os_string())),
+            Path::new("missing")
+        );
+
+        let log = AuditLog::new(Some(dir.path().join("audit.log")));
+        for code in 0..3 {
+            log.append(&AuditEntry {
+                time: code as u64,
+                window: "main".to_string(),
+                caller: Some("ai-tool".to_string()),
+                program: "tool".to_string(),
+                resolved_program: "/bin/tool".to_string(),
+                args: vec![],
+                cwd: None,
+                decision: Decision::Allowed,
+                exit_code: Some(code),
+                signal: None,
+                timed_out: false,
+                duration_ms: 1,
+            })
+            .unwrap();
+        }
+        let tail = log.tail(2).unwrap();
+        assert_eq!(
+            tail.iter().map(|entry| entry.exit_code).collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+    }
+}
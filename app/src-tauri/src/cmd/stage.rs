@@ -0,0 +1,121 @@
+//! stage.msgpack 的语义模型
+//!
+//! stage.msgpack 是前端 @graphif/serializer 序列化出的舞台对象数组：
+//! - 每个对象都带有 `_` 字段表示类名，例如 `{ "_": "TextNode", "uuid": ..., "text": ... }`
+//! - 带有 @id 的对象（实体、连线）第二次出现时会被替换为 `{ "$": "/3/associationList/0" }`，
+//!   指向它第一次出现的位置，所以一个实体可能被内嵌在连线的 associationList 中
+//!
+//! 这里只读取 Rust 侧需要的信息，不追求还原完整的对象。
+
+use serde_json::Value;
+
+/// 对象的类名，即 `_` 字段
+pub fn class_name(value: &Value) -> Option<&str> {
+    value.get("_")?.as_str()
+}
+
+/// 对象的 UUID
+pub fn uuid(value: &Value) -> Option<&str> {
+    value.get("uuid")?.as_str()
+}
+
+/// 根据 `{ "$": path }` 中的路径找到被引用的对象，path 相对于整个舞台数组
+pub fn resolve_path<'a>(stage: &'a [Value], path: &str) -> Option<&'a Value> {
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let first: usize = segments.next()?.parse().ok()?;
+    let mut current = stage.get(first)?;
+    for segment in segments {
+        current = match current {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            Value::Object(map) => map.get(segment)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// 如果是引用则解析，否则原样返回
+pub fn resolve<'a>(stage: &'a [Value], value: &'a Value) -> Option<&'a Value> {
+    match value.get("$").and_then(Value::as_str) {
+        Some(path) => resolve_path(stage, path),
+        None => Some(value),
+    }
+}
+
+/// 遍历所有舞台对象（带有类名和 UUID 的对象），包括内嵌在其他对象中的
+/// 每个 UUID 只访问一次
+pub fn for_each_object<'a>(stage: &'a [Value], mut f: impl FnMut(&'a Value)) {
+    fn walk<'a>(
+        value: &'a Value,
+        seen: &mut std::collections::HashSet<&'a str>,
+        f: &mut impl FnMut(&'a Value),
+    ) {
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| walk(item, seen, f)),
+            Value::Object(map) => {
+                if let (Some(_), Some(id)) = (class_name(value), uuid(value)) {
+                    if !seen.insert(id) {
+                        return;
+                    }
+                    f(value);
+                }
+                map.values().for_each(|item| walk(item, seen, f));
+            }
+            _ => {}
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    stage
+        .iter()
+        .for_each(|value| walk(value, &mut seen, &mut f));
+}
+
+/// 收集 details（Plate 富文本）中的所有文字
+fn collect_details_text(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_details_text(item, out)),
+        Value::Object(map) => {
+            if let Some(Value::String(text)) = map.get("text") {
+                if !text.trim().is_empty() {
+                    out.push(text.clone());
+                }
+            }
+            if let Some(children) = map.get("children") {
+                collect_details_text(children, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 一个对象上用户可见的文字：text、title、url、latexSource 以及 details 中的内容
+pub fn object_text(value: &Value) -> String {
+    let mut parts = Vec::new();
+    for key in ["text", "title", "url", "latexSource"] {
+        if let Some(text) = value.get(key).and_then(Value::as_str) {
+            if !text.trim().is_empty() {
+                parts.push(text.to_string());
+            }
+        }
+    }
+    if let Some(details) = value.get("details") {
+        collect_details_text(details, &mut parts);
+    }
+    parts.join("\n")
+}
+
+/// 所有带文字的舞台对象，返回 (UUID, 文字)
+pub fn text_items(stage: &[Value]) -> Vec<(String, String)> {
+    let mut items = Vec::new();
+    for_each_object(stage, |value| {
+        let text = object_text(value);
+        if !text.is_empty() {
+            if let Some(id) = uuid(value) {
+                items.push((id.to_string(), text));
+            }
+        }
+    });
+    items
+}
@@ -0,0 +1,92 @@
+//! 可取消的后台任务
+//!
+//! 耗时的命令接受一个由前端生成的 task_id，
+//! 前端随时可以调用 cancel_task(task_id) 让它尽快结束。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+/// 取消标记，任务在循环中定期检查
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+type TaskMap = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
+/// 正在运行的可取消任务
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: TaskMap,
+}
+
+/// 任务结束（guard 被丢弃）时自动从注册表中移除
+pub struct TaskGuard {
+    tasks: TaskMap,
+    id: Option<String>,
+    token: CancellationToken,
+}
+
+impl TaskGuard {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            let mut tasks = self.tasks.lock().unwrap();
+            // 同一个 task_id 可能已经被新的任务复用了
+            if tasks
+                .get(id)
+                .is_some_and(|token| Arc::ptr_eq(&token.0, &self.token.0))
+            {
+                tasks.remove(id);
+            }
+        }
+    }
+}
+
+impl TaskRegistry {
+    /// 注册一个任务
+    /// 没有 task_id 的任务无法从前端取消，但依然可以用同样的方式检查 token
+    pub fn register(&self, task_id: Option<String>) -> TaskGuard {
+        let token = CancellationToken::default();
+        if let Some(id) = &task_id {
+            self.tasks.lock().unwrap().insert(id.clone(), token.clone());
+        }
+        TaskGuard {
+            tasks: self.tasks.clone(),
+            id: task_id,
+            token,
+        }
+    }
+
+    pub fn cancel(&self, task_id: &str) -> bool {
+        match self.tasks.lock().unwrap().get(task_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 取消一个正在运行的任务
+/// 返回任务是否存在
+#[tauri::command]
+pub fn cancel_task(registry: State<'_, TaskRegistry>, task_id: String) -> bool {
+    registry.cancel(&task_id)
+}
@@ -0,0 +1,315 @@
+//! .prg 缩略图缓存
+//!
+//! 最近文件、欢迎页需要同时显示很多 .prg 的缩略图。
+//! 这里只读取 ZIP 的中央目录和 thumbnail.png 这一个条目，缩放到需要的尺寸后
+//! 缓存到应用缓存目录的 prg-thumbnail-cache-v2 中。
+//!
+//! 缓存文件名为 `{路径的哈希}-{修改时间、大小和尺寸的哈希}.png`，
+//! .prg 被修改后缓存自然失效，写入新缓存时删除同一路径的旧缓存。
+//! 没有缩略图的 .prg 缓存为空文件，避免反复打开它。
+
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use super::fs::write_file_atomic;
+use super::prg::{open_archive, read_entry, THUMBNAIL_ENTRY};
+use super::scope::FsScope;
+
+const CACHE_DIR: &str = "prg-thumbnail-cache-v2";
+
+/// 默认的最大边长
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+
+pub fn cache_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| format!("无法获取缓存目录: {}", e))
+}
+
+/// 只读取中央目录和 thumbnail.png，不解析工程的其他内容
+pub fn extract_thumbnail(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    let mut archive = open_archive(path)?;
+    read_entry(&mut archive, THUMBNAIL_ENTRY)
+}
+
+/// 等比缩放到最大边长不超过 max_size，本来就更小的图片原样返回
+pub fn resize_png(bytes: &[u8], max_size: u32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("无法解析缩略图: {}", e))?;
+    if image.width() <= max_size && image.height() <= max_size {
+        return Ok(bytes.to_vec());
+    }
+    let mut out = Cursor::new(Vec::new());
+    image
+        .thumbnail(max_size, max_size)
+        .write_to(&mut out, image::ImageFormat::Png)
+        .map_err(|e| format!("无法编码缩略图: {}", e))?;
+    Ok(out.into_inner())
+}
+
+fn path_key(path: &Path) -> String {
+    blake3::hash(path.as_os_str().as_encoded_bytes()).to_hex()[..16].to_string()
+}
+
+/// 缓存文件的位置，由路径、修改时间、大小和尺寸决定
+fn cache_path(cache_dir: &Path, path: &Path, max_size: u32) -> Result<PathBuf, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("无法读取文件信息: {}", e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let version = format!("{}:{}:{}", modified, metadata.len(), max_size);
+    Ok(cache_dir.join(format!(
+        "{}-{}.png",
+        path_key(path),
+        &blake3::hash(version.as_bytes()).to_hex()[..16]
+    )))
+}
+
+/// 同一路径最近的一份缓存，用于无法访问 .prg 本身的情况
+fn latest_cache(cache_dir: &Path, path: &Path) -> Option<PathBuf> {
+    let prefix = format!("{}-", path_key(path));
+    std::fs::read_dir(cache_dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+/// 删除同一路径的其他缓存
+fn remove_stale(cache_dir: &Path, path: &Path, keep: &Path) {
+    let prefix = format!("{}-", path_key(path));
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) && entry.path() != keep {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// 缓存的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailStatus {
+    /// 已有缓存
+    Cached,
+    /// 新生成了缓存
+    Updated,
+    /// 文件中没有缩略图
+    Missing,
+}
+
+/// 生成缩略图缓存，已有缓存且 refresh 为 false 时直接使用
+/// 返回缩略图内容，没有缩略图时为空
+pub fn ensure_cached(
+    cache_dir: &Path,
+    path: &Path,
+    max_size: u32,
+    refresh: bool,
+) -> Result<(ThumbnailStatus, Vec<u8>), String> {
+    let cached = cache_path(cache_dir, path, max_size)?;
+    if !refresh {
+        if let Ok(data) = std::fs::read(&cached) {
+            return Ok((ThumbnailStatus::Cached, data));
+        }
+    }
+    let (status, data) = match extract_thumbnail(path)? {
+        Some(thumbnail) => (ThumbnailStatus::Updated, resize_png(&thumbnail, max_size)?),
+        None => (ThumbnailStatus::Missing, Vec::new()),
+    };
+    std::fs::create_dir_all(cache_dir).map_err(|e| format!("无法创建缓存目录: {}", e))?;
+    write_file_atomic(&cached, &data, false)?;
+    remove_stale(cache_dir, path, &cached);
+    Ok((status, data))
+}
+
+/// 读取缩略图
+/// cache_only 为 true 时不打开 .prg（例如 macOS 上没有权限访问），
+/// 无法读取文件信息时使用这个路径最近的一份缓存
+pub fn read_thumbnail(
+    cache_dir: &Path,
+    path: &Path,
+    max_size: u32,
+    cache_only: bool,
+) -> Result<Vec<u8>, String> {
+    if !cache_only {
+        return ensure_cached(cache_dir, path, max_size, false).map(|(_, data)| data);
+    }
+    let cached = cache_path(cache_dir, path, max_size)
+        .ok()
+        .filter(|cached| cached.exists())
+        .or_else(|| latest_cache(cache_dir, path));
+    Ok(cached
+        .and_then(|cached| std::fs::read(cached).ok())
+        .unwrap_or_default())
+}
+
+/// 批量缓存的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailCacheResult {
+    pub path: String,
+    pub status: Option<ThumbnailStatus>,
+    pub error: Option<String>,
+}
+
+/// 获取 .prg 的缩略图，以二进制返回，没有缩略图时返回空数据
+/// size: 最大边长，默认 256
+/// cache_only: 只读取缓存，不打开 .prg
+#[tauri::command]
+pub async fn get_prg_thumbnail<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    path: String,
+    size: Option<u32>,
+    cache_only: Option<bool>,
+) -> Result<tauri::ipc::Response, String> {
+    scope.check(Path::new(&path))?;
+    let cache_dir = cache_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        read_thumbnail(
+            &cache_dir,
+            Path::new(&path),
+            size.unwrap_or(DEFAULT_THUMBNAIL_SIZE),
+            cache_only.unwrap_or(false),
+        )
+        .map(tauri::ipc::Response::new)
+    })
+    .await
+    .map_err(|e| format!("读取缩略图失败: {}", e))?
+}
+
+/// 并行为多个 .prg 生成缩略图缓存
+/// refresh 为 true 时忽略已有缓存，重新生成
+#[tauri::command]
+pub async fn cache_prg_thumbnails<R: Runtime>(
+    app: AppHandle<R>,
+    scope: State<'_, FsScope>,
+    paths: Vec<String>,
+    size: Option<u32>,
+    refresh: Option<bool>,
+) -> Result<Vec<ThumbnailCacheResult>, String> {
+    let cache_dir = cache_dir(&app)?;
+    let max_size = size.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+    let refresh = refresh.unwrap_or(false);
+    // 范围外的路径和其他错误一样记录在对应的结果中
+    let paths: Vec<(String, Result<(), String>)> = paths
+        .into_iter()
+        .map(|path| {
+            let allowed = scope
+                .check(Path::new(&path))
+                .map(|_| ())
+                .map_err(String::from);
+            (path, allowed)
+        })
+        .collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        paths
+            .into_par_iter()
+            .map(|(path, allowed)| {
+                match allowed
+                    .and_then(|()| ensure_cached(&cache_dir, Path::new(&path), max_size, refresh))
+                {
+                    Ok((status, _)) => ThumbnailCacheResult {
+                        path,
+                        status: Some(status),
+                        error: None,
+                    },
+                    Err(error) => ThumbnailCacheResult {
+                        path,
+                        status: None,
+                        error: Some(error),
+                    },
+                }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("生成缩略图失败: {}", e))
+}
+
+/// 清空缩略图缓存
+#[tauri::command]
+pub async fn clear_prg_thumbnail_cache<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    let cache_dir = cache_dir(&app)?;
+    match std::fs::remove_dir_all(&cache_dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("无法清空缩略图缓存: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::prg::PrgProject;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image::RgbaImage::new(width, height)
+            .write_to(&mut out, image::ImageFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn caches_resized_thumbnails_until_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let with_thumbnail = dir.path().join("a.prg");
+        let without_thumbnail = dir.path().join("b.prg");
+        PrgProject {
+            thumbnail: Some(png(800, 400)),
+            ..Default::default()
+        }
+        .save(&with_thumbnail)
+        .unwrap();
+        PrgProject::default().save(&without_thumbnail).unwrap();
+
+        let (status, data) = ensure_cached(&cache, &with_thumbnail, 100, false).unwrap();
+        assert_eq!(status, ThumbnailStatus::Updated);
+        let image = image::load_from_memory(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+        let (status, cached) = ensure_cached(&cache, &with_thumbnail, 100, false).unwrap();
+        assert_eq!(status, ThumbnailStatus::Cached);
+        assert_eq!(cached, data);
+
+        let (status, data) = ensure_cached(&cache, &without_thumbnail, 100, false).unwrap();
+        assert_eq!(status, ThumbnailStatus::Missing);
+        assert!(data.is_empty());
+        assert_eq!(
+            ensure_cached(&cache, &without_thumbnail, 100, false)
+                .unwrap()
+                .0,
+            ThumbnailStatus::Cached
+        );
+
+        // 文件变化后重新生成，旧缓存被删除
+        PrgProject {
+            thumbnail: Some(png(50, 20)),
+            ..Default::default()
+        }
+        .save(&with_thumbnail)
+        .unwrap();
+        let (status, data) = ensure_cached(&cache, &with_thumbnail, 100, false).unwrap();
+        assert_eq!(status, ThumbnailStatus::Updated);
+        assert_eq!(data, png(50, 20));
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 2);
+
+        // 源文件不可访问时，只读缓存仍然能拿到最近的缩略图
+        std::fs::remove_file(&with_thumbnail).unwrap();
+        assert_eq!(
+            read_thumbnail(&cache, &with_thumbnail, 100, true).unwrap(),
+            data
+        );
+        assert!(read_thumbnail(&cache, &with_thumbnail, 100, false).is_err());
+    }
+}
@@ -0,0 +1,666 @@
+//! 分块传输文件
+//!
+//! 大文件（图片、PDF 等附件）不再整个编码成 base64 字符串，而是：
+//! 1. begin_file_read / begin_file_write 开始一次传输，得到传输 ID
+//! 2. 通过二进制 IPC 逐块调用 read_file_chunk / write_file_chunk，每块指定 offset 和 length
+//! 3. finish_file_transfer 结束传输，返回整个文件的 SHA-256
+//!
+//! 每传输一块都会向发起传输的窗口发送 `fs-transfer-progress` 事件。
+//! 传输 ID 只在发起传输的窗口中有效。
+//! 写入先写到同目录下的临时文件，结束时校验通过后才替换目标文件。
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::{AppHandle, Emitter, Runtime, State, Window};
+
+use super::fs::FsError;
+use super::hash::to_hex;
+use super::scope::FsScope;
+
+/// 发送给前端的事件名
+pub const FS_TRANSFER_PROGRESS_EVENT: &str = "fs-transfer-progress";
+
+/// 单块的最大长度
+pub const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferKind {
+    Read,
+    Write,
+}
+
+/// `fs-transfer-progress` 事件的内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub transfer_id: u32,
+    pub kind: TransferKind,
+    pub path: String,
+    /// 已传输的字节数
+    pub transferred: u64,
+    /// 文件总大小，写入时不知道大小则为 None
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStart {
+    pub transfer_id: u32,
+    /// 读取时为文件大小
+    pub size: Option<u64>,
+}
+
+/// 传输结束时的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferResult {
+    pub size: u64,
+    /// 整个文件的 SHA-256，十六进制小写
+    pub checksum: String,
+}
+
+/// 按顺序传输时顺便计算哈希，不按顺序时在结束时重新读取一遍
+struct SequentialHasher {
+    hasher: Sha256,
+    position: u64,
+    in_order: bool,
+}
+
+impl SequentialHasher {
+    fn new() -> Self {
+        SequentialHasher {
+            hasher: Sha256::new(),
+            position: 0,
+            in_order: true,
+        }
+    }
+
+    fn update(&mut self, offset: u64, data: &[u8]) {
+        if self.in_order && offset == self.position {
+            self.hasher.update(data);
+            self.position += data.len() as u64;
+        } else {
+            self.in_order = false;
+        }
+    }
+
+    fn finish(self, file: &mut File, size: u64) -> std::io::Result<String> {
+        if self.in_order && self.position == size {
+            return Ok(to_hex(&self.hasher.finalize()));
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(file, &mut hasher)?;
+        Ok(to_hex(&hasher.finalize()))
+    }
+}
+
+/// 写入时的临时文件，没有正常结束的写入会在丢弃时删除它
+struct TempFile(Option<PathBuf>);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// 读取或写入过的区间，起点 -> 终点（不含），重叠或相邻的区间会合并
+/// 重试的块不会被重复计入进度；临时文件预先分配了空间，文件大小不能说明每个字节都写入过
+#[derive(Default)]
+struct TransferredRanges(BTreeMap<u64, u64>);
+
+impl TransferredRanges {
+    fn insert(&mut self, mut start: u64, mut end: u64) {
+        if start >= end {
+            return;
+        }
+        let touching: Vec<(u64, u64)> = self
+            .0
+            .range(..=end)
+            .filter(|(_, range_end)| **range_end >= start)
+            .map(|(range_start, range_end)| (*range_start, *range_end))
+            .collect();
+        for (range_start, range_end) in touching {
+            self.0.remove(&range_start);
+            start = start.min(range_start);
+            end = end.max(range_end);
+        }
+        self.0.insert(start, end);
+    }
+
+    /// 传输过的字节数
+    fn len(&self) -> u64 {
+        self.0.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// 是否恰好写满了 [0, size)
+    fn covers(&self, size: u64) -> bool {
+        match size {
+            0 => self.0.is_empty(),
+            _ => self.0.len() == 1 && self.0.get(&0) == Some(&size),
+        }
+    }
+}
+
+struct Transfer {
+    kind: TransferKind,
+    window: String,
+    path: PathBuf,
+    temp: TempFile,
+    file: File,
+    total: Option<u64>,
+    ranges: TransferredRanges,
+    hasher: SequentialHasher,
+}
+
+impl Transfer {
+    fn progress(&self, id: u32) -> TransferProgress {
+        TransferProgress {
+            transfer_id: id,
+            kind: self.kind,
+            path: self.path.to_string_lossy().to_string(),
+            transferred: self.ranges.len(),
+            total: self.total,
+        }
+    }
+
+    fn read_chunk(&mut self, offset: u64, length: u64) -> Result<Vec<u8>, FsError> {
+        let length = length.min(MAX_CHUNK_SIZE);
+        let mut buffer = Vec::with_capacity(length as usize);
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| (&mut self.file).take(length).read_to_end(&mut buffer))
+            .map_err(|e| FsError::from_io(e, &self.path))?;
+        self.hasher.update(offset, &buffer);
+        self.ranges.insert(offset, offset + buffer.len() as u64);
+        Ok(buffer)
+    }
+
+    fn write_chunk(&mut self, offset: u64, data: &[u8]) -> Result<(), FsError> {
+        let temp_path = self.temp.0.as_deref().unwrap_or(&self.path);
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.write_all(data))
+            .map_err(|e| FsError::from_io(e, temp_path))?;
+        self.ranges.insert(offset, offset + data.len() as u64);
+        self.hasher.update(offset, data);
+        Ok(())
+    }
+
+    /// 结束传输，写入时校验每个字节都写入过、大小和 SHA-256，然后替换目标文件
+    fn finish(self, expected_checksum: Option<&str>) -> Result<TransferResult, FsError> {
+        let Transfer {
+            kind,
+            path,
+            mut temp,
+            mut file,
+            total,
+            ranges,
+            hasher,
+            ..
+        } = self;
+        let size = file
+            .metadata()
+            .map_err(|e| FsError::from_io(e, &path))?
+            .len();
+        let checksum = hasher
+            .finish(&mut file, size)
+            .map_err(|e| FsError::from_io(e, &path))?;
+
+        if kind == TransferKind::Write {
+            if let Some(total) = total.filter(|total| *total != size) {
+                return Err(FsError::Other(format!(
+                    "文件大小不一致: {}: 预期 {} 字节，实际 {} 字节",
+                    path.display(),
+                    total,
+                    size
+                )));
+            }
+            if !ranges.covers(size) {
+                return Err(FsError::Other(format!(
+                    "写入不完整: {}: 共 {} 字节，只写入了 {} 字节",
+                    path.display(),
+                    size,
+                    ranges.len()
+                )));
+            }
+            if let Some(expected) =
+                expected_checksum.filter(|expected| !expected.eq_ignore_ascii_case(&checksum))
+            {
+                return Err(FsError::Other(format!(
+                    "校验失败: {}: 预期 {}，实际 {}",
+                    path.display(),
+                    expected,
+                    checksum
+                )));
+            }
+            file.sync_all().map_err(|e| FsError::from_io(e, &path))?;
+            // Windows 上无法重命名仍然打开着的文件
+            drop(file);
+            if let Some(temp_path) = &temp.0 {
+                std::fs::rename(temp_path, &path).map_err(|e| FsError::from_io(e, &path))?;
+                temp.0 = None;
+            }
+        }
+        Ok(TransferResult { size, checksum })
+    }
+}
+
+/// 一次传输，结束后为 None
+/// 正在处理的块持有它的锁，结束传输时会等待这一块处理完
+type SharedTransfer = Arc<Mutex<Option<Transfer>>>;
+
+fn transfer_ended(id: u32) -> FsError {
+    FsError::Other(format!("传输不存在或已结束: {}", id))
+}
+
+fn with_transfer<T>(
+    transfer: &SharedTransfer,
+    id: u32,
+    f: impl FnOnce(&mut Transfer) -> Result<T, FsError>,
+) -> Result<T, FsError> {
+    let mut guard = transfer.lock().unwrap();
+    f(guard.as_mut().ok_or_else(|| transfer_ended(id))?)
+}
+
+/// 正在进行的传输
+#[derive(Default)]
+pub struct TransferRegistry {
+    transfers: Mutex<HashMap<u32, SharedTransfer>>,
+    next_id: AtomicU32,
+}
+
+impl TransferRegistry {
+    fn insert(&self, id: u32, transfer: Transfer) {
+        self.transfers
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(Mutex::new(Some(transfer))));
+    }
+
+    /// 只能访问本窗口发起的传输
+    fn get(&self, id: u32, window: &str) -> Result<SharedTransfer, FsError> {
+        let transfer = self
+            .transfers
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| transfer_ended(id))?;
+        let owned = transfer
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|transfer| transfer.window == window);
+        if owned {
+            Ok(transfer)
+        } else {
+            Err(transfer_ended(id))
+        }
+    }
+
+    fn take(&self, id: u32, window: &str) -> Result<Transfer, FsError> {
+        self.get(id, window)?;
+        let transfer = self
+            .transfers
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| transfer_ended(id))?;
+        let transfer = transfer.lock().unwrap().take();
+        transfer.ok_or_else(|| transfer_ended(id))
+    }
+
+    /// 对传输中的文件执行操作
+    #[cfg(test)]
+    fn with<T>(
+        &self,
+        id: u32,
+        f: impl FnOnce(&mut Transfer) -> Result<T, FsError>,
+    ) -> Result<T, FsError> {
+        with_transfer(&self.get(id, "main")?, id, f)
+    }
+
+    /// 开始读取文件
+    pub fn begin_read(&self, window: String, path: &Path) -> Result<TransferStart, FsError> {
+        let file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+        let metadata = file.metadata().map_err(|e| FsError::from_io(e, path))?;
+        if metadata.is_dir() {
+            return Err(FsError::IsADirectory(path.display().to_string()));
+        }
+        let size = metadata.len();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.insert(
+            id,
+            Transfer {
+                kind: TransferKind::Read,
+                window,
+                path: path.to_path_buf(),
+                temp: TempFile(None),
+                file,
+                total: Some(size),
+                ranges: TransferredRanges::default(),
+                hasher: SequentialHasher::new(),
+            },
+        );
+        Ok(TransferStart {
+            transfer_id: id,
+            size: Some(size),
+        })
+    }
+
+    /// 开始写入文件，先写到同目录下的临时文件
+    pub fn begin_write(
+        &self,
+        window: String,
+        path: &Path,
+        size: Option<u64>,
+    ) -> Result<TransferStart, FsError> {
+        if path.is_dir() {
+            return Err(FsError::IsADirectory(path.display().to_string()));
+        }
+        let name = path
+            .file_name()
+            .ok_or_else(|| FsError::Other(format!("无效的文件路径: {}", path.display())))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}-{}.part", std::process::id(), id));
+        let temp_path = path.with_file_name(temp_name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .map_err(|e| FsError::from_io(e, &temp_path))?;
+        let temp = TempFile(Some(temp_path.clone()));
+        if let Some(size) = size {
+            // 预先分配空间，磁盘不足时尽早失败
+            file.set_len(size)
+                .map_err(|e| FsError::from_io(e, &temp_path))?;
+        }
+        self.insert(
+            id,
+            Transfer {
+                kind: TransferKind::Write,
+                window,
+                path: path.to_path_buf(),
+                temp,
+                file,
+                total: size,
+                ranges: TransferredRanges::default(),
+                hasher: SequentialHasher::new(),
+            },
+        );
+        Ok(TransferStart {
+            transfer_id: id,
+            size,
+        })
+    }
+
+    /// 窗口关闭时取消它的所有传输
+    pub fn abort_window(&self, window: &str) {
+        self.transfers.lock().unwrap().retain(|_, transfer| {
+            transfer
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|transfer| transfer.window != window)
+        });
+    }
+}
+
+/// 开始分块读取文件，返回传输 ID 和文件大小
+#[tauri::command]
+pub fn begin_file_read<R: Runtime>(
+    window: Window<R>,
+    scope: State<'_, FsScope>,
+    registry: State<'_, TransferRegistry>,
+    path: String,
+) -> Result<TransferStart, FsError> {
+    scope.check(Path::new(&path))?;
+    registry.begin_read(window.label().to_string(), Path::new(&path))
+}
+
+/// 读取一块，以二进制返回
+/// 到达文件末尾时返回的数据比 length 短
+#[tauri::command]
+pub async fn read_file_chunk<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    registry: State<'_, TransferRegistry>,
+    transfer_id: u32,
+    offset: u64,
+    length: u64,
+) -> Result<Response, FsError> {
+    let transfer = registry.get(transfer_id, window.label())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        with_transfer(&transfer, transfer_id, |transfer| {
+            let data = transfer.read_chunk(offset, length)?;
+            let _ = app.emit_to(
+                transfer.window.as_str(),
+                FS_TRANSFER_PROGRESS_EVENT,
+                transfer.progress(transfer_id),
+            );
+            Ok(Response::new(data))
+        })
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("读取失败: {}", e)))?
+}
+
+/// 开始分块写入文件
+/// size: 文件的总大小，传入后会预先分配空间，并在结束时校验
+#[tauri::command]
+pub fn begin_file_write<R: Runtime>(
+    window: Window<R>,
+    scope: State<'_, FsScope>,
+    registry: State<'_, TransferRegistry>,
+    path: String,
+    size: Option<u64>,
+) -> Result<TransferStart, FsError> {
+    scope.check(Path::new(&path))?;
+    registry.begin_write(window.label().to_string(), Path::new(&path), size)
+}
+
+fn header<T: std::str::FromStr>(request: &Request<'_>, name: &str) -> Result<T, FsError> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| FsError::Other(format!("缺少请求头: {}", name)))
+}
+
+/// 写入一块
+/// 请求体是二进制数据，传输 ID 和偏移量放在请求头 `transfer-id`、`offset` 中
+#[tauri::command]
+pub async fn write_file_chunk<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    registry: State<'_, TransferRegistry>,
+    request: Request<'_>,
+) -> Result<(), FsError> {
+    let transfer_id: u32 = header(&request, "transfer-id")?;
+    let offset: u64 = header(&request, "offset")?;
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err(FsError::Other("请求体必须是二进制数据".to_string()));
+    };
+    if data.len() as u64 > MAX_CHUNK_SIZE {
+        return Err(FsError::Other(format!(
+            "单块不能超过 {} 字节",
+            MAX_CHUNK_SIZE
+        )));
+    }
+    let data = data.clone();
+    let transfer = registry.get(transfer_id, window.label())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        with_transfer(&transfer, transfer_id, |transfer| {
+            transfer.write_chunk(offset, &data)?;
+            let _ = app.emit_to(
+                transfer.window.as_str(),
+                FS_TRANSFER_PROGRESS_EVENT,
+                transfer.progress(transfer_id),
+            );
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e| FsError::Other(format!("写入失败: {}", e)))?
+}
+
+/// 结束传输，返回文件大小和 SHA-256
+/// 写入时如果传入 checksum，不一致则放弃写入，目标文件保持不变
+#[tauri::command]
+pub async fn finish_file_transfer<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, TransferRegistry>,
+    transfer_id: u32,
+    checksum: Option<String>,
+) -> Result<TransferResult, FsError> {
+    let transfer = registry.take(transfer_id, window.label())?;
+    tauri::async_runtime::spawn_blocking(move || transfer.finish(checksum.as_deref()))
+        .await
+        .map_err(|e| FsError::Other(format!("传输失败: {}", e)))?
+}
+
+/// 取消传输，写入时删除临时文件
+/// 返回传输是否存在
+#[tauri::command]
+pub fn abort_file_transfer<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, TransferRegistry>,
+    transfer_id: u32,
+) -> bool {
+    registry.take(transfer_id, window.label()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256(data: &[u8]) -> String {
+        to_hex(&Sha256::digest(data))
+    }
+
+    #[test]
+    fn transfers_files_in_chunks_with_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("image.png");
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &content).unwrap();
+        let registry = TransferRegistry::default();
+
+        let read = registry.begin_read("main".into(), &source).unwrap();
+        assert_eq!(read.size, Some(10_000));
+        let mut received = Vec::new();
+        while received.len() < content.len() {
+            let offset = received.len() as u64;
+            let chunk = registry
+                .with(read.transfer_id, |t| t.read_chunk(offset, 4096))
+                .unwrap();
+            received.extend(chunk);
+        }
+        let result = registry
+            .take(read.transfer_id, "main")
+            .unwrap()
+            .finish(None)
+            .unwrap();
+        assert_eq!(received, content);
+        assert_eq!(result.checksum, sha256(&content));
+
+        // 不按顺序写入，结束时重新计算哈希
+        let target = dir.path().join("copy.png");
+        std::fs::write(&target, b"old").unwrap();
+        let write = registry
+            .begin_write("main".into(), &target, Some(10_000))
+            .unwrap();
+        let id = write.transfer_id;
+        registry
+            .with(id, |t| t.write_chunk(5000, &content[5000..]))
+            .unwrap();
+        registry
+            .with(id, |t| t.write_chunk(0, &content[..5000]))
+            .unwrap();
+        assert!(registry
+            .take(id, "main")
+            .unwrap()
+            .finish(Some("0000"))
+            .is_err());
+        assert_eq!(std::fs::read(&target).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        assert!(registry.with(id, |_| Ok(())).is_err());
+
+        // 缺少中间的一块时不能把预分配的 0 当作内容提交
+        let write = registry
+            .begin_write("main".into(), &target, Some(10_000))
+            .unwrap();
+        let id = write.transfer_id;
+        registry
+            .with(id, |t| t.write_chunk(0, &content[..4000]))
+            .unwrap();
+        registry
+            .with(id, |t| t.write_chunk(6000, &content[6000..]))
+            .unwrap();
+        let error = registry.take(id, "main").unwrap().finish(None).unwrap_err();
+        assert!(error.to_string().contains("8000"), "{}", error);
+        assert_eq!(std::fs::read(&target).unwrap(), b"old");
+
+        let write = registry.begin_write("main".into(), &target, None).unwrap();
+        registry
+            .with(write.transfer_id, |t| t.write_chunk(0, &content))
+            .unwrap();
+        let result = registry
+            .take(write.transfer_id, "main")
+            .unwrap()
+            .finish(Some(&sha256(&content).to_uppercase()))
+            .unwrap();
+        assert_eq!(result.size, 10_000);
+        assert_eq!(std::fs::read(&target).unwrap(), content);
+    }
+
+    #[test]
+    fn counts_retried_chunks_once_and_checks_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.bin");
+        std::fs::write(&source, vec![1u8; 1000]).unwrap();
+        let registry = TransferRegistry::default();
+
+        let read = registry.begin_read("main".into(), &source).unwrap();
+        let id = read.transfer_id;
+        for offset in [0, 0, 500, 400] {
+            registry.with(id, |t| t.read_chunk(offset, 500)).unwrap();
+        }
+        let progress = registry.with(id, |t| Ok(t.progress(id))).unwrap();
+        assert_eq!(progress.transferred, 1000);
+        assert_eq!(progress.total, Some(1000));
+
+        assert!(registry.get(id, "other").is_err());
+        assert!(registry.take(id, "other").is_err());
+        assert!(registry.take(id, "main").is_ok());
+
+        let write = registry
+            .begin_write("main".into(), &dir.path().join("b.bin"), Some(1000))
+            .unwrap();
+        let id = write.transfer_id;
+        for offset in [0, 0, 200] {
+            registry
+                .with(id, |t| t.write_chunk(offset, &[2; 300]))
+                .unwrap();
+        }
+        let progress = registry.with(id, |t| Ok(t.progress(id))).unwrap();
+        assert_eq!(progress.transferred, 500);
+        registry.abort_window("main");
+        assert!(registry.get(id, "main").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! 回收站
+//!
+//! Linux 上按照 freedesktop.org 的 Trash 规范实现：
+//! https://specifications.freedesktop.org/trash-spec/latest/
+//! - 与家目录在同一文件系统上的文件移动到 $XDG_DATA_HOME/Trash
+//! - 其他文件系统上的文件移动到该文件系统根目录下的 .Trash-$uid
+//!
+//! macOS 和 Windows 上交给 trash crate 调用系统的回收站。
+
+use std::path::{Path, PathBuf};
+
+use super::fs::FsError;
+
+/// 把文件或文件夹移入回收站
+/// Linux 上返回它在回收站中的位置
+#[cfg(target_os = "linux")]
+pub fn move_to_trash(path: &Path) -> Result<Option<PathBuf>, FsError> {
+    xdg::move_to_trash(path).map(Some)
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn move_to_trash(path: &Path) -> Result<Option<PathBuf>, FsError> {
+    ::trash::delete(path)
+        .map(|_| None)
+        .map_err(|e| FsError::Other(format!("无法移入回收站: {}: {}", path.display(), e)))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn move_to_trash(path: &Path) -> Result<Option<PathBuf>, FsError> {
+    Err(FsError::Other(format!(
+        "当前平台不支持回收站: {}",
+        path.display()
+    )))
+}
+
+#[cfg(target_os = "linux")]
+pub mod xdg {
+    use super::*;
+    use crate::cmd::fs::numbered_name;
+    use std::ffi::OsString;
+    use std::io::Write;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    /// 家目录回收站：$XDG_DATA_HOME/Trash，未设置时为 ~/.local/share/Trash
+    pub fn home_trash_dir() -> Result<PathBuf, FsError> {
+        home_trash_dir_from(std::env::var_os("XDG_DATA_HOME"), std::env::var_os("HOME"))
+    }
+
+    fn home_trash_dir_from(
+        data_home: Option<OsString>,
+        home: Option<OsString>,
+    ) -> Result<PathBuf, FsError> {
+        let data_home = data_home
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| home.map(|home| PathBuf::from(home).join(".local/share")))
+            .ok_or_else(|| FsError::Other("无法确定回收站位置：未设置 HOME".to_string()))?;
+        Ok(data_home.join("Trash"))
+    }
+
+    /// 路径中除了不需要转义的字符以外都按 URL 编码
+    fn encode_path(path: &Path) -> String {
+        let mut encoded = String::new();
+        for byte in path.as_os_str().as_bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                    encoded.push(*byte as char)
+                }
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        encoded
+    }
+
+    /// 本地时间，格式为 YYYY-MM-DDThh:mm:ss
+    fn deletion_date() -> String {
+        let now = unsafe { libc::time(std::ptr::null_mut()) };
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        unsafe { libc::localtime_r(&now, &mut tm) };
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+
+    fn create_private_dir(path: &Path) -> Result<(), FsError> {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)
+            .map_err(|e| FsError::from_io(e, path))
+    }
+
+    /// 把 path 移动到指定的回收站目录
+    /// info_base 不为空时，.trashinfo 中记录相对它的路径（用于挂载点下的回收站）
+    pub fn move_into(
+        path: &Path,
+        trash_dir: &Path,
+        info_base: Option<&Path>,
+    ) -> Result<PathBuf, FsError> {
+        let path = std::path::absolute(path).map_err(|e| FsError::from_io(e, path))?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| FsError::Other(format!("无法移入回收站: {}", path.display())))?;
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+        create_private_dir(&files_dir)?;
+        create_private_dir(&info_dir)?;
+
+        let recorded = match info_base {
+            Some(base) => path.strip_prefix(base).unwrap_or(&path).to_path_buf(),
+            None => path.clone(),
+        };
+        let info_content = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&recorded),
+            deletion_date()
+        );
+
+        for n in 1.. {
+            let candidate = numbered_name(name, n);
+            let target = files_dir.join(&candidate);
+            let mut info_name = candidate.clone();
+            info_name.push(".trashinfo");
+            let info_path = info_dir.join(info_name);
+
+            // 先以独占方式创建 .trashinfo，占住这个名字，避免与其他程序冲突
+            let mut info = match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(FsError::from_io(e, &info_path)),
+            };
+            if target.symlink_metadata().is_ok() {
+                drop(info);
+                let _ = std::fs::remove_file(&info_path);
+                continue;
+            }
+            let written = info
+                .write_all(info_content.as_bytes())
+                .and_then(|_| info.sync_all());
+            if let Err(e) = written {
+                let _ = std::fs::remove_file(&info_path);
+                return Err(FsError::from_io(e, &info_path));
+            }
+            if let Err(e) = std::fs::rename(&path, &target) {
+                let _ = std::fs::remove_file(&info_path);
+                return Err(FsError::from_io(e, &path));
+            }
+            return Ok(target);
+        }
+        unreachable!()
+    }
+
+    /// 找到 path 所在文件系统的挂载点
+    fn mount_point(path: &Path) -> Option<PathBuf> {
+        let dev = path.symlink_metadata().ok()?.dev();
+        let mut top = path.to_path_buf();
+        while let Some(parent) = top.parent() {
+            if parent.metadata().ok()?.dev() != dev {
+                break;
+            }
+            top = parent.to_path_buf();
+        }
+        Some(top)
+    }
+
+    /// 移入回收站
+    /// 与家目录回收站不在同一文件系统时，使用挂载点下的 .Trash-$uid
+    pub fn move_to_trash(path: &Path) -> Result<PathBuf, FsError> {
+        move_to_trash_with_home(path, &home_trash_dir()?)
+    }
+
+    fn move_to_trash_with_home(path: &Path, home_trash: &Path) -> Result<PathBuf, FsError> {
+        let error = match move_into(path, home_trash, None) {
+            Ok(target) => return Ok(target),
+            Err(error) => error,
+        };
+        let crosses_devices = {
+            let home_dev = home_trash.metadata().map(|m| m.dev()).ok();
+            let path_dev = path.symlink_metadata().map(|m| m.dev()).ok();
+            home_dev.is_some() && path_dev.is_some() && home_dev != path_dev
+        };
+        if !crosses_devices {
+            return Err(error);
+        }
+        let absolute = std::path::absolute(path).map_err(|e| FsError::from_io(e, path))?;
+        let top = mount_point(&absolute)
+            .ok_or_else(|| FsError::Other(format!("无法找到挂载点: {}", path.display())))?;
+        let uid = unsafe { libc::getuid() };
+        move_into(&absolute, &top.join(format!(".Trash-{}", uid)), Some(&top))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn moves_files_into_trash_with_info() {
+            let data_home = tempfile::tempdir().unwrap();
+            let trash = data_home.path().join("Trash");
+            let work = tempfile::tempdir_in(data_home.path()).unwrap();
+            let file = work.path().join("图 1.prg");
+
+            assert_eq!(
+                home_trash_dir_from(Some(data_home.path().into()), None).unwrap(),
+                trash
+            );
+            assert_eq!(
+                home_trash_dir_from(Some("relative".into()), Some("/home/user".into())).unwrap(),
+                Path::new("/home/user/.local/share/Trash")
+            );
+
+            std::fs::write(&file, b"first").unwrap();
+            let first = move_to_trash_with_home(&file, &trash).unwrap();
+            std::fs::write(&file, b"second").unwrap();
+            let second = move_into(&file, &trash, None).unwrap();
+
+            assert!(!file.exists());
+            assert_eq!(first, trash.join("files/图 1.prg"));
+            assert_eq!(second, trash.join("files/图 1 (2).prg"));
+            assert_eq!(std::fs::read(&second).unwrap(), b"second");
+
+            let info = std::fs::read_to_string(trash.join("info/图 1.prg.trashinfo")).unwrap();
+            assert!(info.starts_with("[Trash Info]\n"));
+            assert!(info.contains(&format!(
+                "Path={}\n",
+                encode_path(&work.path().join("图 1.prg"))
+            )));
+            assert!(info.contains("%E5%9B%BE%201.prg"));
+            assert!(info.contains("DeletionDate="));
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! 文件系统监听
+//!
+//! 前端通过 watch_path 监听打开的 .prg、附件或者文件夹，
+//! 变化经过防抖合并后以 `fs-watch` 事件发送给发起监听的窗口。
+//!
+//! 监听单个文件时实际监听的是它所在的文件夹：inotify 等按 inode 监听，
+//! 原子保存（写入临时文件再重命名覆盖）之后原来的监听就失效了。
+
+use notify::event::{ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime, State, Window};
+
+use super::scope::FsScope;
+
+/// 发送给前端的事件名
+pub const FS_WATCH_EVENT: &str = "fs-watch";
+
+/// 默认的防抖时间
+const DEFAULT_DEBOUNCE_MS: u64 = 300;
+
+/// 变化一直不停时，最多等待防抖时间的这么多倍就发送一次
+const MAX_LATENCY_FACTOR: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+/// 一次变化
+/// 重命名时 from 为原路径，path 为新路径
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsChange {
+    pub kind: FsChangeKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+/// `fs-watch` 事件的内容，一次防抖周期内的所有变化
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsWatchEvent {
+    pub watch_id: u32,
+    pub changes: Vec<FsChange>,
+}
+
+/// 一个防抖周期内收集到的变化，按路径合并
+#[derive(Default)]
+struct PendingChanges {
+    order: Vec<PathBuf>,
+    changes: HashMap<PathBuf, (FsChangeKind, Option<PathBuf>)>,
+}
+
+impl PendingChanges {
+    fn push(&mut self, path: PathBuf, kind: FsChangeKind, from: Option<PathBuf>) {
+        let merged = match (self.changes.get(&path).map(|(kind, _)| *kind), kind) {
+            (None, kind) => Some(kind),
+            // 新建后又修改，仍然是新建
+            (Some(FsChangeKind::Created), FsChangeKind::Modified) => Some(FsChangeKind::Created),
+            // 新建后又删除，相当于什么都没发生
+            (Some(FsChangeKind::Created), FsChangeKind::Removed) => None,
+            // 删除后又新建（很多编辑器保存时就是这样），视为修改
+            (Some(FsChangeKind::Removed), FsChangeKind::Created) => Some(FsChangeKind::Modified),
+            (Some(FsChangeKind::Renamed), FsChangeKind::Modified) => Some(FsChangeKind::Renamed),
+            (Some(_), kind) => Some(kind),
+        };
+        match merged {
+            Some(kind) => {
+                let from = match self.changes.get(&path) {
+                    Some((FsChangeKind::Renamed, previous_from))
+                        if kind == FsChangeKind::Renamed =>
+                    {
+                        from.or_else(|| previous_from.clone())
+                    }
+                    _ => from,
+                };
+                if !self.changes.contains_key(&path) {
+                    self.order.push(path.clone());
+                }
+                self.changes.insert(path, (kind, from));
+            }
+            None => {
+                self.changes.remove(&path);
+                self.order.retain(|p| p != &path);
+            }
+        }
+    }
+
+    fn push_event(&mut self, event: Event) {
+        let mut paths = event.paths.into_iter();
+        match event.kind {
+            EventKind::Create(_) => paths.for_each(|p| self.push(p, FsChangeKind::Created, None)),
+            EventKind::Remove(_) => paths.for_each(|p| self.push(p, FsChangeKind::Removed, None)),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.push(from.clone(), FsChangeKind::Removed, None);
+                    self.push(to, FsChangeKind::Renamed, Some(from));
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                paths.for_each(|p| self.push(p, FsChangeKind::Removed, None))
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                paths.for_each(|p| self.push(p, FsChangeKind::Created, None))
+            }
+            EventKind::Modify(_) | EventKind::Any => {
+                paths.for_each(|p| self.push(p, FsChangeKind::Modified, None))
+            }
+            EventKind::Access(_) | EventKind::Other => {}
+        }
+    }
+
+    fn take(&mut self) -> Vec<FsChange> {
+        let mut changes = std::mem::take(&mut self.changes);
+        std::mem::take(&mut self.order)
+            .into_iter()
+            .filter_map(|path| {
+                let (kind, from) = changes.remove(&path)?;
+                // 重命名时原路径的“删除”已经由 from 表示了
+                if kind == FsChangeKind::Removed
+                    && changes
+                        .values()
+                        .any(|(_, from)| from.as_deref() == Some(path.as_path()))
+                {
+                    return None;
+                }
+                Some(FsChange {
+                    kind,
+                    path: path.to_string_lossy().to_string(),
+                    from: from.map(|p| p.to_string_lossy().to_string()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 监听单个文件时只保留与它有关的事件，路径统一为 file
+/// 部分平台报告的是解析过符号链接的路径，所以同时与 real 比较
+/// 其他文件重命名覆盖它（原子保存）视为修改，它被重命名走视为删除
+fn retain_file_event(mut event: Event, file: &Path, real: &Path) -> Option<Event> {
+    let is_file = |p: &PathBuf| p == file || p == real;
+    if !event.paths.iter().any(is_file) {
+        return None;
+    }
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            event.kind = if event.paths.get(1).is_some_and(is_file) {
+                EventKind::Modify(ModifyKind::Any)
+            } else {
+                EventKind::Remove(RemoveKind::Any)
+            };
+        }
+        // 只报告了新路径的重命名（Windows 等），新路径就是监听的文件
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            event.kind = EventKind::Modify(ModifyKind::Any);
+        }
+        _ => {}
+    }
+    event.paths = vec![file.to_path_buf()];
+    Some(event)
+}
+
+/// 创建监听，事件交给 on_event
+/// 文件夹直接监听，文件则监听它所在的文件夹并过滤出它的事件
+fn start_watcher(
+    path: &Path,
+    recursive: bool,
+    on_event: impl Fn(Event) + Send + 'static,
+) -> Result<RecommendedWatcher, String> {
+    let file = match path.parent() {
+        Some(parent) if !path.is_dir() => Some((path.to_path_buf(), parent)),
+        _ => None,
+    };
+    let filter = file.as_ref().map(|(file, _)| {
+        let real = file.canonicalize().unwrap_or_else(|_| file.clone());
+        (file.clone(), real)
+    });
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        let event = match &filter {
+            Some((file, real)) => retain_file_event(event, file, real),
+            None => Some(event),
+        };
+        if let Some(event) = event {
+            on_event(event);
+        }
+    })
+    .map_err(|e| format!("无法创建文件监听: {}", e))?;
+    let (target, mode) = match &file {
+        Some((_, parent)) => (*parent, RecursiveMode::NonRecursive),
+        None if recursive => (path, RecursiveMode::Recursive),
+        None => (path, RecursiveMode::NonRecursive),
+    };
+    watcher
+        .watch(target, mode)
+        .map_err(|e| format!("无法监听 {}: {}", path.display(), e))?;
+    Ok(watcher)
+}
+
+struct WatchEntry {
+    /// 丢弃 watcher 即停止监听，防抖线程随之退出
+    _watcher: RecommendedWatcher,
+    window: String,
+}
+
+/// 所有窗口的监听
+#[derive(Default)]
+pub struct WatcherRegistry {
+    watchers: Mutex<HashMap<u32, WatchEntry>>,
+    next_id: AtomicU32,
+}
+
+impl WatcherRegistry {
+    fn watch<R: Runtime>(
+        &self,
+        app: AppHandle<R>,
+        window: String,
+        path: &Path,
+        recursive: bool,
+        debounce: Duration,
+    ) -> Result<u32, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel::<Event>();
+        let watcher = start_watcher(path, recursive, move |event| {
+            let _ = tx.send(event);
+        })?;
+
+        let target = window.clone();
+        std::thread::spawn(move || {
+            debounce_loop(rx, debounce, |changes| {
+                let _ = app.emit_to(
+                    target.as_str(),
+                    FS_WATCH_EVENT,
+                    FsWatchEvent {
+                        watch_id: id,
+                        changes,
+                    },
+                );
+            })
+        });
+
+        self.watchers.lock().unwrap().insert(
+            id,
+            WatchEntry {
+                _watcher: watcher,
+                window,
+            },
+        );
+        Ok(id)
+    }
+
+    /// 只能取消本窗口发起的监听
+    fn unwatch(&self, id: u32, window: &str) -> bool {
+        let mut watchers = self.watchers.lock().unwrap();
+        if watchers
+            .get(&id)
+            .is_some_and(|entry| entry.window == window)
+        {
+            watchers.remove(&id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 窗口关闭时清理它的所有监听
+    pub fn unwatch_window(&self, window: &str) {
+        self.watchers
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.window != window);
+    }
+}
+
+/// first 是这一批的第一个事件，now 是最近的事件
+fn next_deadline(first: Instant, now: Instant, debounce: Duration) -> Instant {
+    (now + debounce).min(first + debounce * MAX_LATENCY_FACTOR)
+}
+
+/// 收到第一个事件后开始计时，debounce 时间内没有新事件时把合并后的变化交给 emit
+/// 文件一直在变化时，距第一个事件超过 MAX_LATENCY_FACTOR 倍的防抖时间也会发送
+/// watcher 被丢弃后 channel 断开，线程退出
+fn debounce_loop(rx: mpsc::Receiver<Event>, debounce: Duration, emit: impl Fn(Vec<FsChange>)) {
+    let mut pending = PendingChanges::default();
+    while let Ok(event) = rx.recv() {
+        pending.push_event(event);
+        let first = Instant::now();
+        let mut deadline = next_deadline(first, first, debounce);
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) => {
+                    pending.push_event(event);
+                    deadline = next_deadline(first, Instant::now(), debounce);
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        let changes = pending.take();
+        if !changes.is_empty() {
+            emit(changes);
+        }
+    }
+}
+
+/// 监听文件或文件夹，返回监听 ID
+/// 变化以 `fs-watch` 事件发送给调用的窗口
+/// recursive: 是否监听子文件夹，默认为 true
+/// debounce_ms: 防抖时间，默认为 300 毫秒
+#[tauri::command]
+pub fn watch_path<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    registry: State<'_, WatcherRegistry>,
+    scope: State<'_, FsScope>,
+    path: String,
+    recursive: Option<bool>,
+    debounce_ms: Option<u64>,
+) -> Result<u32, String> {
+    scope.check(Path::new(&path))?;
+    registry.watch(
+        app,
+        window.label().to_string(),
+        Path::new(&path),
+        recursive.unwrap_or(true),
+        Duration::from_millis(debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS)),
+    )
+}
+
+/// 取消本窗口发起的监听
+/// 返回是否存在该监听
+#[tauri::command]
+pub fn unwatch_path<R: Runtime>(
+    window: Window<R>,
+    registry: State<'_, WatcherRegistry>,
+    id: u32,
+) -> bool {
+    registry.unwatch(id, window.label())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange};
+
+    fn event(kind: EventKind, paths: &[&str]) -> Event {
+        Event {
+            kind,
+            paths: paths.iter().map(PathBuf::from).collect(),
+            attrs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn merges_bursts_by_path() {
+        let mut pending = PendingChanges::default();
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        pending.push_event(event(EventKind::Create(CreateKind::File), &["/a"]));
+        pending.push_event(event(modify, &["/a"]));
+        pending.push_event(event(EventKind::Remove(RemoveKind::File), &["/b"]));
+        pending.push_event(event(EventKind::Create(CreateKind::File), &["/b"]));
+        pending.push_event(event(EventKind::Create(CreateKind::File), &["/c"]));
+        pending.push_event(event(EventKind::Remove(RemoveKind::File), &["/c"]));
+
+        let changes = pending.take();
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| (c.kind, c.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (FsChangeKind::Created, "/a"),
+                (FsChangeKind::Modified, "/b")
+            ]
+        );
+        assert!(pending.take().is_empty());
+    }
+
+    #[test]
+    fn flushes_a_steady_stream_of_changes() {
+        let debounce = Duration::from_millis(40);
+        let first = Instant::now();
+        // 事件之间的间隔比防抖时间短时，期限随事件推后
+        assert_eq!(
+            next_deadline(first, first + Duration::from_millis(10), debounce),
+            first + Duration::from_millis(50)
+        );
+        // 但不会超过第一个事件之后 MAX_LATENCY_FACTOR 倍的防抖时间
+        let mut deadline = first;
+        for i in 1..=60 {
+            deadline = next_deadline(first, first + Duration::from_millis(10 * i), debounce);
+        }
+        assert_eq!(deadline, first + debounce * MAX_LATENCY_FACTOR);
+    }
+
+    #[test]
+    fn reports_renames_with_source_path() {
+        let mut pending = PendingChanges::default();
+        pending.push_event(event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/old.prg", "/new.prg"],
+        ));
+        assert_eq!(
+            pending.take(),
+            vec![FsChange {
+                kind: FsChangeKind::Renamed,
+                path: "/new.prg".to_string(),
+                from: Some("/old.prg".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn keeps_watching_a_file_replaced_by_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.prg");
+        std::fs::write(&file, b"1").unwrap();
+        std::fs::write(dir.path().join("other.md"), b"").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let _watcher = start_watcher(&file, true, move |event| {
+            let _ = tx.send(event);
+        })
+        .unwrap();
+
+        // 事件什么时候到达由系统决定，只限制总的等待时间
+        let deadline = Instant::now() + Duration::from_secs(10);
+        for round in 0..3 {
+            // 与 write_file_atomic 一样：写入临时文件再重命名覆盖
+            let temp = dir.path().join(format!(".a.prg.{}.tmp", round));
+            std::fs::write(&temp, round.to_string()).unwrap();
+            std::fs::rename(&temp, &file).unwrap();
+            std::fs::write(dir.path().join("other.md"), round.to_string()).unwrap();
+
+            let event = rx
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .expect("the file should still be watched");
+            assert_eq!(event.paths, vec![file.clone()]);
+        }
+        while let Ok(event) = rx.try_recv() {
+            assert_eq!(event.paths, vec![file.clone()]);
+        }
+    }
+
+    #[test]
+    fn reports_renames_over_a_watched_file_as_modified() {
+        let file = Path::new("/dir/a.prg");
+        let rename = |mode, paths: &[&str]| {
+            let mut pending = PendingChanges::default();
+            if let Some(event) = retain_file_event(
+                event(EventKind::Modify(ModifyKind::Name(mode)), paths),
+                file,
+                file,
+            ) {
+                pending.push_event(event);
+            }
+            pending.take()
+        };
+        let change = |kind| {
+            vec![FsChange {
+                kind,
+                path: "/dir/a.prg".to_string(),
+                from: None,
+            }]
+        };
+
+        assert_eq!(
+            rename(RenameMode::Both, &["/dir/.a.tmp", "/dir/a.prg"]),
+            change(FsChangeKind::Modified)
+        );
+        assert_eq!(
+            rename(RenameMode::To, &["/dir/a.prg"]),
+            change(FsChangeKind::Modified)
+        );
+        assert_eq!(
+            rename(RenameMode::Both, &["/dir/a.prg", "/dir/b.prg"]),
+            change(FsChangeKind::Removed)
+        );
+        assert_eq!(
+            rename(RenameMode::From, &["/dir/a.prg"]),
+            change(FsChangeKind::Removed)
+        );
+        assert!(rename(RenameMode::Both, &["/dir/.b.tmp", "/dir/b.prg"]).is_empty());
+    }
+
+    #[test]
+    fn only_the_owning_window_can_unwatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = WatcherRegistry::default();
+        let watcher = start_watcher(dir.path(), false, |_| {}).unwrap();
+        registry.watchers.lock().unwrap().insert(
+            7,
+            WatchEntry {
+                _watcher: watcher,
+                window: "main".to_string(),
+            },
+        );
+        assert!(!registry.unwatch(7, "other"));
+        assert!(registry.unwatch(7, "main"));
+        assert!(!registry.unwatch(7, "main"));
+    }
+}
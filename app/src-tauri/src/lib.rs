@@ -1,3 +1,5 @@
+#[cfg(desktop)]
+mod cli;
 mod cmd;
 
 use std::sync::{Mutex, OnceLock};
@@ -10,10 +12,18 @@ pub static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
 #[derive(Default)]
 struct PendingOpenFiles(Mutex<Vec<String>>);
 
+/// 通过系统打开的文件同时允许 cmd::fs 访问
 #[tauri::command]
-fn take_pending_open_files(state: State<PendingOpenFiles>) -> Vec<String> {
+fn take_pending_open_files(
+    state: State<PendingOpenFiles>,
+    scope: State<cmd::scope::FsScope>,
+) -> Vec<String> {
     let mut guard = state.0.lock().unwrap();
-    std::mem::take(&mut *guard)
+    let files = std::mem::take(&mut *guard);
+    for file in &files {
+        let _ = scope.allow(Path::new(file), cmd::scope::ScopeKind::Project);
+    }
+    files
 }
 
 #[tauri::command]
@@ -33,6 +43,12 @@ fn exit(code: i32) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 命令行子命令不需要窗口，在创建窗口、初始化 CEF 之前执行并直接退出
+    #[cfg(desktop)]
+    if let Some(code) = cli::run(std::env::args_os()) {
+        std::process::exit(code);
+    }
+
     // 在 Linux 上禁用 DMA-BUF 渲染器
     // 否则无法在 Linux 上运行
     // 相同的bug: https://github.com/tauri-apps/tauri/issues/10702
@@ -53,9 +69,19 @@ pub fn run() {
     #[cfg(not(target_os = "linux"))]
     let builder = tauri::Builder::default();
 
+    // 只在桌面端编译的命令模块，状态也只在桌面端注册
+    #[cfg(desktop)]
+    let builder = builder
+        .manage(cmd::mcp::McpStdioManager::default())
+        .manage(cmd::watch::WatcherRegistry::default())
+        .manage(cmd::search::SearchIndexState::default())
+        .manage(cmd::pty::PtyRegistry::default());
+
     builder
         .manage(PendingOpenFiles::default())
-        .manage(cmd::mcp::McpStdioManager::default())
+        .manage(cmd::task::TaskRegistry::default())
+        .manage(cmd::transfer::TransferRegistry::default())
+        .manage(cmd::shell::ProcessRegistry::default())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_http::init())
@@ -65,6 +91,9 @@ pub fn run() {
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .setup(|app| {
+            app.manage(cmd::scope::FsScope::load(app.handle()));
+            cmd::scope::track_picked_paths(app.handle());
+            app.manage(cmd::shell_policy::ShellPolicy::load(app.handle()));
             #[cfg(debug_assertions)]
             {
                 app.handle().plugin(tauri_plugin_devtools::init())?;
@@ -86,6 +115,31 @@ pub fn run() {
             }
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // 拖入窗口的文件同样允许 cmd::fs 访问，例如拖入图片时分块读取
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                let scope = window.state::<cmd::scope::FsScope>();
+                for path in paths {
+                    let _ = scope.allow(path, cmd::scope::ScopeKind::Project);
+                }
+            }
+            if let tauri::WindowEvent::Destroyed = event {
+                #[cfg(desktop)]
+                window
+                    .state::<cmd::watch::WatcherRegistry>()
+                    .unwatch_window(window.label());
+                window
+                    .state::<cmd::transfer::TransferRegistry>()
+                    .abort_window(window.label());
+                window
+                    .state::<cmd::shell::ProcessRegistry>()
+                    .kill_window(window.label());
+                #[cfg(desktop)]
+                window
+                    .state::<cmd::pty::PtyRegistry>()
+                    .kill_window(window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             cmd::device::get_device_id,
             write_stdout,
@@ -98,17 +152,71 @@ pub fn run() {
             cmd::paddle::paddleocr_vl_1_6_model_exists,
             #[cfg(desktop)]
             cmd::paddle::paddleocr_vl_1_6_generate,
+            cmd::task::cancel_task,
             cmd::fs::read_folder_structure,
             cmd::fs::exists,
             cmd::fs::read_folder,
             cmd::fs::read_folder_recursive,
+            cmd::fs::scan_folder_recursive,
             cmd::fs::delete_file,
+            cmd::fs::delete_path,
+            cmd::fs::copy_path,
+            cmd::fs::move_path,
             cmd::fs::read_text_file,
             cmd::fs::read_file_base64,
             cmd::fs::write_text_file,
             cmd::fs::write_file_base64,
             cmd::fs::create_folder,
+            cmd::transfer::begin_file_read,
+            cmd::transfer::read_file_chunk,
+            cmd::transfer::begin_file_write,
+            cmd::transfer::write_file_chunk,
+            cmd::transfer::finish_file_transfer,
+            cmd::transfer::abort_file_transfer,
+            cmd::hash::hash_file,
+            cmd::hash::hash_bytes,
+            cmd::hash::find_duplicate_attachments,
+            cmd::hash::find_duplicate_attachments_in_folder,
+            cmd::scope::get_fs_scopes,
+            #[cfg(desktop)]
+            cmd::scope::add_fs_scope,
+            cmd::scope::remove_fs_scope,
+            cmd::prg::open_prg,
+            cmd::prg::read_prg_attachment,
+            cmd::prg::read_prg_thumbnail,
+            cmd::prg::validate_prg,
+            cmd::prg::save_prg,
+            cmd::repair::verify_prg,
+            cmd::repair::repair_prg,
+            cmd::diff::diff_prg,
+            cmd::merge::merge_prg,
+            cmd::history::record_prg_history,
+            cmd::history::list_prg_history,
+            cmd::history::diff_prg_history,
+            cmd::history::restore_prg_history,
+            cmd::history::prune_prg_history,
+            cmd::thumbnail::get_prg_thumbnail,
+            cmd::thumbnail::cache_prg_thumbnails,
+            cmd::thumbnail::clear_prg_thumbnail_cache,
+            cmd::diagram::find_diagram_tools,
+            cmd::diagram::render_diagram,
             cmd::shell::run_command,
+            cmd::shell::spawn_command,
+            cmd::shell::write_process_stdin,
+            cmd::shell::close_process_stdin,
+            cmd::shell::kill_process,
+            cmd::shell_policy::get_shell_policy,
+            #[cfg(desktop)]
+            cmd::shell_policy::set_shell_policy,
+            cmd::shell_policy::get_shell_audit_log,
+            #[cfg(desktop)]
+            cmd::pty::open_pty,
+            #[cfg(desktop)]
+            cmd::pty::write_pty,
+            #[cfg(desktop)]
+            cmd::pty::resize_pty,
+            #[cfg(desktop)]
+            cmd::pty::close_pty,
             cmd::device::get_distribution,
             #[cfg(desktop)]
             cmd::mcp::mcp_stdio_start,
@@ -118,6 +226,18 @@ pub fn run() {
             cmd::mcp::mcp_stdio_call_tool,
             #[cfg(desktop)]
             cmd::mcp::mcp_stdio_stop,
+            #[cfg(desktop)]
+            cmd::watch::watch_path,
+            #[cfg(desktop)]
+            cmd::watch::unwatch_path,
+            #[cfg(desktop)]
+            cmd::search::update_search_index,
+            #[cfg(desktop)]
+            cmd::search::search_files,
+            #[cfg(desktop)]
+            cmd::search::get_search_index_roots,
+            #[cfg(desktop)]
+            cmd::search::clear_search_index,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
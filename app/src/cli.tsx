@@ -31,4 +31,6 @@ const cliHelpText = `
 
 https://project-graph.top/zh/features/cli
 
+不需要窗口的子命令（info、extract-text、to-json 等）见 project-graph help
+
 `;
@@ -77,6 +77,7 @@ import type { StageManager } from "@/core/stage/stageManager/StageManager";
 import { StageObject } from "@/core/stage/stageObject/abstract/StageObject";
 import { nextProjectIdAtom, store, tabsAtom } from "@/state";
 import { createDefaultMetadata, isValidMetadata, PrgMetadata } from "@/types/metadata";
+import { recordProjectHistory } from "@/utils/projectHistory";
 import { deserialize, serialize } from "@graphif/serializer";
 import { Decoder, Encoder } from "@msgpack/msgpack";
 import { BlobReader, BlobWriter, Uint8ArrayReader, Uint8ArrayWriter, ZipReader, ZipWriter } from "@zip.js/zip.js";
@@ -395,6 +396,10 @@ export class Project extends Tab {
       this.isSaving = true;
       await this.fs.write(this.uri, await this.getFileContent(options));
       this.projectState = ProjectState.Saved;
+      if (this.uri.scheme === "file") {
+        // 历史版本记录失败不影响保存本身
+        recordProjectHistory(this.uri.fsPath).catch((err) => console.warn("记录历史版本失败", err));
+      }
     } finally {
       this.isSaving = false;
     }
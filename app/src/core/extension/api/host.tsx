@@ -115,6 +115,7 @@ export function extensionHostApiFactory(extension: Extension) {
         program,
         cmdArgs: args ?? [],
         stdin,
+        caller: `extension:${extensionId}`,
       });
     },
 
@@ -10,12 +10,14 @@ import { PenStroke } from "@/core/stage/stageObject/entity/PenStroke";
 import { ReferenceBlockNode } from "@/core/stage/stageObject/entity/ReferenceBlockNode";
 import { Section } from "@/core/stage/stageObject/entity/Section";
 import { SvgNode } from "@/core/stage/stageObject/entity/SvgNode";
+import { TerminalNode } from "@/core/stage/stageObject/entity/TerminalNode";
 import { TextNode } from "@/core/stage/stageObject/entity/TextNode";
 import { UrlNode } from "@/core/stage/stageObject/entity/UrlNode";
 import { DetailsManager } from "@/core/stage/stageObject/tools/entityDetailsManager";
 import { Color, Vector } from "@graphif/data-structures";
 import { Rectangle } from "@graphif/shapes";
 import { ExtensionEntityRenderer } from "./ExtensionEntityRenderer";
+import { TerminalNodeRenderer } from "./terminalNode/TerminalNodeRenderer";
 
 /**
  * 处理节点相关的绘制
@@ -24,9 +26,11 @@ import { ExtensionEntityRenderer } from "./ExtensionEntityRenderer";
 export class EntityRenderer {
   private sectionSortedZIndex: Section[] = [];
   public extensionEntityRenderer: ExtensionEntityRenderer;
+  public terminalNodeRenderer: TerminalNodeRenderer;
 
   constructor(private readonly project: Project) {
     this.extensionEntityRenderer = new ExtensionEntityRenderer(this.project);
+    this.terminalNodeRenderer = new TerminalNodeRenderer(this.project);
   }
 
   /**
@@ -191,6 +195,8 @@ export class EntityRenderer {
       this.project.referenceBlockRenderer.render(entity);
     } else if (entity instanceof ExtensionEntity) {
       this.extensionEntityRenderer.render(entity);
+    } else if (entity instanceof TerminalNode) {
+      this.terminalNodeRenderer.render(entity);
     }
     // details右上角小按钮
     if (this.project.camera.currentScale > 0.065) {
@@ -0,0 +1,74 @@
+import { Project } from "@/core/Project";
+import { Renderer } from "@/core/render/canvas2d/renderer";
+import { Settings } from "@/core/service/Settings";
+import { TerminalNode } from "@/core/stage/stageObject/entity/TerminalNode";
+import { Color, Vector } from "@graphif/data-structures";
+
+const BACKGROUND = new Color(24, 24, 27);
+const FOREGROUND = new Color(228, 228, 231);
+const TITLE_COLOR = new Color(161, 161, 170);
+const FONT_FAMILY = "ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace";
+
+/**
+ * 终端节点的渲染器
+ * 不管舞台主题如何，终端总是深色背景，内容按 TerminalScreen 的行逐行绘制
+ */
+export class TerminalNodeRenderer {
+  constructor(private readonly project: Project) {}
+
+  render(node: TerminalNode) {
+    node.syncSize();
+    const scale = this.project.camera.currentScale;
+    const style = this.project.stageStyleManager.currentStyle;
+    if (node.isSelected) {
+      this.project.collisionBoxRenderer.render(node.collisionBox, style.CollideBoxSelected);
+    }
+    this.project.shapeRenderer.renderRect(
+      this.project.renderer.transformWorld2View(node.rectangle),
+      node.color.a === 0 ? BACKGROUND : node.color,
+      node.isFocused ? style.CollideBoxSelected : style.StageObjectBorder,
+      2 * scale,
+      Renderer.NODE_ROUNDED_RADIUS * scale,
+    );
+    // 视野缩放过小就不渲染内部文字
+    if (TerminalNode.fontSize * scale <= Settings.ignoreTextNodeTextRenderLessThanFontSize) {
+      return;
+    }
+
+    const ctx = this.project.canvas.ctx;
+    ctx.save();
+    ctx.textBaseline = "top";
+    ctx.textAlign = "left";
+    ctx.font = `${TerminalNode.fontSize * 0.85 * scale}px ${FONT_FAMILY}`;
+    ctx.fillStyle = TITLE_COLOR.toString();
+    const titleLocation = this.project.renderer.transformWorld2View(
+      node.rectangle.location.add(new Vector(TerminalNode.padding, TerminalNode.padding)),
+    );
+    const titleWidth = (node.rectangle.size.x - TerminalNode.padding * 2) * scale;
+    ctx.fillText(node.title, titleLocation.x, titleLocation.y, titleWidth);
+
+    const screenRect = node.screenRectangle;
+    const origin = this.project.renderer.transformWorld2View(screenRect.location);
+    const cellWidth = TerminalNode.cellWidth * scale;
+    const lineHeight = TerminalNode.lineHeight * scale;
+    // 超出的内容裁掉，窄一点的字体也不会画到节点外面
+    ctx.beginPath();
+    ctx.rect(origin.x, origin.y, screenRect.size.x * scale, screenRect.size.y * scale);
+    ctx.clip();
+    ctx.font = `${TerminalNode.fontSize * scale}px ${FONT_FAMILY}`;
+    ctx.fillStyle = FOREGROUND.toString();
+    node.screen.lines.forEach((line, row) => {
+      if (line) ctx.fillText(line, origin.x, origin.y + row * lineHeight);
+    });
+    if (node.isFocused) {
+      ctx.globalAlpha = 0.7;
+      ctx.fillRect(
+        origin.x + node.screen.cursorX * cellWidth,
+        origin.y + node.screen.cursorY * lineHeight,
+        cellWidth,
+        lineHeight,
+      );
+    }
+    ctx.restore();
+  }
+}
@@ -13,6 +13,7 @@ import { Vector } from "@graphif/data-structures";
 import { deserialize } from "@graphif/serializer";
 import { Rectangle } from "@graphif/shapes";
 import { Decoder } from "@msgpack/msgpack";
+import { invoke } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import { exists, readFile, writeFile } from "@tauri-apps/plugin-fs";
 import { open as shellOpen } from "@tauri-apps/plugin-shell";
@@ -315,6 +316,9 @@ export async function onOpenFile(uri?: URI, source: string = "unknown"): Promise
             Telemetry.event("打开文件失败", {
               error: String(e),
             });
+            if (uri.fsPath.endsWith(".prg")) {
+              void offerPrgRepair(uri.fsPath);
+            }
             return `读取时发生错误，已发送错误报告，可在群内联系开发者\n${String(e)}`;
           },
         },
@@ -329,6 +333,49 @@ export async function onOpenFile(uri?: URI, source: string = "unknown"): Promise
   return tab as any;
 }
 
+type PrgVerifyReport = {
+  ok: boolean;
+  directoryError?: string;
+  entries: { name: string; state: "ok" | "corrupt" | "missing"; size: number; error?: string }[];
+};
+
+type PrgRepairReport = {
+  output: string;
+  recovered: string[];
+  lost: string[];
+  stageObjects: number;
+  stagePartial: boolean;
+};
+
+/**
+ * 打开 .prg 失败后检查文件是否损坏，损坏时询问是否把能用的部分修复到新文件中
+ */
+async function offerPrgRepair(fsPath: string) {
+  const report = await invoke<PrgVerifyReport>("verify_prg", { path: fsPath }).catch(() => undefined);
+  if (!report || report.ok) return;
+  const broken = report.entries.filter((entry) => entry.state !== "ok").map((entry) => entry.error ?? entry.name);
+  if (report.directoryError) broken.unshift(`ZIP 目录损坏: ${report.directoryError}`);
+  const confirmed = await Dialog.confirm(
+    "文件已损坏",
+    `${broken.slice(0, 20).join("\n")}\n\n是否尝试把仍然完好的内容恢复到一个新文件中？原文件不会被修改。`,
+  );
+  if (!confirmed) return;
+  try {
+    const result = await invoke<PrgRepairReport>("repair_prg", { path: fsPath });
+    const detail = [
+      `${result.stageObjects} 个舞台对象${result.stagePartial ? "（部分恢复）" : ""}`,
+      result.lost.length > 0 ? `丢弃 ${result.lost.length} 个损坏的部分` : null,
+    ]
+      .filter(Boolean)
+      .join("，");
+    if (await Dialog.confirm("修复完成", `已保存到 ${result.output}\n${detail}\n\n是否立即打开？`)) {
+      await onOpenFile(URI.file(result.output), "修复损坏的文件");
+    }
+  } catch (e) {
+    toast.error(`修复失败: ${String(e)}`);
+  }
+}
+
 /**
  * 将旧版 JSON（1.x 系列）或旧版 msgpack 格式文件升级为新版 .prg 文件，
  * 生成在同一目录下，完成后弹窗提示并询问是否立即打开。
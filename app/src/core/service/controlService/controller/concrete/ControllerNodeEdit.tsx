@@ -5,6 +5,7 @@ import { TextNode } from "@/core/stage/stageObject/entity/TextNode";
 import { UrlNode } from "@/core/stage/stageObject/entity/UrlNode";
 import { ReferenceBlockNode } from "@/core/stage/stageObject/entity/ReferenceBlockNode";
 import { LatexNode } from "@/core/stage/stageObject/entity/LatexNode";
+import { TerminalNode } from "@/core/stage/stageObject/entity/TerminalNode";
 import { isMac } from "@/utils/platform";
 import { Vector } from "@graphif/data-structures";
 import { open } from "@tauri-apps/plugin-shell";
@@ -56,6 +57,9 @@ export class ControllerNodeEditClass extends ControllerClass {
     } else if (clickedEntity instanceof ReferenceBlockNode) {
       // 双击引用块跳转到源头
       clickedEntity.goToSource();
+    } else if (clickedEntity instanceof TerminalNode) {
+      // 双击终端开始输入，shell 没有运行时先启动
+      clickedEntity.focus();
     }
   };
 
@@ -28,6 +28,8 @@ import EditUrlNodeLinkWindow from "@/sub/EditUrlNodeLinkWindow";
 import FindWindow from "@/sub/FindWindow";
 // import KeyboardRecentFilesWindow from "@/sub/KeyboardRecentFilesWindow";
 import { LatexNode } from "@/core/stage/stageObject/entity/LatexNode";
+import { TerminalNode } from "@/core/stage/stageObject/entity/TerminalNode";
+import { CollisionBox } from "@/core/stage/stageObject/collisionBox/collisionBox";
 import AIToolsWindow from "@/sub/AIToolsWindow";
 import AIWindow from "@/sub/AIWindow";
 import AttachmentsWindow from "@/sub/AttachmentsWindow";
@@ -60,6 +62,7 @@ import { Color, Vector } from "@graphif/data-structures";
 import { serialize } from "@graphif/serializer";
 import { Rectangle } from "@graphif/shapes";
 import { Encoder } from "@msgpack/msgpack";
+import { invoke } from "@tauri-apps/api/core";
 import { appCacheDir, appDataDir, dataDir, join, tempDir } from "@tauri-apps/api/path";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { exists, mkdir, writeFile } from "@tauri-apps/plugin-fs";
@@ -942,6 +945,21 @@ export const allKeyBinds: KeyBindItem[] = [
       );
     },
   },
+  {
+    id: "createTerminalNodeFromMouseLocation",
+    defaultKey: "t e r m",
+    icon: Terminal,
+    when: whenHasProject,
+    onPress: (project) => {
+      // 只创建节点，双击节点后才启动 shell
+      const location = project!.renderer.transformView2World(MouseLocation.vector());
+      const node = new TerminalNode(project!, {
+        collisionBox: new CollisionBox([new Rectangle(location, new Vector(TerminalNode.width, TerminalNode.height))]),
+      });
+      project!.stageManager.add(node);
+      project!.historyManager.recordStep();
+    },
+  },
   {
     id: "createConnectPointFromMouseLocation",
     defaultKey: "S-.",
@@ -3406,7 +3424,8 @@ export const allKeyBinds: KeyBindItem[] = [
     icon: FolderTree,
     when: whenHasProject,
     onPress: async (project) => {
-      const path = await open({ title: "打开文件夹", directory: true, multiple: false, filters: [] });
+      // 选中的文件夹同时加入 cmd::fs 允许访问的范围
+      const path = await invoke<string | null>("add_fs_scope");
       if (!path) return;
       project!.generateFromFolder.generateFromFolder(path);
     },
@@ -3417,8 +3436,8 @@ export const allKeyBinds: KeyBindItem[] = [
     icon: FolderTree,
     when: whenHasProject,
     onPress: async (project) => {
-      const path = await open({ title: "打开文件夹", directory: true, multiple: false, filters: [] });
-      if (!path || typeof path !== "string") return;
+      const path = await invoke<string | null>("add_fs_scope");
+      if (!path) return;
       project!.generateFromFolder.generateTreeFromFolder(path);
     },
   },
@@ -154,7 +154,8 @@ export type FolderEntry = {
 
 function readFolderStructure(path: string): Promise<FolderEntry> {
   // 不可能是isWeb的情况了
-  return invoke("read_folder_structure", { path });
+  // 跳过隐藏文件和 .gitignore 中的内容，避免在 node_modules、.git 这类目录上卡死
+  return invoke("read_folder_structure", { path, options: { respectGitignore: true, includeHidden: false } });
 }
 
 /**
@@ -81,6 +81,28 @@ describe("MCP configuration documents", () => {
     });
   });
 
+  it("keeps the restart policy of stdio servers only", () => {
+    const restart = { mode: "onFailure", maxRestarts: 3, initialBackoffMs: 1000 } as const;
+    const definitions = parseMCPConfigDocument(
+      JSON.stringify({ mcpServers: { local: { command: "node", args: ["server.js"], restart } } }),
+    );
+    expect(definitions).toEqual([
+      { name: "local", transport: { type: "stdio", command: "node", args: ["server.js"], restart } },
+    ]);
+    expect(JSON.parse(serializeMCPConfigDocument(definitions)).mcpServers.local.restart).toEqual(restart);
+
+    expect(() =>
+      parseMCPConfigDocument(
+        JSON.stringify({ mcpServers: { docs: { url: "https://example.com/mcp", restart: { mode: "always" } } } }),
+      ),
+    ).toThrow(/restart/);
+    expect(() =>
+      parseMCPConfigDocument(
+        JSON.stringify({ mcpServers: { local: { command: "node", restart: { mode: "sometimes" } } } }),
+      ),
+    ).toThrow(/restart/);
+  });
+
   it("creates stable fingerprints regardless of environment key order", () => {
     const left: AIMCPServerDefinition = {
       name: "local",
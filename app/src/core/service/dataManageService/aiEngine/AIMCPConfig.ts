@@ -11,12 +11,21 @@ export type AIMCPToolDescriptor = {
   inputSchema: unknown;
 };
 
+/** When the Rust supervisor restarts a stdio server that exited; omitted fields use the Rust defaults. */
+export type AIMCPRestartPolicy = {
+  mode: "never" | "onFailure" | "always";
+  maxRestarts?: number;
+  initialBackoffMs?: number;
+  maxBackoffMs?: number;
+};
+
 export type AIMCPStdioTransport = {
   type: "stdio";
   command: string;
   args: string[];
   cwd?: string;
   env?: Record<string, string>;
+  restart?: AIMCPRestartPolicy;
 };
 
 export type AIMCPHttpTransport = {
@@ -46,6 +55,14 @@ export type AIMCPSnapshot = {
 export type AIMCPServerConfig = AIMCPServerDefinition & AIMCPServerRuntimeState;
 
 const stringRecordSchema = z.record(z.string(), z.string());
+const restartPolicySchema = z
+  .object({
+    mode: z.enum(["never", "onFailure", "always"]),
+    maxRestarts: z.number().int().nonnegative().optional(),
+    initialBackoffMs: z.number().int().nonnegative().optional(),
+    maxBackoffMs: z.number().int().nonnegative().optional(),
+  })
+  .strict();
 const sourceServerSchema = z
   .object({
     type: z.enum(["stdio", "http", "streamable-http", "sse"]).optional(),
@@ -53,6 +70,7 @@ const sourceServerSchema = z
     args: z.array(z.string()).optional(),
     cwd: z.string().optional(),
     env: stringRecordSchema.optional(),
+    restart: restartPolicySchema.optional(),
     url: z.string().optional(),
     headers: stringRecordSchema.optional(),
     note: z.string().optional(),
@@ -83,6 +101,7 @@ const normalizedDefinitionSchema = z.object({
       args: z.array(z.string()),
       cwd: z.string().min(1).optional(),
       env: stringRecordSchema.optional(),
+      restart: restartPolicySchema.optional(),
     }),
     z.object({
       type: z.literal("streamable-http"),
@@ -195,6 +214,7 @@ function normalizeSourceServer(serverName: string, value: unknown): AIMCPServerD
         args: source.args ?? [],
         ...(cwd ? { cwd } : {}),
         ...(source.env ? { env: source.env } : {}),
+        ...(source.restart ? { restart: source.restart } : {}),
       },
     };
   }
@@ -203,9 +223,10 @@ function normalizeSourceServer(serverName: string, value: unknown): AIMCPServerD
     source.command !== undefined ||
     source.args !== undefined ||
     source.cwd !== undefined ||
-    source.env !== undefined
+    source.env !== undefined ||
+    source.restart !== undefined
   ) {
-    throw new Error(`MCP HTTP server ${serverName} must not define command, args, cwd, or env`);
+    throw new Error(`MCP HTTP server ${serverName} must not define command, args, cwd, env, or restart`);
   }
   if (!source.url?.trim()) throw new Error(`MCP HTTP server ${serverName} URL is required`);
   return {
@@ -271,6 +292,7 @@ function definitionToSource(definition: AIMCPServerDefinition): Record<string, u
       args: definition.transport.args,
       ...(definition.transport.cwd ? { cwd: definition.transport.cwd } : {}),
       ...(definition.transport.env ? { env: sortedRecord(definition.transport.env) } : {}),
+      ...(definition.transport.restart ? { restart: definition.transport.restart } : {}),
     };
   }
   return {
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { jsonSchema, type ToolSet } from "ai";
 import { z } from "zod/v4";
 import type { AIMCPServerConfig, AIMCPToolDescriptor } from "./AIMCPConfig";
@@ -7,6 +8,22 @@ export type AIMCPStdioConfig = AIMCPServerConfig & {
   transport: Extract<AIMCPServerConfig["transport"], { type: "stdio" }>;
 };
 
+/** Payload of the `mcp-server-status` event sent by the Rust supervisor when a stdio server exits or restarts. */
+export type AIMCPStdioServerStatus = {
+  serverName: string;
+  state: "running" | "restarting" | "exited" | "failed";
+  exit: {
+    code: number | null;
+    signal: number | null;
+    signalName: string | null;
+    timedOut: boolean;
+  } | null;
+  error: string | null;
+  stderrTail: string[];
+  restartCount: number;
+  retryInMs: number | null;
+};
+
 const mcpToolSchema = z.object({
   name: z.string().min(1),
   title: z.string().optional(),
@@ -25,6 +42,7 @@ function toStdioCommandConfig(config: AIMCPStdioConfig) {
     args: config.transport.args,
     cwd: config.transport.cwd,
     env: config.transport.env ?? {},
+    restart: config.transport.restart,
   };
 }
 
@@ -82,6 +100,12 @@ export async function stopStdioMCPServer(serverName: string): Promise<void> {
   }
 }
 
+export function listenStdioMCPServerStatus(
+  handler: (status: AIMCPStdioServerStatus) => void,
+): Promise<UnlistenFn> {
+  return listen<AIMCPStdioServerStatus>("mcp-server-status", (event) => handler(event.payload));
+}
+
 export function createStdioMCPTools(
   config: AIMCPStdioConfig,
   descriptors: AIMCPToolDescriptor[],
@@ -4,7 +4,7 @@ import { SvgNode } from "@/core/stage/stageObject/entity/SvgNode";
 import { TextNode } from "@/core/stage/stageObject/entity/TextNode";
 import { Vector } from "@graphif/data-structures";
 import { Rectangle } from "@graphif/shapes";
-import { readFile, stat } from "@tauri-apps/plugin-fs";
+import { stat } from "@tauri-apps/plugin-fs";
 import { toast } from "sonner";
 import { URI } from "vscode-uri";
 import { onOpenFile } from "../../GlobalMenu";
@@ -13,6 +13,7 @@ import { DetailsManager } from "@/core/stage/stageObject/tools/entityDetailsMana
 import { Settings } from "@/core/service/Settings";
 import { prepareImageBlobForImport } from "../imageUtils";
 import { createImageNodeFromBlob } from "../imageNodeFactory";
+import { readFileChunked } from "@/utils/chunkedFile";
 
 /**
  * 处理文件拖拽到舞台的引擎
@@ -162,9 +163,9 @@ export namespace DragFileIntoStageEngine {
     sourceMime: string,
     imageIndex: number = 0,
   ): Promise<void> {
-    const fileData = await readFile(filePath);
+    const { data: fileData } = await readFileChunked(filePath);
     const prepared = await prepareImageBlobForImport(
-      new Blob([new Uint8Array(fileData) as BlobPart], { type: sourceMime }),
+      new Blob([fileData as BlobPart], { type: sourceMime }),
     );
 
     // 第1张在视野中心，之后每张左移50px、下移50px（y轴向下）
@@ -185,7 +186,7 @@ export namespace DragFileIntoStageEngine {
   }
 
   export async function handleDropTxt(project: Project, filePath: string) {
-    const fileData = await readFile(filePath);
+    const { data: fileData } = await readFileChunked(filePath);
     const content = new TextDecoder().decode(fileData);
     const textNode = new TextNode(project, {
       text: content,
@@ -197,7 +198,7 @@ export namespace DragFileIntoStageEngine {
   }
 
   export async function handleDropSvg(project: Project, filePath: string) {
-    const fileData = await readFile(filePath);
+    const { data: fileData } = await readFileChunked(filePath);
     const content = new TextDecoder().decode(fileData);
     const svg = new DOMParser().parseFromString(content, "image/svg+xml");
     const item = new XMLSerializer().serializeToString(svg.documentElement);
@@ -11,6 +11,7 @@ import { LatexNode } from "@/core/stage/stageObject/entity/LatexNode";
 import { PenStroke } from "@/core/stage/stageObject/entity/PenStroke";
 import { Section } from "@/core/stage/stageObject/entity/Section";
 import { SvgNode } from "@/core/stage/stageObject/entity/SvgNode";
+import { TerminalNode } from "@/core/stage/stageObject/entity/TerminalNode";
 import { TextNode } from "@/core/stage/stageObject/entity/TextNode";
 import { UrlNode } from "@/core/stage/stageObject/entity/UrlNode";
 import { Color, ProgressNumber } from "@graphif/data-structures";
@@ -43,6 +44,7 @@ export class DeleteManager {
     this.registerHandler(LatexNode, this.deleteLatexNode.bind(this));
     this.registerHandler(ReferenceBlockNode, this.deleteReferenceBlockNode.bind(this));
     this.registerHandler(ExtensionEntity, this.deleteExtensionEntity.bind(this));
+    this.registerHandler(TerminalNode, this.deleteTerminalNode.bind(this));
     this.registerHandler(MultiTargetUndirectedEdge, this.deleteMultiTargetUndirectedEdge.bind(this));
   }
 
@@ -131,6 +133,14 @@ export class DeleteManager {
       this.deleteEntityAfterClearAssociation(entity);
     }
   }
+  private deleteTerminalNode(entity: TerminalNode) {
+    if (this.project.stageManager.getEntities().includes(entity)) {
+      entity.stop();
+      this.project.stageManager.delete(entity);
+      // 删除所有相关的边
+      this.deleteEntityAfterClearAssociation(entity);
+    }
+  }
   private deleteUrlNode(entity: UrlNode) {
     if (this.project.stageManager.getUrlNodes().includes(entity)) {
       this.project.stageManager.delete(entity);
@@ -0,0 +1,221 @@
+import { Project } from "@/core/Project";
+import { ConnectableEntity } from "@/core/stage/stageObject/abstract/ConnectableEntity";
+import { CollisionBox } from "@/core/stage/stageObject/collisionBox/collisionBox";
+import { PathString } from "@/utils/pathString";
+import { isWeb } from "@/utils/platform";
+import { openPty, PtySession } from "@/utils/pty";
+import { TerminalScreen, terminalKeySequence } from "@/utils/terminalScreen";
+import { Color, Vector } from "@graphif/data-structures";
+import { id, passExtraAtArg1, passObject, serializable } from "@graphif/serializer";
+import { Rectangle } from "@graphif/shapes";
+import { toast } from "sonner";
+
+/**
+ * 终端节点
+ * 双击后在伪终端中启动 shell，之后键盘输入直接发送给 shell，点击节点以外的地方退出输入
+ * 总是启动用户的默认 shell，工作目录为工程文件所在的目录
+ * shell 和工作目录都不保存到文件中，打开别人的文件时不会运行文件里指定的程序
+ * 会话本身不保存，重新打开文件后需要双击重新启动
+ */
+@passExtraAtArg1
+@passObject
+export class TerminalNode extends ConnectableEntity {
+  @id
+  @serializable
+  uuid: string;
+  @serializable
+  color: Color;
+  @serializable
+  public collisionBox: CollisionBox;
+
+  static width = 640;
+  static height = 400;
+  /** 上方标题栏的高度 */
+  static titleHeight = 28;
+  static padding = 8;
+  static fontSize = 14;
+  static lineHeight = 18;
+  /** 等宽字体中一个字符的宽度 */
+  static cellWidth = TerminalNode.fontSize * 0.6;
+
+  screen: TerminalScreen;
+  session: PtySession | null = null;
+  /** 正在启动 shell */
+  isStarting = false;
+  /** 上次 shell 退出的方式，用于在标题栏显示 */
+  exitDescription = "";
+  /** 键盘输入是否发送给这个终端 */
+  isFocused = false;
+  private input: HTMLTextAreaElement | null = null;
+
+  isHiddenBySectionCollapse: boolean = false;
+
+  constructor(
+    protected readonly project: Project,
+    {
+      uuid = crypto.randomUUID() as string,
+      details = [],
+      collisionBox = new CollisionBox([
+        new Rectangle(Vector.getZero(), new Vector(TerminalNode.width, TerminalNode.height)),
+      ]),
+      color = Color.Transparent,
+    },
+  ) {
+    super();
+    this.uuid = uuid;
+    this.details = details;
+    this.color = color;
+    this.collisionBox = collisionBox;
+    this.screen = new TerminalScreen(...this.gridSize());
+  }
+
+  get geometryCenter(): Vector {
+    return this.collisionBox.getRectangle().center;
+  }
+  /**
+   * 只读，获取节点的矩形
+   * 若要修改节点的矩形，请使用 moveTo等 方法
+   */
+  public get rectangle(): Rectangle {
+    return this.collisionBox.shapes[0] as Rectangle;
+  }
+  /** 显示终端内容的区域 */
+  get screenRectangle(): Rectangle {
+    const rect = this.rectangle;
+    return new Rectangle(
+      rect.location.add(new Vector(TerminalNode.padding, TerminalNode.titleHeight + TerminalNode.padding)),
+      rect.size.subtract(new Vector(TerminalNode.padding * 2, TerminalNode.titleHeight + TerminalNode.padding * 2)),
+    );
+  }
+  get isRunning() {
+    return this.session !== null;
+  }
+  get title() {
+    const shell = this.session?.shell ?? "shell";
+    if (this.isStarting) return `${shell}（正在启动）`;
+    if (this.session) return shell;
+    return this.exitDescription ? `${shell}（${this.exitDescription}，双击重新启动）` : `${shell}（双击启动）`;
+  }
+
+  move(delta: Vector): void {
+    const newRectangle = this.rectangle.clone();
+    newRectangle.location = newRectangle.location.add(delta);
+    this.collisionBox.shapes[0] = newRectangle;
+    this.updateFatherSectionByMove();
+    this.updateOtherEntityLocationByMove();
+  }
+  moveTo(location: Vector): void {
+    const newRectangle = this.rectangle.clone();
+    newRectangle.location = location.clone();
+    this.collisionBox.shapes[0] = newRectangle;
+    this.updateFatherSectionByMove();
+  }
+
+  /** 按节点大小计算终端的列数和行数 */
+  private gridSize(): [number, number] {
+    const size = this.screenRectangle.size;
+    return [
+      Math.max(2, Math.floor(size.x / TerminalNode.cellWidth)),
+      Math.max(1, Math.floor(size.y / TerminalNode.lineHeight)),
+    ];
+  }
+
+  /** 节点大小改变后同步终端的行列数，每次绘制前调用 */
+  syncSize() {
+    const [cols, rows] = this.gridSize();
+    if (cols === this.screen.cols && rows === this.screen.rows) return;
+    this.screen.resize(cols, rows);
+    this.session?.resize(cols, rows).catch(() => {});
+  }
+
+  async start() {
+    if (this.session || this.isStarting) return;
+    if (isWeb) {
+      toast.error("网页版不支持终端");
+      return;
+    }
+    this.isStarting = true;
+    this.exitDescription = "";
+    const [cols, rows] = this.gridSize();
+    this.screen = new TerminalScreen(cols, rows);
+    const cwd = this.project.uri.scheme === "file" ? PathString.dirPath(this.project.uri.fsPath) : "";
+    try {
+      const session = await openPty({ cols, rows }, (bytes) => this.screen.write(bytes), {
+        options: cwd ? { cwd } : undefined,
+        caller: "terminal-node",
+      });
+      this.session = session;
+      session.exited.then((exit) => {
+        if (this.session !== session) return;
+        this.session = null;
+        this.exitDescription =
+          exit.signalName !== null ? `被 ${exit.signalName} 结束` : `已退出，退出码 ${exit.code ?? "未知"}`;
+        this.blur();
+      });
+    } catch (e) {
+      toast.error(`无法启动终端：${e}`);
+    } finally {
+      this.isStarting = false;
+    }
+  }
+
+  /** 结束 shell，节点被删除时调用 */
+  stop() {
+    this.blur();
+    this.session?.close().catch(() => {});
+  }
+
+  /**
+   * 开始接收键盘输入
+   * 用一个看不见的输入框接收按键，这样输入法、粘贴都能正常使用，快捷键也不会被触发
+   */
+  async focus() {
+    await this.start();
+    if (!this.session || this.isFocused) return;
+    const input = document.createElement("textarea");
+    input.setAttribute("autocapitalize", "off");
+    input.setAttribute("autocomplete", "off");
+    input.spellcheck = false;
+    Object.assign(input.style, {
+      position: "fixed",
+      left: "0",
+      top: "0",
+      width: "1px",
+      height: "1px",
+      opacity: "0",
+      pointerEvents: "none",
+    });
+    input.addEventListener("keydown", (event) => {
+      if (event.isComposing) return;
+      const sequence = terminalKeySequence(event);
+      if (sequence === null) return;
+      event.preventDefault();
+      this.session?.write(sequence).catch(() => {});
+    });
+    input.addEventListener("input", (event) => {
+      if ((event as InputEvent).isComposing) return;
+      this.sendInputValue(input);
+    });
+    input.addEventListener("compositionend", () => this.sendInputValue(input));
+    input.addEventListener("blur", () => this.blur());
+    document.body.appendChild(input);
+    this.input = input;
+    this.isFocused = true;
+    input.focus();
+  }
+
+  private sendInputValue(input: HTMLTextAreaElement) {
+    if (!input.value) return;
+    // 粘贴的多行文本中的换行按回车发送
+    this.session?.write(input.value.replace(/\r?\n/g, "\r")).catch(() => {});
+    input.value = "";
+  }
+
+  blur() {
+    if (!this.isFocused) return;
+    this.isFocused = false;
+    const input = this.input;
+    this.input = null;
+    input?.remove();
+  }
+}
@@ -12,11 +12,10 @@ import { activeResourceTabAtom } from "@/state";
 import { cn } from "@/utils/cn";
 import { PathString } from "@/utils/pathString";
 import { isMac } from "@/utils/platform";
-import { readCachedPrgThumbnail, readPrgThumbnailBlob, refreshPrgThumbnailCache } from "@/utils/readPrgThumbnail";
+import { readCachedPrgThumbnail, readPrgThumbnailBlob, refreshPrgThumbnailCaches } from "@/utils/readPrgThumbnail";
 import { Vector } from "@graphif/data-structures";
 import { Rectangle } from "@graphif/shapes";
 import { invoke } from "@tauri-apps/api/core";
-import { open } from "@tauri-apps/plugin-dialog";
 import { useAtom } from "jotai";
 import {
   DoorClosed,
@@ -177,11 +176,8 @@ export default function RecentFilesWindow({ tabId }: { tabId: string }) {
   // 选择文件夹并导入PRG文件
   const importPrgFilesFromFolder = async () => {
     try {
-      // 打开文件夹选择对话框
-      const folderPath = await open({
-        directory: true,
-        multiple: false,
-      });
+      // 打开文件夹选择对话框，选中的文件夹同时加入 cmd::fs 允许访问的范围
+      const folderPath = await invoke<string | null>("add_fs_scope");
 
       if (!folderPath) return;
 
@@ -316,18 +312,16 @@ export default function RecentFilesWindow({ tabId }: { tabId: string }) {
 
       const uniqueFsPaths = Array.from(new Set(recentFiles.map((f) => f.uri.fsPath)));
       let updatedCount = 0;
-      let removedCount = 0;
       let missingCount = 0;
       const errors: string[] = [];
 
-      for (const fsPath of uniqueFsPaths) {
-        try {
-          const result = await refreshPrgThumbnailCache(fsPath);
-          if (result === "updated") updatedCount++;
-          if (result === "removed") removedCount++;
-          if (result === "missing") missingCount++;
-        } catch (e) {
-          errors.push(`${PathString.getFileNameFromPath(fsPath)}: ${String(e)}`);
+      for (const result of await refreshPrgThumbnailCaches(uniqueFsPaths)) {
+        if (result.error) {
+          errors.push(`${PathString.getFileNameFromPath(result.path)}: ${result.error}`);
+        } else if (result.status === "missing") {
+          missingCount++;
+        } else {
+          updatedCount++;
         }
       }
 
@@ -340,7 +334,6 @@ export default function RecentFilesWindow({ tabId }: { tabId: string }) {
       } else {
         const detail = [
           `成功 ${updatedCount} 个`,
-          removedCount > 0 ? `移除 ${removedCount} 个` : null,
           missingCount > 0 ? `无缩略图 ${missingCount} 个` : null,
         ]
           .filter(Boolean)
@@ -736,6 +736,7 @@ export const shortcutKeysGroups: ShortcutKeysGroup[] = [
     keys: [
       "createTextNodeFromCameraLocation",
       "createTextNodeFromMouseLocation",
+      "createTerminalNodeFromMouseLocation",
       "toggleTextNodeSizeMode",
       "splitTextNodes",
       "mergeTextNodes",
@@ -13,6 +13,7 @@ import SettingsWindow from "@/sub/SettingsWindow";
 import { cn } from "@/utils/cn";
 import { Path } from "@/utils/path";
 import { isMac } from "@/utils/platform";
+import { refreshPrgThumbnailCaches } from "@/utils/readPrgThumbnail";
 import { Vector } from "@graphif/data-structures";
 import { Rectangle } from "@graphif/shapes";
 import { getVersion } from "@tauri-apps/api/app";
@@ -163,8 +164,13 @@ export default function WelcomeWindow({ tabId }: { tabId: string }) {
   async function refresh() {
     setIsLoading(true);
     await RecentFileManager.sortTimeRecentFiles();
-    setRecentFiles(await RecentFileManager.getRecentFiles());
+    const files = await RecentFileManager.getRecentFiles();
+    setRecentFiles(files);
     setIsLoading(false);
+    // 在后台并行预先生成缩略图缓存，打开最近文件窗口时可以直接显示
+    if (!isMac) {
+      void refreshPrgThumbnailCaches(files.map((file) => file.uri.fsPath), false).catch(() => {});
+    }
   }
 
   async function closeSelf() {
@@ -1,8 +1,14 @@
 export namespace Base64 {
   export function encode(str: string) {
-    return btoa(Array.from(new TextEncoder().encode(str), (byte) => String.fromCodePoint(byte)).join(""));
+    return encodeBytes(new TextEncoder().encode(str));
   }
   export function decode(b64: string) {
-    return new TextDecoder().decode(Uint8Array.from(atob(b64), (m) => m.codePointAt(0)!));
+    return new TextDecoder().decode(decodeBytes(b64));
+  }
+  export function encodeBytes(bytes: Uint8Array) {
+    return btoa(Array.from(bytes, (byte) => String.fromCodePoint(byte)).join(""));
+  }
+  export function decodeBytes(b64: string) {
+    return Uint8Array.from(atob(b64), (m) => m.codePointAt(0)!);
   }
 }
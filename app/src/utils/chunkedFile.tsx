@@ -0,0 +1,82 @@
+import { invoke, type InvokeOptions } from "@tauri-apps/api/core";
+
+/**
+ * 分块读写大文件，避免把整个文件编码成 base64 字符串
+ * 对应 Rust 侧的 cmd::transfer
+ * 进度通过 `fs-transfer-progress` 事件发送，也可以直接传入 onProgress
+ */
+
+const CHUNK_SIZE = 4 * 1024 * 1024;
+
+type TransferStart = { transferId: number; size: number | null };
+type TransferResult = { size: number; checksum: string };
+
+declare global {
+  interface Window {
+    /** CEF runtime 提供的二进制 invoke 通道 */
+    __kb_raw_invoke__?: (cmd: string, payload: Uint8Array, options?: InvokeOptions) => Promise<unknown>;
+  }
+}
+
+function invokeRaw(cmd: string, payload: Uint8Array, headers: Record<string, string>) {
+  if (window.__kb_raw_invoke__) {
+    return window.__kb_raw_invoke__(cmd, payload, { headers });
+  }
+  return invoke(cmd, payload, { headers });
+}
+
+function toBytes(data: ArrayBuffer | number[]): Uint8Array {
+  return data instanceof ArrayBuffer ? new Uint8Array(data) : Uint8Array.from(data);
+}
+
+export async function readFileChunked(
+  path: string,
+  onProgress?: (transferred: number, total: number) => void,
+): Promise<{ data: Uint8Array; checksum: string }> {
+  const { transferId, size } = await invoke<TransferStart>("begin_file_read", { path });
+  const total = size ?? 0;
+  const data = new Uint8Array(total);
+  try {
+    let offset = 0;
+    while (offset < total) {
+      const chunk = toBytes(
+        await invoke<ArrayBuffer | number[]>("read_file_chunk", { transferId, offset, length: CHUNK_SIZE }),
+      );
+      if (chunk.length === 0) break;
+      data.set(chunk, offset);
+      offset += chunk.length;
+      onProgress?.(offset, total);
+    }
+    const { checksum } = await invoke<TransferResult>("finish_file_transfer", { transferId });
+    return { data: data.subarray(0, offset), checksum };
+  } catch (e) {
+    await invoke("abort_file_transfer", { transferId }).catch(() => {});
+    throw e;
+  }
+}
+
+/**
+ * 写入完成后才会替换目标文件
+ * 传入 checksum（SHA-256）时会在 Rust 侧校验
+ */
+export async function writeFileChunked(
+  path: string,
+  data: Uint8Array,
+  options?: { checksum?: string; onProgress?: (transferred: number, total: number) => void },
+): Promise<TransferResult> {
+  const { transferId } = await invoke<TransferStart>("begin_file_write", { path, size: data.length });
+  try {
+    for (let offset = 0; offset < data.length; offset += CHUNK_SIZE) {
+      const chunk = data.subarray(offset, offset + CHUNK_SIZE);
+      await invokeRaw("write_file_chunk", chunk, {
+        "transfer-id": String(transferId),
+        offset: String(offset),
+      });
+      options?.onProgress?.(offset + chunk.length, data.length);
+    }
+    return await invoke<TransferResult>("finish_file_transfer", { transferId, checksum: options?.checksum });
+  } catch (e) {
+    await invoke("abort_file_transfer", { transferId }).catch(() => {});
+    throw e;
+  }
+}
@@ -0,0 +1,42 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * 用本机安装的 Graphviz（dot）、PlantUML、mermaid-cli（mmdc）渲染图表
+ * 对应 Rust 侧的 cmd::diagram，在临时目录中渲染，结果按源码哈希缓存在应用缓存目录中
+ */
+
+export type DiagramLanguage = "dot" | "plantUml" | "mermaid";
+export type DiagramFormat = "svg" | "png";
+
+export type DiagramTool = {
+  language: DiagramLanguage;
+  program: string;
+  /** 没有安装时为 null */
+  path: string | null;
+};
+
+/** 查找本机安装了哪些渲染工具 */
+export async function findDiagramTools() {
+  return invoke<DiagramTool[]>("find_diagram_tools");
+}
+
+/**
+ * 渲染图表，例如把 MermaidExporter 导出的文本交给 mmdc 渲染
+ * @param options.refresh 为 true 时忽略缓存重新渲染
+ */
+export async function renderDiagram(
+  language: DiagramLanguage,
+  source: string,
+  options: { format?: DiagramFormat; timeoutMs?: number; refresh?: boolean } = {},
+): Promise<Blob> {
+  const format = options.format ?? "svg";
+  const data = await invoke<ArrayBuffer | number[]>("render_diagram", {
+    language,
+    source,
+    format,
+    timeoutMs: options.timeoutMs,
+    refresh: options.refresh,
+  });
+  const bytes = data instanceof ArrayBuffer ? new Uint8Array(data) : Uint8Array.from(data);
+  return new Blob([bytes], { type: format === "svg" ? "image/svg+xml" : "image/png" });
+}
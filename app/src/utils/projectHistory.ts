@@ -0,0 +1,87 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * 工程的本地历史版本
+ * 由 Rust 侧的 cmd::history 在每次保存后记录快照，附件等内容按哈希去重存放在应用数据目录中，
+ * 旧快照按保留策略自动清理（默认一天内每小时、一个月内每天各保留一份）
+ */
+
+export type HistorySnapshot = {
+  id: string;
+  /** 毫秒时间戳 */
+  time: number;
+  size: number;
+  attachments: number;
+};
+
+export type RetentionPolicy = {
+  keepRecent?: number;
+  hourlyHours?: number;
+  dailyDays?: number;
+  weeklyWeeks?: number;
+  utcOffsetMinutes?: number;
+};
+
+export type HistoryPruneResult = {
+  removedSnapshots: number;
+  removedObjects: number;
+  freedBytes: number;
+};
+
+/** 按本地时区划分“每天” */
+function withLocalOffset(policy: RetentionPolicy = {}): RetentionPolicy {
+  return { utcOffsetMinutes: -new Date().getTimezoneOffset(), ...policy };
+}
+
+/**
+ * 记录文件当前的内容，内容与最新的快照相同时不会重复记录
+ * @param fsPath PRG 文件的文件系统路径
+ */
+export async function recordProjectHistory(fsPath: string, policy?: RetentionPolicy) {
+  return invoke<{ snapshot: HistorySnapshot; created: boolean }>("record_prg_history", {
+    path: fsPath,
+    policy: withLocalOffset(policy),
+  });
+}
+
+/** 历史版本，从新到旧排列 */
+export async function listProjectHistory(fsPath: string) {
+  return invoke<HistorySnapshot[]>("list_prg_history", { path: fsPath });
+}
+
+/**
+ * 对比两个历史版本
+ * @param to 为空时与文件当前的内容对比
+ */
+export async function diffProjectHistory(fsPath: string, from: string, to?: string) {
+  return invoke<{ changes: ({ kind: string } & Record<string, unknown>)[] }>("diff_prg_history", {
+    path: fsPath,
+    from,
+    to: to ?? null,
+  });
+}
+
+/**
+ * 还原历史版本
+ * @param output 为空时覆盖原文件，覆盖前会先记录当前内容，还原后也可以再回到还原前的版本
+ * @returns 写入的文件路径
+ */
+export async function restoreProjectHistory(fsPath: string, id: string, output?: string) {
+  return invoke<string>("restore_prg_history", {
+    path: fsPath,
+    id,
+    output: output ?? null,
+    policy: withLocalOffset(),
+  });
+}
+
+/**
+ * 按保留策略清理历史版本
+ * @param fsPath 为空时清理所有工程
+ */
+export async function pruneProjectHistory(fsPath?: string, policy?: RetentionPolicy) {
+  return invoke<HistoryPruneResult>("prune_prg_history", {
+    path: fsPath ?? null,
+    policy: withLocalOffset(policy),
+  });
+}
@@ -0,0 +1,57 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { Base64 } from "./base64";
+import type { CommandOptions, ProcessExit } from "./shell";
+
+/**
+ * 伪终端会话
+ * 对应 Rust 侧的 cmd::pty，与 runCommand 一样启动前经过 cmd::shell_policy 的权限检查
+ * 输出是终端的原始字节，包含 ANSI 转义序列，需要交给 TerminalScreen 之类的解析器处理
+ */
+
+type PtyEvent = { event: "output"; data: string } | { event: "exit"; data: ProcessExit };
+
+export type PtySession = Awaited<ReturnType<typeof openPty>>;
+
+/**
+ * 在伪终端中启动 shell
+ * @param shell 为空时使用用户的默认 shell
+ * @param options 只有 cwd、env、clearEnv 生效
+ * @returns 会话句柄，exited 在 shell 结束后 resolve
+ */
+export async function openPty(
+  size: { cols: number; rows: number },
+  onOutput: (bytes: Uint8Array) => void,
+  { shell, args, options, caller }: { shell?: string; args?: string[]; options?: CommandOptions; caller?: string } = {},
+) {
+  let resolveExit: (exit: ProcessExit) => void;
+  const exited = new Promise<ProcessExit>((resolve) => (resolveExit = resolve));
+  const onEvent = new Channel<PtyEvent>();
+  onEvent.onmessage = (message) => {
+    switch (message.event) {
+      case "output":
+        onOutput(Base64.decodeBytes(message.data));
+        break;
+      case "exit":
+        resolveExit(message.data);
+        break;
+    }
+  };
+  const info = await invoke<{ id: number; pid: number | null; shell: string }>("open_pty", {
+    shell,
+    shellArgs: args,
+    cols: size.cols,
+    rows: size.rows,
+    options,
+    onEvent,
+    caller,
+  });
+  const { id } = info;
+  return {
+    ...info,
+    exited,
+    /** 写入按键或粘贴的文本 */
+    write: (data: string) => invoke<void>("write_pty", { id, data }),
+    resize: (cols: number, rows: number) => invoke<void>("resize_pty", { id, cols, rows }),
+    close: () => invoke<void>("close_pty", { id }),
+  };
+}
@@ -1,38 +1,24 @@
-import { appCacheDir, join } from "@tauri-apps/api/path";
-import { exists, mkdir, readFile, remove, writeFile } from "@tauri-apps/plugin-fs";
-import { BlobWriter, Uint8ArrayReader, ZipReader } from "@zip.js/zip.js";
+import { invoke } from "@tauri-apps/api/core";
 
 /**
- * 工具函数，将输入字符串转换为sha256哈希值
- * @param input
- * @returns
+ * prg 缩略图
+ * 由 Rust 侧的 cmd::thumbnail 读取 ZIP 中央目录并只解压 thumbnail.png，
+ * 缩放后按（路径、修改时间、大小）缓存在应用缓存目录中
  */
-async function sha256Hex(input: string): Promise<string> {
-  const data = new TextEncoder().encode(input);
-  const hashBuffer = await crypto.subtle.digest("SHA-256", data);
-  return Array.from(new Uint8Array(hashBuffer))
-    .map((b) => b.toString(16).padStart(2, "0"))
-    .join("");
-}
 
-/**
- * 获取缩略图整体缓存文件夹
- * @returns
- */
-async function getPrgThumbnailCacheDir() {
-  return await join(await appCacheDir(), "prg-thumbnail-cache-v1");
-}
+/** 缩略图的最大边长 */
+const THUMBNAIL_SIZE = 256;
 
-/**
- * 获取一个prg文件的缩略图缓存文件
- * 绝对路径 ==SHA256=> xxx   dir/xxx
- * @param fsPath
- * @returns
- */
-async function getPrgThumbnailCachePath(fsPath: string) {
-  const dir = await getPrgThumbnailCacheDir();
-  const key = await sha256Hex(fsPath);
-  return await join(dir, `${key}.png`);
+type ThumbnailCacheResult = {
+  path: string;
+  status: "cached" | "updated" | "missing" | null;
+  error: string | null;
+};
+
+function toBlob(data: ArrayBuffer | number[]): Blob | undefined {
+  const bytes = data instanceof ArrayBuffer ? new Uint8Array(data) : Uint8Array.from(data);
+  if (bytes.length === 0) return undefined;
+  return new Blob([bytes], { type: "image/png" });
 }
 
 /**
@@ -41,75 +27,51 @@ async function getPrgThumbnailCachePath(fsPath: string) {
  * @returns thumbnail PNG 的 Blob，如果不存在则返回 undefined
  */
 export async function readPrgThumbnailBlob(fsPath: string): Promise<Blob | undefined> {
-  const fileContent = await readFile(fsPath);
-  const reader = new ZipReader(new Uint8ArrayReader(fileContent));
-  const entries = await reader.getEntries();
-
-  for (const entry of entries) {
-    if (entry.filename === "thumbnail.png" && "getData" in entry && typeof entry.getData === "function") {
-      const blob = await entry.getData(new BlobWriter("image/png"));
-      await reader.close();
-      return blob;
-    }
-  }
-
-  await reader.close();
-  return undefined;
+  return toBlob(await invoke<ArrayBuffer | number[]>("get_prg_thumbnail", { path: fsPath, size: THUMBNAIL_SIZE }));
 }
 
 /**
  * 根据prg文件绝对路径字符串，获取这个prg的缩略图缓存图片
+ * 不会打开prg文件本身，文件无法访问时使用最近一次的缓存
  * @param fsPath
  * @returns
  */
 export async function readCachedPrgThumbnail(fsPath: string): Promise<Blob | undefined> {
-  const cachePath = await getPrgThumbnailCachePath(fsPath);
-  if (!(await exists(cachePath))) return undefined;
-  const content = await readFile(cachePath);
-  return new Blob([content], { type: "image/png" });
+  return toBlob(
+    await invoke<ArrayBuffer | number[]>("get_prg_thumbnail", {
+      path: fsPath,
+      size: THUMBNAIL_SIZE,
+      cacheOnly: true,
+    }),
+  );
 }
 
 /**
  * 传入一个prg文件路径，生成缓存缩略图文件 到对应缓存位置。
- * 如果图片已经存在，则不生成缩略图，什么也不做
+ * 如果缓存已经是最新的，则什么也不做
  * @param fsPath prg文件路径
  * @returns
  */
 export async function ensurePrgThumbnailCached(fsPath: string): Promise<void> {
-  const cacheDir = await getPrgThumbnailCacheDir();
-  if (!(await exists(cacheDir))) {
-    await mkdir(cacheDir, { recursive: true });
-  }
-
-  const prgThumbnailCachedPath = await getPrgThumbnailCachePath(fsPath);
-  if (await exists(prgThumbnailCachedPath)) return;
-
-  const blob = await readPrgThumbnailBlob(fsPath);
-  if (!blob) return;
-
-  const buffer = await blob.arrayBuffer();
-  await writeFile(prgThumbnailCachedPath, new Uint8Array(buffer));
+  await refreshPrgThumbnailCaches([fsPath], false);
 }
 
-export async function refreshPrgThumbnailCache(fsPath: string): Promise<"updated" | "removed" | "missing"> {
-  const cacheDir = await getPrgThumbnailCacheDir();
-  if (!(await exists(cacheDir))) {
-    await mkdir(cacheDir, { recursive: true });
-  }
-
-  const cachePath = await getPrgThumbnailCachePath(fsPath);
-  const blob = await readPrgThumbnailBlob(fsPath);
-
-  if (!blob) {
-    const hadCache = await exists(cachePath);
-    if (hadCache) {
-      await remove(cachePath);
-    }
-    return hadCache ? "removed" : "missing";
-  }
-
-  const buffer = await blob.arrayBuffer();
-  await writeFile(cachePath, new Uint8Array(buffer));
+export async function refreshPrgThumbnailCache(fsPath: string): Promise<"updated" | "missing"> {
+  const [result] = await refreshPrgThumbnailCaches([fsPath]);
+  if (result.error) throw new Error(result.error);
+  return result.status === "missing" ? "missing" : "updated";
+}
 
-  return "updated";
+/**
+ * 并行为多个prg文件生成缩略图缓存
+ * @param fsPaths prg文件路径
+ * @param refresh 为 true 时忽略已有缓存，重新生成
+ * @returns 每个文件的结果，失败时 error 不为空
+ */
+export async function refreshPrgThumbnailCaches(fsPaths: string[], refresh = true): Promise<ThumbnailCacheResult[]> {
+  return await invoke<ThumbnailCacheResult[]>("cache_prg_thumbnails", {
+    paths: fsPaths,
+    size: THUMBNAIL_SIZE,
+    refresh,
+  });
 }
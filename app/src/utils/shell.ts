@@ -0,0 +1,122 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { Base64 } from "./base64";
+
+/**
+ * 运行外部命令
+ * 对应 Rust 侧的 cmd::shell，子进程运行在单独的进程组中，超时或被结束时整个进程组一起结束
+ * 运行前经过 cmd::shell_policy 的权限检查，caller 会被记录在审计日志中，例如 "extension:xxx"、"ai-tool"
+ */
+
+export type CommandOptions = {
+  cwd?: string;
+  env?: Record<string, string>;
+  /** 为 true 时不继承应用的环境变量 */
+  clearEnv?: boolean;
+  timeoutMs?: number;
+  /** runCommand 中 stdout、stderr 各自最多保留的字节数 */
+  maxOutputBytes?: number;
+  /** runCommand 返回的 stdout 的编码，二进制输出使用 "base64" */
+  stdoutEncoding?: "text" | "base64";
+};
+
+export type ProcessExit = {
+  code: number | null;
+  signal: number | null;
+  signalName: string | null;
+  timedOut: boolean;
+};
+
+export type RunCommandResult = ProcessExit & {
+  stdout: string;
+  stderr: string;
+  truncated: boolean;
+};
+
+export type ProcessEvent =
+  | { event: "stdout"; data: string }
+  | { event: "stderr"; data: string }
+  | { event: "exit"; data: ProcessExit };
+
+/**
+ * 运行命令直到结束，返回全部输出
+ */
+export async function runCommand(
+  program: string,
+  args: string[] = [],
+  stdin?: string,
+  options?: CommandOptions,
+  caller?: string,
+) {
+  return invoke<RunCommandResult>("run_command", { program, cmdArgs: args, stdin, options, caller });
+}
+
+/**
+ * 以二进制方式运行命令，例如通过 ImageMagick、Graphviz 转换图片
+ * @param stdin 原始字节，在单独的线程中写入子进程
+ * @returns stdout 为原始字节，stderr 仍为文本
+ */
+export async function runCommandBinary(
+  program: string,
+  args: string[] = [],
+  stdin?: Uint8Array,
+  options?: CommandOptions,
+  caller?: string,
+) {
+  const result = await invoke<RunCommandResult>("run_command", {
+    program,
+    cmdArgs: args,
+    stdinBase64: stdin && Base64.encodeBytes(stdin),
+    options: { ...options, stdoutEncoding: "base64" },
+    caller,
+  });
+  return { ...result, stdout: Base64.decodeBytes(result.stdout) };
+}
+
+/**
+ * 在后台运行命令，stdout / stderr 按行回调
+ * @returns 进程句柄，exited 在进程结束后 resolve
+ */
+export async function spawnCommand(
+  program: string,
+  args: string[],
+  handlers: { onStdout?: (line: string) => void; onStderr?: (line: string) => void },
+  options?: CommandOptions,
+  caller?: string,
+) {
+  let resolveExit: (exit: ProcessExit) => void;
+  const exited = new Promise<ProcessExit>((resolve) => (resolveExit = resolve));
+  const onEvent = new Channel<ProcessEvent>();
+  onEvent.onmessage = (message) => {
+    switch (message.event) {
+      case "stdout":
+        handlers.onStdout?.(message.data);
+        break;
+      case "stderr":
+        handlers.onStderr?.(message.data);
+        break;
+      case "exit":
+        resolveExit(message.data);
+        break;
+    }
+  };
+  const { id, pid } = await invoke<{ id: number; pid: number }>("spawn_command", {
+    program,
+    cmdArgs: args,
+    options,
+    onEvent,
+    caller,
+  });
+  return {
+    id,
+    pid,
+    exited,
+    /** 写入 stdin，Uint8Array 按原始字节写入 */
+    write: (data: string | Uint8Array) =>
+      invoke<void>(
+        "write_process_stdin",
+        typeof data === "string" ? { id, data } : { id, dataBase64: Base64.encodeBytes(data) },
+      ),
+    closeStdin: () => invoke<void>("close_process_stdin", { id }),
+    kill: () => invoke<void>("kill_process", { id }),
+  };
+}
@@ -0,0 +1,42 @@
+import { describe, expect, it } from "vitest";
+import { TerminalScreen, terminalKeySequence } from "./terminalScreen";
+
+describe("TerminalScreen", () => {
+  it("prints, wraps and scrolls lines into the scrollback", () => {
+    const screen = new TerminalScreen(5, 2);
+    screen.write("ab\r\ncdefgh\r\nij");
+    expect(screen.lines).toEqual(["h", "ij"]);
+    expect(screen.scrollback).toEqual(["ab", "cdefg"]);
+    expect([screen.cursorX, screen.cursorY]).toEqual([2, 1]);
+  });
+
+  it("handles cursor movement and erase sequences split across writes", () => {
+    const screen = new TerminalScreen(10, 3);
+    screen.write("hello\x1b[");
+    screen.write("2DXY\x1b[K\r\n\x1b]0;title\x07你好");
+    expect(screen.lines).toEqual(["helXY", "你好", ""]);
+
+    screen.write("\x1b[2J\x1b[3;2Hz\x1b[31mred\x1b[0m");
+    expect(screen.lines).toEqual(["", "", " zred"]);
+  });
+
+  it("decodes utf-8 split across chunks and restores the main screen", () => {
+    const screen = new TerminalScreen(10, 2);
+    const bytes = new TextEncoder().encode("终端");
+    screen.write(bytes.slice(0, 4));
+    screen.write(bytes.slice(4));
+    screen.write("\x1b[?1049h\x1b[Hvim");
+    expect(screen.lines).toEqual(["vim", ""]);
+    screen.write("\x1b[?1049l");
+    expect(screen.lines).toEqual(["终端", ""]);
+  });
+
+  it("maps control keys to byte sequences", () => {
+    const key = (key: string, mods: Partial<Record<"ctrlKey" | "altKey" | "metaKey", boolean>> = {}) =>
+      terminalKeySequence({ key, ctrlKey: false, altKey: false, metaKey: false, ...mods });
+    expect(key("c", { ctrlKey: true })).toBe("\x03");
+    expect(key("ArrowUp")).toBe("\x1b[A");
+    expect(key("b", { altKey: true })).toBe("\x1bb");
+    expect(key("a")).toBeNull();
+  });
+});
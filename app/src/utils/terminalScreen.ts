@@ -0,0 +1,380 @@
+/**
+ * 极简的终端屏幕模型，把伪终端的输出解析成若干行文本，供画布上的终端节点绘制
+ *
+ * 只处理常用的控制字符和 CSI 序列（光标移动、清屏、清行、插入删除行、备用屏幕），
+ * 颜色等 SGR 属性直接忽略，OSC（窗口标题等）也被丢弃
+ */
+export class TerminalScreen {
+  /** 滚出屏幕后最多保留的行数 */
+  static readonly SCROLLBACK_LIMIT = 1000;
+
+  /** 每个格子一个字符，宽字符后面的格子为空字符串 */
+  private cells: string[][];
+  private mainCells: string[][] | null = null;
+  readonly scrollback: string[] = [];
+  cursorX = 0;
+  cursorY = 0;
+  private savedCursor = { x: 0, y: 0 };
+  private decoder = new TextDecoder();
+  /** 上次输出末尾不完整的转义序列 */
+  private pending = "";
+
+  constructor(
+    public cols: number,
+    public rows: number,
+  ) {
+    this.cells = Array.from({ length: rows }, () => this.blankLine());
+  }
+
+  /** 是否在备用屏幕中（vim、less 等全屏程序） */
+  get isAlternate() {
+    return this.mainCells !== null;
+  }
+
+  /** 屏幕上的每一行，去掉了行尾空白 */
+  get lines(): string[] {
+    return this.cells.map((line) => line.join("").trimEnd());
+  }
+
+  write(bytes: Uint8Array | string) {
+    const text = typeof bytes === "string" ? bytes : this.decoder.decode(bytes, { stream: true });
+    this.feed(this.pending + text);
+  }
+
+  resize(cols: number, rows: number) {
+    cols = Math.max(1, cols);
+    rows = Math.max(1, rows);
+    if (cols === this.cols && rows === this.rows) return;
+    const fit = (line: string[]) =>
+      line.length >= cols ? line.slice(0, cols) : [...line, ...Array(cols - line.length).fill(" ")];
+    this.cells = this.cells.map(fit);
+    this.mainCells = this.mainCells?.map(fit) ?? null;
+    // 行数变少时，光标以上的行滚出屏幕
+    while (this.cells.length > rows) {
+      if (this.cursorY > 0) {
+        this.pushScrollback(this.cells.shift()!);
+        this.cursorY--;
+      } else {
+        this.cells.pop();
+      }
+    }
+    while (this.cells.length < rows) {
+      this.cells.push(Array(cols).fill(" "));
+    }
+    this.cols = cols;
+    this.rows = rows;
+    this.cursorX = Math.min(this.cursorX, cols - 1);
+    this.cursorY = Math.min(this.cursorY, rows - 1);
+  }
+
+  private blankLine() {
+    return Array<string>(this.cols).fill(" ");
+  }
+
+  private pushScrollback(line: string[]) {
+    if (this.isAlternate) return;
+    this.scrollback.push(line.join("").trimEnd());
+    if (this.scrollback.length > TerminalScreen.SCROLLBACK_LIMIT) {
+      this.scrollback.shift();
+    }
+  }
+
+  private feed(text: string) {
+    this.pending = "";
+    const chars = Array.from(text);
+    let i = 0;
+    while (i < chars.length) {
+      const ch = chars[i];
+      if (ch === "\x1b") {
+        const consumed = this.escape(chars, i);
+        if (consumed === 0) {
+          // 转义序列还没有收完整，等下一次输出
+          this.pending = chars.slice(i).join("");
+          return;
+        }
+        i += consumed;
+        continue;
+      }
+      switch (ch) {
+        case "\r":
+          this.cursorX = 0;
+          break;
+        case "\n":
+        case "\v":
+        case "\f":
+          this.lineFeed();
+          break;
+        case "\b":
+          this.cursorX = Math.max(0, this.cursorX - 1);
+          break;
+        case "\t":
+          this.cursorX = Math.min(this.cols - 1, (Math.floor(this.cursorX / 8) + 1) * 8);
+          break;
+        default:
+          if (ch >= " " && ch !== "\x7f") this.print(ch);
+      }
+      i++;
+    }
+  }
+
+  private print(ch: string) {
+    const width = isWide(ch) ? 2 : 1;
+    if (this.cursorX + width > this.cols) {
+      this.cursorX = 0;
+      this.lineFeed();
+    }
+    const line = this.cells[this.cursorY];
+    line[this.cursorX] = ch;
+    if (width === 2 && this.cursorX + 1 < this.cols) {
+      line[this.cursorX + 1] = "";
+    }
+    this.cursorX += width;
+  }
+
+  private lineFeed() {
+    if (this.cursorY === this.rows - 1) {
+      this.scrollUp(1);
+    } else {
+      this.cursorY++;
+    }
+  }
+
+  private scrollUp(count: number) {
+    for (let n = 0; n < count; n++) {
+      this.pushScrollback(this.cells.shift()!);
+      this.cells.push(this.blankLine());
+    }
+  }
+
+  private scrollDown(count: number) {
+    for (let n = 0; n < count; n++) {
+      this.cells.pop();
+      this.cells.unshift(this.blankLine());
+    }
+  }
+
+  /**
+   * 处理从 start 开始的转义序列
+   * @returns 消耗的字符数，序列不完整时返回 0
+   */
+  private escape(chars: string[], start: number): number {
+    const kind = chars[start + 1];
+    if (kind === undefined) return 0;
+    switch (kind) {
+      case "[": {
+        let end = start + 2;
+        while (end < chars.length && !/[\x40-\x7e]/.test(chars[end])) end++;
+        if (end >= chars.length) return 0;
+        this.csi(chars.slice(start + 2, end).join(""), chars[end]);
+        return end - start + 1;
+      }
+      case "]": {
+        // OSC 以 BEL 或 ESC \ 结束
+        for (let end = start + 2; end < chars.length; end++) {
+          if (chars[end] === "\x07") return end - start + 1;
+          if (chars[end] === "\x1b") {
+            if (end + 1 >= chars.length) return 0;
+            if (chars[end + 1] === "\\") return end - start + 2;
+          }
+        }
+        return 0;
+      }
+      case "(":
+      case ")":
+        return start + 2 < chars.length ? 3 : 0;
+      case "7":
+        this.savedCursor = { x: this.cursorX, y: this.cursorY };
+        return 2;
+      case "8":
+        this.cursorX = this.savedCursor.x;
+        this.cursorY = this.savedCursor.y;
+        return 2;
+      case "M":
+        if (this.cursorY === 0) this.scrollDown(1);
+        else this.cursorY--;
+        return 2;
+      case "c":
+        this.cells = Array.from({ length: this.rows }, () => this.blankLine());
+        this.cursorX = this.cursorY = 0;
+        return 2;
+      default:
+        return 2;
+    }
+  }
+
+  private csi(params: string, final: string) {
+    if (params.startsWith("?")) {
+      const modes = params.slice(1).split(";");
+      if (modes.some((mode) => mode === "1049" || mode === "1047" || mode === "47")) {
+        if (final === "h") this.enterAlternate();
+        if (final === "l") this.leaveAlternate();
+      }
+      return;
+    }
+    const args = params.split(";").map((arg) => parseInt(arg, 10));
+    const arg = (index: number, fallback: number) => (Number.isFinite(args[index]) ? args[index] : fallback);
+    const n = Math.max(1, arg(0, 1));
+    const line = this.cells[this.cursorY];
+    switch (final) {
+      case "A":
+        this.cursorY = Math.max(0, this.cursorY - n);
+        break;
+      case "B":
+        this.cursorY = Math.min(this.rows - 1, this.cursorY + n);
+        break;
+      case "C":
+        this.cursorX = Math.min(this.cols - 1, this.cursorX + n);
+        break;
+      case "D":
+        this.cursorX = Math.max(0, this.cursorX - n);
+        break;
+      case "E":
+        this.cursorX = 0;
+        this.cursorY = Math.min(this.rows - 1, this.cursorY + n);
+        break;
+      case "F":
+        this.cursorX = 0;
+        this.cursorY = Math.max(0, this.cursorY - n);
+        break;
+      case "G":
+        this.cursorX = Math.min(this.cols - 1, n - 1);
+        break;
+      case "d":
+        this.cursorY = Math.min(this.rows - 1, n - 1);
+        break;
+      case "H":
+      case "f":
+        this.cursorY = Math.min(this.rows - 1, Math.max(1, arg(0, 1)) - 1);
+        this.cursorX = Math.min(this.cols - 1, Math.max(1, arg(1, 1)) - 1);
+        break;
+      case "J": {
+        const mode = arg(0, 0);
+        if (mode === 2 || mode === 3) {
+          this.cells = Array.from({ length: this.rows }, () => this.blankLine());
+        } else {
+          const [from, to] = mode === 1 ? [0, this.cursorY] : [this.cursorY, this.rows - 1];
+          for (let y = from; y <= to; y++) {
+            if (y === this.cursorY) this.eraseInLine(mode);
+            else this.cells[y] = this.blankLine();
+          }
+        }
+        break;
+      }
+      case "K":
+        this.eraseInLine(arg(0, 0));
+        break;
+      case "L":
+        for (let k = 0; k < n; k++) {
+          this.cells.splice(this.cursorY, 0, this.blankLine());
+          this.cells.pop();
+        }
+        break;
+      case "M":
+        for (let k = 0; k < n; k++) {
+          this.cells.splice(this.cursorY, 1);
+          this.cells.push(this.blankLine());
+        }
+        break;
+      case "P":
+        line.splice(this.cursorX, n);
+        while (line.length < this.cols) line.push(" ");
+        break;
+      case "@":
+        line.splice(this.cursorX, 0, ...Array(n).fill(" "));
+        line.length = this.cols;
+        break;
+      case "X":
+        for (let x = this.cursorX; x < Math.min(this.cols, this.cursorX + n); x++) line[x] = " ";
+        break;
+      case "S":
+        this.scrollUp(n);
+        break;
+      case "T":
+        this.scrollDown(n);
+        break;
+      case "s":
+        this.savedCursor = { x: this.cursorX, y: this.cursorY };
+        break;
+      case "u":
+        this.cursorX = this.savedCursor.x;
+        this.cursorY = this.savedCursor.y;
+        break;
+      // m（颜色）、r（滚动区域）等不影响文本内容，忽略
+    }
+  }
+
+  /** 0：光标到行尾，1：行首到光标，2：整行 */
+  private eraseInLine(mode: number) {
+    const line = this.cells[this.cursorY];
+    const [from, to] = mode === 1 ? [0, this.cursorX] : mode === 2 ? [0, this.cols - 1] : [this.cursorX, this.cols - 1];
+    for (let x = from; x <= Math.min(to, this.cols - 1); x++) line[x] = " ";
+  }
+
+  private enterAlternate() {
+    if (this.isAlternate) return;
+    this.mainCells = this.cells;
+    this.savedCursor = { x: this.cursorX, y: this.cursorY };
+    this.cells = Array.from({ length: this.rows }, () => this.blankLine());
+  }
+
+  private leaveAlternate() {
+    if (!this.mainCells) return;
+    this.cells = this.mainCells;
+    this.mainCells = null;
+    this.cursorX = this.savedCursor.x;
+    this.cursorY = this.savedCursor.y;
+  }
+}
+
+/** 东亚宽字符和 emoji 占两个格子 */
+function isWide(ch: string) {
+  const code = ch.codePointAt(0)!;
+  return (
+    (code >= 0x1100 && code <= 0x115f) ||
+    (code >= 0x2e80 && code <= 0xa4cf) ||
+    (code >= 0xac00 && code <= 0xd7a3) ||
+    (code >= 0xf900 && code <= 0xfaff) ||
+    (code >= 0xfe30 && code <= 0xfe4f) ||
+    (code >= 0xff00 && code <= 0xff60) ||
+    (code >= 0xffe0 && code <= 0xffe6) ||
+    (code >= 0x1f300 && code <= 0x1faff) ||
+    (code >= 0x20000 && code <= 0x3fffd)
+  );
+}
+
+/**
+ * 把按键转换为发送给终端的字节序列，不需要发送时返回 null
+ * 普通字符由输入框的 input 事件处理（兼容输入法），这里只处理控制键和组合键
+ */
+export function terminalKeySequence(event: Pick<KeyboardEvent, "key" | "ctrlKey" | "altKey" | "metaKey">) {
+  const special: Record<string, string> = {
+    Enter: "\r",
+    Backspace: "\x7f",
+    Tab: "\t",
+    Escape: "\x1b",
+    ArrowUp: "\x1b[A",
+    ArrowDown: "\x1b[B",
+    ArrowRight: "\x1b[C",
+    ArrowLeft: "\x1b[D",
+    Home: "\x1b[H",
+    End: "\x1b[F",
+    Insert: "\x1b[2~",
+    Delete: "\x1b[3~",
+    PageUp: "\x1b[5~",
+    PageDown: "\x1b[6~",
+  };
+  if (event.metaKey) return null;
+  if (event.key in special) {
+    return (event.altKey ? "\x1b" : "") + special[event.key];
+  }
+  if (event.key.length !== 1) return null;
+  if (event.ctrlKey) {
+    const code = event.key.toUpperCase().charCodeAt(0);
+    // Ctrl+@ 到 Ctrl+_ 对应 0x00 - 0x1f
+    if (code >= 0x40 && code <= 0x5f) return (event.altKey ? "\x1b" : "") + String.fromCharCode(code & 0x1f);
+    if (event.key === " ") return "\x00";
+    return null;
+  }
+  if (event.altKey) return "\x1b" + event.key;
+  return null;
+}